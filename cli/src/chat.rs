@@ -0,0 +1,110 @@
+//! One turn of a chat: the same request, metadata and summary handling as
+//! the PWA, so saved chats open in either.
+
+use std::time::Instant;
+
+use shared::{ApiError, ChatMessage, ChatRequest, ChatSession, MessageMeta, Role, TitleRequest};
+
+use crate::client::ApiClient;
+
+/// Sends `text` and records the exchange on `chat`, returning the reply.
+///
+/// On failure the message is taken back off the chat so turns still
+/// alternate, and the caller can offer to send it again.
+pub fn send(client: &ApiClient, chat: &mut ChatSession, text: &str) -> Result<String, ApiError> {
+    chat.messages
+        .push(ChatMessage::new(Role::User, text).with_meta(MessageMeta::now()));
+    chat.touch();
+
+    let started = Instant::now();
+    let response = match client.chat(&chat_request(chat)) {
+        Ok(response) => response,
+        Err(error) => {
+            chat.messages.pop();
+            return Err(error);
+        }
+    };
+
+    let meta = MessageMeta {
+        animal: Some(chat.animal),
+        intelligence: Some(chat.intelligence),
+        model: response.model,
+        tokens_used: response.tokens_used,
+        latency_ms: Some(started.elapsed().as_millis().min(u32::MAX as u128) as u32),
+        ..MessageMeta::now()
+    };
+    chat.messages
+        .push(ChatMessage::new(Role::Assistant, &response.response).with_meta(meta));
+    if let Some(summary) = response.summary {
+        chat.summary = Some(summary);
+    }
+    chat.touch();
+
+    if chat.wants_title() {
+        name_chat(client, chat);
+    }
+    Ok(response.response)
+}
+
+/// The request for a reply to the last message of the active branch.
+pub fn chat_request(chat: &ChatSession) -> ChatRequest {
+    let path = chat.messages.messages();
+    let (history, summary) = chat.request_history(path.len().saturating_sub(1));
+    ChatRequest {
+        message: path.last().map(|m| m.content.clone()).unwrap_or_default(),
+        animal: chat.animal,
+        intelligence: chat.intelligence,
+        language: chat.language,
+        history,
+        candidates: None,
+        summary,
+        persona: None,
+    }
+}
+
+/// Titles the chat after its first exchange; a failure keeps the default title.
+fn name_chat(client: &ApiClient, chat: &mut ChatSession) {
+    let path = chat.messages.messages();
+    let request = TitleRequest {
+        message: path[0].content.clone(),
+        reply: path[1].content.clone(),
+        animal: chat.animal,
+        intelligence: chat.intelligence,
+        language: chat.language,
+        persona: None,
+    };
+    if let Ok(title) = client.title(&request) {
+        chat.title = title;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{AnimalType, ConversationSummary, IntelligenceLevel, Language};
+
+    #[test]
+    fn request_sends_the_turns_after_the_cached_summary() {
+        let mut chat = ChatSession::new(AnimalType::OCTOPUS, IntelligenceLevel::High, Language::En);
+        for (role, text) in [
+            (Role::User, "Hi"),
+            (Role::Assistant, "Blub"),
+            (Role::User, "Ink?"),
+            (Role::Assistant, "Ink."),
+            (Role::User, "Why?"),
+        ] {
+            chat.messages.push(ChatMessage::new(role, text));
+        }
+        chat.summary = Some(ConversationSummary {
+            text: "They said hi.".to_string(),
+            covers: 2,
+        });
+
+        let request = chat_request(&chat);
+        assert_eq!(request.message, "Why?");
+        assert_eq!(request.language, Language::En);
+        let history: Vec<&str> = request.history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(history, ["Ink?", "Ink."]);
+        assert_eq!(request.summary.map(|s| s.covers), Some(2));
+    }
+}
@@ -0,0 +1,67 @@
+//! Blocking HTTP client for the `/api/*` contract.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use shared::{ApiError, ApiErrorCode, ChatRequest, ChatResponse, TitleRequest, TitleResponse};
+
+pub struct ApiClient {
+    http: reqwest::blocking::Client,
+    base_url: String,
+    /// Anonymous id for per-client rate limits, like the PWA's.
+    token: String,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::blocking::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    pub fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, ApiError> {
+        self.post("chat", request)
+    }
+
+    pub fn title(&self, request: &TitleRequest) -> Result<String, ApiError> {
+        self.post::<TitleResponse>("title", request).map(|r| r.title)
+    }
+
+    fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, ApiError> {
+        let url = format!("{}/{path}", self.base_url);
+        let response = self
+            .http
+            .post(&url)
+            .header("X-Client-Token", &self.token)
+            .json(body)
+            .send()
+            .map_err(|e| {
+                ApiError::new(ApiErrorCode::Offline, format!("Could not reach {url}"))
+                    .with_details(json!({ "error": e.to_string() }))
+            })?;
+
+        let status = response.status();
+        let bytes = response.bytes().map_err(|e| {
+            ApiError::new(ApiErrorCode::UpstreamUnavailable, format!("Failed to read response: {e}"))
+        })?;
+        if status.is_success() {
+            serde_json::from_slice(&bytes).map_err(|e| {
+                ApiError::new(ApiErrorCode::UpstreamUnavailable, format!("Unexpected response: {e}"))
+            })
+        } else {
+            // Proxies and the like may answer without our error body.
+            Err(serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+                ApiError::new(
+                    ApiErrorCode::UpstreamUnavailable,
+                    format!("{url} answered {status}"),
+                )
+            }))
+        }
+    }
+}
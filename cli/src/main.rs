@@ -0,0 +1,162 @@
+//! Terminal client for the chat API: an interactive REPL, or a single
+//! message for scripts. Chats are saved in the PWA's `ChatSession` JSON
+//! format, one file per chat.
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use clap::Parser;
+use shared::{AnimalType, ChatSession, IntelligenceLevel, Language};
+
+mod chat;
+mod client;
+mod render;
+mod repl;
+mod store;
+
+use client::ApiClient;
+use store::SessionStore;
+
+/// Where `cargo run -p api-server` and `wrangler dev` listen.
+const DEFAULT_API_URL: &str = "http://127.0.0.1:8787/api";
+
+/// Talk to the animals from the terminal.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Message to send once, printing the reply and exiting (`-` reads stdin).
+    /// Starts the interactive chat when omitted.
+    message: Option<String>,
+
+    /// Animal to talk to, by id (octopus, cat, owl...).
+    #[arg(short, long, default_value = "octopus", value_parser = parse_animal)]
+    animal: AnimalType,
+
+    /// high, medium or low.
+    #[arg(short, long, default_value = "high", value_parser = parse_intelligence)]
+    intelligence: IntelligenceLevel,
+
+    /// es or en.
+    #[arg(short, long, default_value = "es", value_parser = parse_language)]
+    language: Language,
+
+    /// Base URL of the API.
+    #[arg(long, env = "ANIMAL_API_URL", default_value = DEFAULT_API_URL)]
+    api: String,
+
+    /// Continue a saved chat, by id or the start of one.
+    #[arg(short, long)]
+    session: Option<String>,
+
+    /// List the saved chats and exit.
+    #[arg(long)]
+    list: bool,
+
+    /// Print replies as plain text instead of rendered Markdown.
+    #[arg(long)]
+    raw: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let store = SessionStore::open_default()?;
+
+    if args.list {
+        render::print_sessions(&store.list()?);
+        return Ok(());
+    }
+
+    let client = ApiClient::new(&args.api);
+    let chat = match &args.session {
+        Some(id) => Some(store.load(id)?),
+        None => None,
+    };
+
+    match args.message {
+        Some(message) => {
+            let message = if message == "-" {
+                let mut input = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut input)
+                    .map_err(|e| format!("Failed to read stdin: {e}"))?;
+                input
+            } else {
+                message
+            };
+            // One-off messages are only saved when continuing a chat.
+            let mut chat = match chat {
+                Some(chat) => chat,
+                None => ChatSession::new(args.animal, args.intelligence, args.language),
+            };
+            let reply = chat::send(&client, &mut chat, message.trim())
+                .map_err(|e| render::error_text(&e))?;
+            render::print_reply(&reply, args.raw);
+            if args.session.is_some() {
+                store.save(&chat)?;
+            }
+            Ok(())
+        }
+        None => {
+            let chat = chat.unwrap_or_else(|| {
+                ChatSession::new(args.animal, args.intelligence, args.language)
+            });
+            repl::run(&client, &store, chat, args.raw)
+        }
+    }
+}
+
+pub fn parse_animal(id: &str) -> Result<AnimalType, String> {
+    AnimalType::from_id(id.trim()).ok_or_else(|| {
+        let ids: Vec<&str> = AnimalType::all().iter().map(|a| a.id()).collect();
+        format!("unknown animal '{id}' (expected one of: {})", ids.join(", "))
+    })
+}
+
+pub fn parse_intelligence(level: &str) -> Result<IntelligenceLevel, String> {
+    IntelligenceLevel::from_id(level.trim()).ok_or_else(|| {
+        let ids: Vec<&str> = IntelligenceLevel::all().iter().map(|l| l.id()).collect();
+        format!("unknown intelligence '{level}' (expected one of: {})", ids.join(", "))
+    })
+}
+
+pub fn parse_language(lang: &str) -> Result<Language, String> {
+    Language::from_code(lang.trim()).ok_or_else(|| {
+        let codes: Vec<&str> = Language::all().iter().map(|l| l.code()).collect();
+        format!("unknown language '{lang}' (expected one of: {})", codes.join(", "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn arguments_are_consistent() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn settings_parse_from_their_ids() {
+        assert_eq!(parse_animal("octopus").unwrap().id(), "octopus");
+        assert!(parse_animal("unicorn").unwrap_err().contains("octopus"));
+        assert_eq!(parse_intelligence("low"), Ok(IntelligenceLevel::Low));
+        assert!(parse_intelligence("genius").is_err());
+        assert_eq!(parse_language("en"), Ok(Language::En));
+
+        let args = Args::parse_from(["animal", "-a", "cat", "-i", "medium", "-l", "en", "Hi"]);
+        assert_eq!(args.animal.id(), "cat");
+        assert_eq!(args.intelligence, IntelligenceLevel::Medium);
+        assert_eq!(args.message.as_deref(), Some("Hi"));
+    }
+}
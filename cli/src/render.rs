@@ -0,0 +1,70 @@
+//! Terminal output: replies as rendered Markdown, the chat list and errors.
+
+use shared::{ApiError, ApiErrorCode, ChatSession};
+use termimad::MadSkin;
+
+/// Prints a reply, rendering its Markdown unless `raw` (or stdout isn't a terminal).
+pub fn print_reply(text: &str, raw: bool) {
+    if raw || !std::io::IsTerminal::is_terminal(&std::io::stdout()) {
+        println!("{text}");
+    } else {
+        MadSkin::default().print_text(text);
+    }
+}
+
+/// One line per chat: short id, last activity, animal and title.
+pub fn print_sessions(chats: &[ChatSession]) {
+    if chats.is_empty() {
+        println!("No saved chats.");
+        return;
+    }
+    for chat in chats {
+        println!("{}", session_line(chat));
+    }
+}
+
+pub fn session_line(chat: &ChatSession) -> String {
+    let updated = chat.updated_at.with_timezone(&chrono::Local);
+    format!(
+        "{}  {}  {:<9} {}",
+        short_id(&chat.id),
+        updated.format("%Y-%m-%d %H:%M"),
+        chat.animal.label(chat.language),
+        chat.title
+    )
+}
+
+/// Enough of a chat id to pick it with `--session` or `/open`.
+pub fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+/// A failed request, with what to do about it.
+pub fn error_text(error: &ApiError) -> String {
+    let hint = match error.code {
+        ApiErrorCode::Offline => " Is the server running? (see --api)",
+        _ => "",
+    };
+    let wait = error
+        .retry_after
+        .map(|seconds| format!(" Retry in {seconds}s."))
+        .unwrap_or_default();
+    format!("Error: {}.{hint}{wait}", error.message.trim_end_matches('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_say_when_to_retry() {
+        let limited = ApiError::new(ApiErrorCode::RateLimited, "Rate limit exceeded").with_retry_after(30);
+        assert_eq!(
+            error_text(&limited),
+            "Error: Rate limit exceeded. Retry in 30s."
+        );
+        let offline = ApiError::new(ApiErrorCode::Offline, "Could not reach http://x/api/chat");
+        assert!(error_text(&offline).ends_with("(see --api)"));
+        assert_eq!(short_id("0123456789"), "01234567");
+    }
+}
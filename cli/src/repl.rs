@@ -0,0 +1,181 @@
+//! Interactive chat. Lines starting with `/` are commands; anything else is
+//! sent to the animal. The chat is saved after every reply.
+
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+use shared::{AnimalType, ChatSession, IntelligenceLevel, Language, Role};
+
+use crate::client::ApiClient;
+use crate::store::SessionStore;
+use crate::{chat, parse_animal, parse_intelligence, parse_language, render};
+
+const HELP: &str = "\
+/animal <id>    talk to another animal (octopus, cat, owl...)
+/level <level>  high, medium or low
+/lang <es|en>   reply language
+/new            start a new chat with the current settings
+/chats          list saved chats
+/open <id>      continue a saved chat
+/history        show this chat so far
+/help           this list
+/quit           leave (Ctrl-D also works)";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Send(String),
+    Animal(AnimalType),
+    Level(IntelligenceLevel),
+    Lang(Language),
+    New,
+    Chats,
+    Open(String),
+    History,
+    Help,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let Some(command) = line.strip_prefix('/') else {
+        return Ok(Command::Send(line.to_string()));
+    };
+    let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+    let arg = arg.trim();
+    let needs_arg = |usage: &str| {
+        if arg.is_empty() {
+            Err(format!("Usage: /{name} {usage}"))
+        } else {
+            Ok(arg)
+        }
+    };
+    match name {
+        "animal" => parse_animal(needs_arg("<id>")?).map(Command::Animal),
+        "level" => parse_intelligence(needs_arg("<high|medium|low>")?).map(Command::Level),
+        "lang" => parse_language(needs_arg("<es|en>")?).map(Command::Lang),
+        "new" => Ok(Command::New),
+        "chats" => Ok(Command::Chats),
+        "open" => needs_arg("<id>").map(|id| Command::Open(id.to_string())),
+        "history" => Ok(Command::History),
+        "help" => Ok(Command::Help),
+        "quit" | "exit" => Ok(Command::Quit),
+        other => Err(format!("Unknown command /{other}; /help lists them")),
+    }
+}
+
+pub fn run(
+    client: &ApiClient,
+    store: &SessionStore,
+    mut chat: ChatSession,
+    raw: bool,
+) -> Result<(), String> {
+    let mut editor = DefaultEditor::new().map_err(|e| format!("Failed to open the terminal: {e}"))?;
+    greet(&chat);
+
+    loop {
+        let prompt = format!("{}·{}> ", chat.animal.id(), chat.intelligence.id());
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(()),
+            Err(e) => return Err(format!("Failed to read input: {e}")),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line.as_str());
+
+        let command = match parse_command(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("{e}");
+                continue;
+            }
+        };
+        match command {
+            Command::Send(text) => match chat::send(client, &mut chat, &text) {
+                Ok(reply) => {
+                    println!("{}:", chat.animal.label(chat.language));
+                    render::print_reply(&reply, raw);
+                    save(store, &chat);
+                }
+                Err(e) => eprintln!("{}", render::error_text(&e)),
+            },
+            Command::Animal(animal) => {
+                chat.animal = animal;
+                save(store, &chat);
+                greet(&chat);
+            }
+            Command::Level(level) => {
+                chat.intelligence = level;
+                save(store, &chat);
+            }
+            Command::Lang(language) => {
+                chat.language = language;
+                save(store, &chat);
+            }
+            Command::New => {
+                chat = ChatSession::new(chat.animal, chat.intelligence, chat.language);
+                greet(&chat);
+            }
+            Command::Chats => match store.list() {
+                Ok(chats) => render::print_sessions(&chats),
+                Err(e) => eprintln!("{e}"),
+            },
+            Command::Open(id) => match store.load(&id) {
+                Ok(saved) => {
+                    chat = saved;
+                    println!("{}", chat.title);
+                    print_history(&chat, raw);
+                }
+                Err(e) => eprintln!("{e}"),
+            },
+            Command::History => print_history(&chat, raw),
+            Command::Help => println!("{HELP}"),
+            Command::Quit => return Ok(()),
+        }
+    }
+}
+
+fn greet(chat: &ChatSession) {
+    println!(
+        "{} — {}  (/help for commands)",
+        chat.animal.label(chat.language),
+        chat.animal.sound(chat.language)
+    );
+}
+
+fn print_history(chat: &ChatSession, raw: bool) {
+    for message in chat.messages.messages() {
+        match message.role {
+            Role::User => println!("> {}", message.content),
+            Role::Assistant => render::print_reply(&message.content, raw),
+        }
+    }
+}
+
+/// Empty chats aren't worth a file; a failed save is reported but not fatal.
+fn save(store: &SessionStore, chat: &ChatSession) {
+    if chat.messages.is_empty() {
+        return;
+    }
+    if let Err(e) = store.save(chat) {
+        eprintln!("{e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_and_messages_are_told_apart() {
+        assert_eq!(parse_command("  hola pulpo "), Ok(Command::Send("hola pulpo".to_string())));
+        assert_eq!(parse_command("/animal owl"), Ok(Command::Animal(AnimalType::OWL)));
+        assert_eq!(parse_command("/level low"), Ok(Command::Level(IntelligenceLevel::Low)));
+        assert_eq!(parse_command("/lang en"), Ok(Command::Lang(Language::En)));
+        assert_eq!(parse_command("/open  ab12 "), Ok(Command::Open("ab12".to_string())));
+        assert_eq!(parse_command("/exit"), Ok(Command::Quit));
+        assert_eq!(parse_command("/animal"), Err("Usage: /animal <id>".to_string()));
+        assert!(parse_command("/animal unicorn").is_err());
+        assert!(parse_command("/dance").unwrap_err().contains("/help"));
+    }
+}
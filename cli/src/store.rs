@@ -0,0 +1,120 @@
+//! Saved chats: one `ChatSession` JSON file per chat, named by its id.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use shared::ChatSession;
+
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// `$ANIMAL_CLI_HOME`, else `$XDG_DATA_HOME/inteligencia-animal/chats`,
+    /// else `~/.local/share/inteligencia-animal/chats`.
+    pub fn open_default() -> Result<Self, String> {
+        let dir = match std::env::var_os("ANIMAL_CLI_HOME") {
+            Some(home) => PathBuf::from(home),
+            None => {
+                let data = std::env::var_os("XDG_DATA_HOME")
+                    .map(PathBuf::from)
+                    .or_else(|| {
+                        std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/share"))
+                    })
+                    .ok_or("Neither ANIMAL_CLI_HOME nor HOME is set")?;
+                data.join("inteligencia-animal").join("chats")
+            }
+        };
+        Ok(Self::at(dir))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Writes the chat through a temporary file, so a crash never leaves half a chat.
+    pub fn save(&self, chat: &ChatSession) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {e}", self.dir.display()))?;
+        let json = serde_json::to_string_pretty(chat)
+            .map_err(|e| format!("Failed to serialize chat: {e}"))?;
+        let path = self.dir.join(format!("{}.json", chat.id));
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, json)
+            .and_then(|()| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to save {}: {e}", path.display()))
+    }
+
+    /// The chat whose id is, or uniquely starts with, `id`.
+    pub fn load(&self, id: &str) -> Result<ChatSession, String> {
+        let id = id.trim();
+        let mut matches: Vec<ChatSession> = self
+            .list()?
+            .into_iter()
+            .filter(|chat| chat.id.starts_with(id))
+            .collect();
+        if let Some(exact) = matches.iter().position(|chat| chat.id == id) {
+            return Ok(matches.swap_remove(exact));
+        }
+        match matches.len() {
+            1 => Ok(matches.remove(0)),
+            0 => Err(format!("No saved chat matches '{id}'")),
+            n => Err(format!("'{id}' matches {n} chats; use more of the id")),
+        }
+    }
+
+    /// Every saved chat, most recent first. Unreadable files are skipped.
+    pub fn list(&self) -> Result<Vec<ChatSession>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {e}", self.dir.display())),
+        };
+        let mut chats: Vec<ChatSession> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let raw = fs::read_to_string(&path).ok()?;
+                match serde_json::from_str(&raw) {
+                    Ok(chat) => Some(chat),
+                    Err(e) => {
+                        eprintln!("Skipping {}: {e}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect();
+        chats.sort_by_key(|chat| std::cmp::Reverse(chat.updated_at));
+        Ok(chats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{AnimalType, ChatMessage, IntelligenceLevel, Language, Role};
+
+    #[test]
+    fn chats_round_trip_and_resolve_by_id_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::at(dir.path().join("chats"));
+        assert!(store.list().unwrap().is_empty());
+
+        let mut older = ChatSession::new(AnimalType::CAT, IntelligenceLevel::Low, Language::Es);
+        older.id = "aaa-1".to_string();
+        older.messages.push(ChatMessage::new(Role::User, "Hola"));
+        let mut newer = ChatSession::new(AnimalType::OWL, IntelligenceLevel::High, Language::En);
+        newer.id = "abc-2".to_string();
+        newer.updated_at = older.updated_at + chrono::Duration::seconds(5);
+        store.save(&older).unwrap();
+        store.save(&newer).unwrap();
+        fs::write(dir.path().join("chats/broken.json"), "{").unwrap();
+
+        let ids: Vec<String> = store.list().unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, ["abc-2", "aaa-1"]);
+        assert_eq!(store.load("aa").unwrap(), older);
+        assert!(store.load("a").unwrap_err().contains("matches 2"));
+        assert!(store.load("zzz").is_err());
+    }
+}
@@ -0,0 +1,511 @@
+//! OpenAI-compatible chat completions facade (`/v1/chat/completions`).
+//!
+//! Each animal and intelligence level is exposed as a model named
+//! `{animal}-{level}`, optionally followed by `-{language}` (`cat-high`,
+//! `octopus-low-en`). The persona's own system prompt replaces any `system`
+//! messages the client sends; the rest of the conversation becomes a regular
+//! [`ChatRequest`], so validation, memory budgets and providers are shared
+//! with `/api/chat`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::{
+    AnimalType, ApiError, ApiErrorCode, ChatMessage, ChatRequest, ChatStreamEvent,
+    IntelligenceLevel, Language, Role, MAX_CANDIDATES,
+};
+
+use crate::providers::Completion;
+
+/// Owner reported by `/v1/models`.
+const OWNER: &str = "inteligencia-animal";
+
+// ═══════════════════════════════════════════════
+// Models
+// ═══════════════════════════════════════════════
+
+/// Reads `{animal}-{level}[-{language}]`; the language defaults to Spanish.
+pub fn parse_model(model: &str) -> Option<(AnimalType, IntelligenceLevel, Language)> {
+    let mut parts = model.trim().split('-');
+    let animal = AnimalType::from_id(parts.next()?)?;
+    let intelligence = IntelligenceLevel::from_id(parts.next()?)?;
+    let language = match parts.next() {
+        None => Language::default(),
+        Some(code) => Language::from_code(code)?,
+    };
+    match parts.next() {
+        None => Some((animal, intelligence, language)),
+        Some(_) => None,
+    }
+}
+
+/// Every `{animal}-{level}` model name.
+pub fn model_ids() -> Vec<String> {
+    AnimalType::all()
+        .iter()
+        .flat_map(|animal| {
+            IntelligenceLevel::all()
+                .iter()
+                .map(move |level| format!("{}-{}", animal.id(), level.id()))
+        })
+        .collect()
+}
+
+/// Body of `GET /v1/models`.
+pub fn model_list() -> ModelList {
+    ModelList {
+        object: "list",
+        data: model_ids()
+            .into_iter()
+            .map(|id| ModelInfo {
+                id,
+                object: "model",
+                created: 0,
+                owned_by: OWNER,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelList {
+    object: &'static str,
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelInfo {
+    id: String,
+    object: &'static str,
+    created: u64,
+    owned_by: &'static str,
+}
+
+// ═══════════════════════════════════════════════
+// Requests
+// ═══════════════════════════════════════════════
+
+/// The subset of a chat completions request we honour; sampling parameters
+/// are the persona's and are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<CompletionMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    /// Choices wanted, 1 to [`MAX_CANDIDATES`]; ignored when streaming.
+    #[serde(default)]
+    pub n: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: MessageContent,
+}
+
+/// Plain text, or the content-part array newer SDKs send (text parts only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentPart {
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl MessageContent {
+    fn into_text(self) -> String {
+        match self {
+            MessageContent::Text(text) => text,
+            MessageContent::Parts(parts) => parts.into_iter().filter_map(|p| p.text).collect(),
+        }
+    }
+}
+
+/// Translates a completions request into our own, to be validated with
+/// [`crate::validate_request`]. The last message must come from the user.
+pub fn chat_request(request: CompletionRequest) -> Result<ChatRequest, ApiError> {
+    let Some((animal, intelligence, language)) = parse_model(&request.model) else {
+        return Err(ApiError::new(
+            ApiErrorCode::InvalidRequest,
+            format!("Unknown model '{}'", request.model),
+        )
+        .with_details(json!({ "models": model_ids() })));
+    };
+
+    let candidates = match request.n {
+        Some(n) if n == 0 || n > u32::from(MAX_CANDIDATES) => {
+            return Err(ApiError::new(
+                ApiErrorCode::InvalidRequest,
+                format!("n must be between 1 and {MAX_CANDIDATES}"),
+            )
+            .with_details(json!({ "max_n": MAX_CANDIDATES })));
+        }
+        Some(n) if !request.stream => u8::try_from(n).ok(),
+        _ => None,
+    };
+
+    let mut history = Vec::new();
+    for message in request.messages {
+        let role = match message.role.as_str() {
+            "system" | "developer" => continue,
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            other => {
+                return Err(ApiError::new(
+                    ApiErrorCode::InvalidHistory,
+                    format!("Unsupported message role '{other}'"),
+                ));
+            }
+        };
+        history.push(ChatMessage::new(role, message.content.into_text()));
+    }
+
+    let message = match history.pop() {
+        Some(last) if last.role == Role::User => last.content,
+        _ => {
+            return Err(ApiError::new(
+                ApiErrorCode::InvalidHistory,
+                "The last message must come from the user",
+            ));
+        }
+    };
+
+    Ok(ChatRequest {
+        message,
+        animal,
+        intelligence,
+        language,
+        history,
+        candidates,
+        summary: None,
+        persona: None,
+    })
+}
+
+// ═══════════════════════════════════════════════
+// Responses
+// ═══════════════════════════════════════════════
+
+/// Identifies one response; chunks of a stream share it.
+#[derive(Debug, Clone)]
+pub struct ResponseId {
+    pub id: String,
+    /// Unix seconds.
+    pub created: u64,
+    /// The facade model name the client asked for.
+    pub model: String,
+}
+
+impl ResponseId {
+    pub fn new(now_ms: u64, model: &str) -> Self {
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        Self {
+            id: format!("chatcmpl-{now_ms:x}{}", &suffix[..12]),
+            created: now_ms / 1000,
+            model: model.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompletionResponse {
+    id: String,
+    object: &'static str,
+    created: u64,
+    model: String,
+    choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Serialize)]
+struct Choice {
+    index: usize,
+    message: AssistantMessage,
+    finish_reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct AssistantMessage {
+    role: &'static str,
+    content: String,
+}
+
+/// Providers only report a total, so the split is not known.
+#[derive(Debug, Serialize)]
+struct Usage {
+    total_tokens: u32,
+}
+
+/// Body of a non-streamed reply: one choice per candidate.
+pub fn completion_response(id: ResponseId, completion: Completion) -> CompletionResponse {
+    let choices = std::iter::once(completion.text)
+        .chain(completion.alternatives)
+        .enumerate()
+        .map(|(index, content)| Choice {
+            index,
+            message: AssistantMessage {
+                role: "assistant",
+                content,
+            },
+            finish_reason: "stop",
+        })
+        .collect();
+
+    CompletionResponse {
+        id: id.id,
+        object: "chat.completion",
+        created: id.created,
+        model: id.model,
+        choices,
+        usage: completion
+            .tokens_used
+            .map(|total_tokens| Usage { total_tokens }),
+    }
+}
+
+/// Rewrites our stream events as `chat.completion.chunk` SSE frames.
+pub struct ChunkWriter {
+    id: ResponseId,
+    started: bool,
+}
+
+impl ChunkWriter {
+    pub fn new(id: ResponseId) -> Self {
+        Self { id, started: false }
+    }
+
+    /// Frames for `event`: the first delta also announces the role, `done`
+    /// closes with a `stop` chunk and `[DONE]`, summaries are dropped.
+    pub fn frames(&mut self, event: ChatStreamEvent) -> Vec<String> {
+        match event {
+            ChatStreamEvent::Delta { text } => {
+                let role = (!self.started).then_some("assistant");
+                self.started = true;
+                vec![self.chunk(json!({ "role": role, "content": text }), None, None)]
+            }
+            ChatStreamEvent::Done { tokens_used, .. } => {
+                let usage = tokens_used.map(|total_tokens| Usage { total_tokens });
+                vec![
+                    self.chunk(json!({}), Some("stop"), usage),
+                    "data: [DONE]\n\n".to_string(),
+                ]
+            }
+            ChatStreamEvent::Error { code, message } => {
+                let error = error_body(&ApiError::new(code, message));
+                vec![format!("data: {error}\n\n")]
+            }
+            ChatStreamEvent::Summary { .. } => Vec::new(),
+        }
+    }
+
+    fn chunk(
+        &self,
+        mut delta: serde_json::Value,
+        finish_reason: Option<&str>,
+        usage: Option<Usage>,
+    ) -> String {
+        if let Some(fields) = delta.as_object_mut() {
+            fields.retain(|_, value| !value.is_null());
+        }
+        let mut chunk = json!({
+            "id": self.id.id,
+            "object": "chat.completion.chunk",
+            "created": self.id.created,
+            "model": self.id.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+        if let Some(usage) = usage {
+            chunk["usage"] = json!(usage);
+        }
+        format!("data: {chunk}\n\n")
+    }
+}
+
+/// An `ApiError` in the protocol's `{"error": {...}}` shape, keeping our
+/// snake_case code.
+pub fn error_body(error: &ApiError) -> serde_json::Value {
+    let kind = match error.code {
+        ApiErrorCode::RateLimited => "rate_limit_error",
+        code if code.status() == 400 => "invalid_request_error",
+        _ => "server_error",
+    };
+    json!({
+        "error": {
+            "message": error.message,
+            "type": kind,
+            "code": error.code,
+            "param": null,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(model: &str, messages: &[(&str, &str)]) -> CompletionRequest {
+        CompletionRequest {
+            model: model.to_string(),
+            messages: messages
+                .iter()
+                .map(|(role, content)| CompletionMessage {
+                    role: role.to_string(),
+                    content: MessageContent::Text(content.to_string()),
+                })
+                .collect(),
+            stream: false,
+            n: None,
+        }
+    }
+
+    #[test]
+    fn model_names_pick_animal_level_and_language() {
+        assert_eq!(
+            parse_model("cat-high"),
+            Some((AnimalType::CAT, IntelligenceLevel::High, Language::Es))
+        );
+        assert_eq!(
+            parse_model("octopus-low-en"),
+            Some((AnimalType::OCTOPUS, IntelligenceLevel::Low, Language::En))
+        );
+        for bad in ["cat", "cat-genius", "unicorn-high", "cat-high-fr", "cat-high-en-x"] {
+            assert_eq!(parse_model(bad), None, "{bad}");
+        }
+        assert!(model_ids().iter().all(|id| parse_model(id).is_some()));
+        assert_eq!(
+            model_ids().len(),
+            AnimalType::all().len() * IntelligenceLevel::all().len()
+        );
+    }
+
+    #[test]
+    fn conversation_maps_onto_chat_request() {
+        let body = chat_request(request(
+            "dog-medium-en",
+            &[
+                ("system", "You are a helpful assistant."),
+                ("user", "Hi"),
+                ("assistant", "Woof!"),
+                ("user", "Walk?"),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(body.animal, AnimalType::DOG);
+        assert_eq!(body.language, Language::En);
+        assert_eq!(body.message, "Walk?");
+        let roles: Vec<Role> = body.history.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(roles, [Role::User, Role::Assistant]);
+
+        let prompt = crate::chat_prompt(body);
+        assert_eq!(
+            prompt.system,
+            crate::build_system_prompt(&AnimalType::DOG, &IntelligenceLevel::Medium, Language::En, None)
+        );
+    }
+
+    #[test]
+    fn bad_conversations_are_rejected() {
+        let unknown = chat_request(request("gpt-4o", &[("user", "Hi")])).unwrap_err();
+        assert_eq!(unknown.code, ApiErrorCode::InvalidRequest);
+
+        let trailing = chat_request(request("cat-low", &[("user", "Hi"), ("assistant", "Meow")]));
+        assert_eq!(trailing.unwrap_err().code, ApiErrorCode::InvalidHistory);
+
+        let tool = chat_request(request("cat-low", &[("tool", "{}"), ("user", "Hi")]));
+        assert_eq!(tool.unwrap_err().code, ApiErrorCode::InvalidHistory);
+
+        for n in [0, 5, 256] {
+            let choices = CompletionRequest { n: Some(n), ..request("cat-low", &[("user", "Hi")]) };
+            let error = chat_request(choices).unwrap_err();
+            assert_eq!(error.code, ApiErrorCode::InvalidRequest);
+            assert_eq!(error.message, "n must be between 1 and 4");
+        }
+    }
+
+    #[test]
+    fn content_parts_are_joined() {
+        let parsed: CompletionRequest = serde_json::from_value(json!({
+            "model": "owl-high",
+            "messages": [{ "role": "user", "content": [{ "type": "text", "text": "Hoo?" }] }],
+            "stream": true,
+            "n": 2,
+        }))
+        .unwrap();
+        let body = chat_request(parsed).unwrap();
+        assert_eq!(body.message, "Hoo?");
+        assert_eq!(body.candidates, None);
+    }
+
+    #[test]
+    fn completion_lists_every_candidate_as_a_choice() {
+        let response = completion_response(
+            ResponseId::new(1_700_000_000_000, "cat-high"),
+            Completion {
+                text: "Miau.".to_string(),
+                tokens_used: Some(12),
+                alternatives: vec!["Prrr.".to_string()],
+            },
+        );
+        let json = serde_json::to_value(response).unwrap();
+
+        assert!(json["id"].as_str().unwrap().starts_with("chatcmpl-"));
+        assert_ne!(
+            ResponseId::new(1_700_000_000_000, "cat-high").id,
+            ResponseId::new(1_700_000_000_000, "cat-high").id
+        );
+        assert_eq!(json["object"], "chat.completion");
+        assert_eq!(json["created"], 1_700_000_000);
+        assert_eq!(json["model"], "cat-high");
+        assert_eq!(json["choices"][1]["message"]["content"], "Prrr.");
+        assert_eq!(json["choices"][0]["finish_reason"], "stop");
+        assert_eq!(json["usage"]["total_tokens"], 12);
+    }
+
+    #[test]
+    fn stream_events_become_chunks() {
+        let mut writer = ChunkWriter::new(ResponseId::new(5_000, "cat-high"));
+        let frames: Vec<String> = [
+            ChatStreamEvent::Delta { text: "Mi".to_string() },
+            ChatStreamEvent::Delta { text: "au".to_string() },
+            ChatStreamEvent::Done { tokens_used: None, model: Some("m".to_string()) },
+        ]
+        .into_iter()
+        .flat_map(|event| writer.frames(event))
+        .collect();
+
+        let chunks: Vec<serde_json::Value> = frames[..3]
+            .iter()
+            .map(|f| serde_json::from_str(f.strip_prefix("data: ").unwrap().trim()).unwrap())
+            .collect();
+        assert_eq!(chunks[0]["choices"][0]["delta"], json!({ "role": "assistant", "content": "Mi" }));
+        assert_eq!(chunks[1]["choices"][0]["delta"], json!({ "content": "au" }));
+        assert_eq!(chunks[2]["choices"][0]["finish_reason"], "stop");
+        assert_eq!(chunks[2]["object"], "chat.completion.chunk");
+        assert_eq!(frames[3], "data: [DONE]\n\n");
+    }
+
+    #[test]
+    fn errors_use_the_protocol_shape() {
+        let body = error_body(&ApiError::new(ApiErrorCode::EmptyMessage, "Message cannot be empty"));
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["code"], "empty_message");
+        assert_eq!(
+            error_body(&ApiError::new(ApiErrorCode::RateLimited, "Slow down"))["error"]["type"],
+            "rate_limit_error"
+        );
+    }
+}
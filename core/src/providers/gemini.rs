@@ -0,0 +1,291 @@
+//! Google Gemini backend (`generateContent` / `streamGenerateContent`).
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use shared::{ChatStreamEvent, Role, SseDecoder};
+
+use super::{
+    post_json, read_json, relay_stream, ChatPrompt, Completion, EventStream, HttpClient,
+    LlmProvider, ProviderError, StreamRelay,
+};
+
+pub const GEMINI_MODEL: &str = "gemini-2.5-flash-lite";
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
+pub struct GeminiProvider {
+    http: Rc<dyn HttpClient>,
+    api_key: String,
+}
+
+impl GeminiProvider {
+    pub fn new(http: Rc<dyn HttpClient>, api_key: String) -> Self {
+        Self { http, api_key }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl LlmProvider for GeminiProvider {
+    fn model(&self) -> &str {
+        GEMINI_MODEL
+    }
+
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        let url = format!("{}/{}:generateContent", GEMINI_BASE_URL, GEMINI_MODEL);
+
+        let body = post_json(
+            &*self.http,
+            &url,
+            &[("x-goog-api-key", &self.api_key)],
+            &GeminiRequest::from_prompt(prompt, prompt.candidates),
+        )
+        .await?;
+
+        let api_response: GeminiApiResponse = read_json(body)
+            .await
+            .map_err(|e| format!("Failed to parse Gemini response: {e}"))?;
+
+        let tokens_used = api_response
+            .usage_metadata
+            .and_then(|u| u.total_token_count);
+
+        // First part of each candidate (`candidateCount` of them)
+        let mut texts = api_response
+            .candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|c| c.content)
+            .filter_map(|c| c.parts.into_iter().next())
+            .map(|p| p.text);
+        let text = texts
+            .next()
+            .ok_or_else(|| "No response text from Gemini".to_string())?;
+
+        Ok(Completion {
+            text,
+            tokens_used,
+            alternatives: texts.collect(),
+        })
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        let url = format!(
+            "{}/{}:streamGenerateContent?alt=sse",
+            GEMINI_BASE_URL, GEMINI_MODEL
+        );
+
+        let upstream = post_json(
+            &*self.http,
+            &url,
+            &[("x-goog-api-key", &self.api_key)],
+            &GeminiRequest::from_prompt(prompt, 1),
+        )
+        .await?;
+
+        Ok(relay_stream(upstream, GeminiStreamRelay::default()))
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Wire Types
+// ═══════════════════════════════════════════════
+
+/// Gemini API request structures
+#[derive(Serialize)]
+struct GeminiRequest {
+    system_instruction: GeminiContent,
+    contents: Vec<GeminiContent>,
+    #[serde(rename = "generationConfig")]
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GeminiPart {
+    text: String,
+}
+
+#[derive(Serialize)]
+struct GenerationConfig {
+    #[serde(rename = "maxOutputTokens")]
+    max_output_tokens: u32,
+    temperature: f32,
+    #[serde(rename = "topP")]
+    top_p: f32,
+    #[serde(rename = "candidateCount", skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u8>,
+}
+
+/// Gemini API response structures
+#[derive(Deserialize)]
+struct GeminiApiResponse {
+    candidates: Option<Vec<GeminiCandidate>>,
+    #[serde(rename = "usageMetadata")]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct GeminiCandidate {
+    // Absent on the final streamed chunk, which only carries `finishReason`.
+    content: Option<GeminiContent>,
+}
+
+#[derive(Deserialize)]
+struct UsageMetadata {
+    #[serde(rename = "totalTokenCount")]
+    total_token_count: Option<u32>,
+}
+
+impl GeminiRequest {
+    /// Build the Gemini request body from history + current message,
+    /// asking for `candidates` alternative replies.
+    fn from_prompt(prompt: &ChatPrompt, candidates: u8) -> Self {
+        // Build conversation contents from history + current message
+        let mut contents: Vec<GeminiContent> = prompt
+            .history
+            .iter()
+            .map(|msg| GeminiContent {
+                role: Some(match msg.role {
+                    Role::User => "user".to_string(),
+                    Role::Assistant => "model".to_string(),
+                }),
+                parts: vec![GeminiPart {
+                    text: msg.content.clone(),
+                }],
+            })
+            .collect();
+
+        // Add current user message
+        contents.push(GeminiContent {
+            role: Some("user".to_string()),
+            parts: vec![GeminiPart {
+                text: prompt.message.clone(),
+            }],
+        });
+
+        GeminiRequest {
+            system_instruction: GeminiContent {
+                role: None,
+                parts: vec![GeminiPart {
+                    text: prompt.system.clone(),
+                }],
+            },
+            contents,
+            generation_config: GenerationConfig {
+                max_output_tokens: 1024,
+                temperature: 0.9,
+                top_p: 0.95,
+                candidate_count: (candidates > 1).then_some(candidates),
+            },
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Streaming
+// ═══════════════════════════════════════════════
+
+/// Translates Gemini's streamed `GeminiApiResponse` chunks into `ChatStreamEvent`s.
+#[derive(Default)]
+struct GeminiStreamRelay {
+    decoder: SseDecoder,
+    tokens_used: Option<u32>,
+}
+
+impl StreamRelay for GeminiStreamRelay {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ChatStreamEvent> {
+        let mut events = Vec::new();
+        for data in self.decoder.push(chunk) {
+            let Ok(parsed) = serde_json::from_str::<GeminiApiResponse>(&data) else {
+                continue;
+            };
+
+            // Usage metadata is cumulative; the last chunk carries the total.
+            if let Some(total) = parsed.usage_metadata.and_then(|u| u.total_token_count) {
+                self.tokens_used = Some(total);
+            }
+
+            let text: String = parsed
+                .candidates
+                .and_then(|c| c.into_iter().next())
+                .and_then(|c| c.content)
+                .map(|c| c.parts.into_iter().map(|p| p.text).collect())
+                .unwrap_or_default();
+
+            if !text.is_empty() {
+                events.push(ChatStreamEvent::Delta { text });
+            }
+        }
+        events
+    }
+
+    fn finish(self) -> ChatStreamEvent {
+        ChatStreamEvent::Done {
+            tokens_used: self.tokens_used,
+            model: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{AnimalType, IntelligenceLevel, Language};
+
+    #[test]
+    fn candidate_count_is_only_sent_when_asking_for_several() {
+        let prompt = ChatPrompt {
+            animal: AnimalType::CHICKEN,
+            intelligence: IntelligenceLevel::Low,
+            language: Language::Es,
+            system: "Eres una gallina.".to_string(),
+            history: vec![],
+            message: "¿Y el huevo?".to_string(),
+            candidates: 3,
+            persona: None,
+        };
+
+        let config = |candidates| {
+            serde_json::to_value(GeminiRequest::from_prompt(&prompt, candidates)).unwrap()
+                ["generationConfig"]
+                .clone()
+        };
+        assert_eq!(config(3)["candidateCount"], 3);
+        assert!(config(1).get("candidateCount").is_none());
+    }
+
+    #[test]
+    fn relay_emits_deltas_and_final_token_count() {
+        let upstream = concat!(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Miau\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"... prrr\"}]}}],",
+            "\"usageMetadata\":{\"totalTokenCount\":12}}\r\n\r\n",
+            "data: {\"candidates\":[{\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"totalTokenCount\":15}}\r\n\r\n",
+        );
+
+        let mut relay = GeminiStreamRelay::default();
+        // Feed in small slices to exercise chunk boundaries.
+        let mut events: Vec<ChatStreamEvent> = upstream
+            .as_bytes()
+            .chunks(7)
+            .flat_map(|chunk| relay.feed(chunk))
+            .collect();
+        events.push(relay.finish());
+
+        assert_eq!(
+            events,
+            vec![
+                ChatStreamEvent::Delta { text: "Miau".to_string() },
+                ChatStreamEvent::Delta { text: "... prrr".to_string() },
+                ChatStreamEvent::Done { tokens_used: Some(15), model: None },
+            ]
+        );
+    }
+}
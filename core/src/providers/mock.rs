@@ -0,0 +1,241 @@
+//! Deterministic in-process backend for offline development and tests.
+//!
+//! Replies are picked from the registry catchphrases (or a custom persona's)
+//! using a hash of the user message, so the same request always yields the
+//! same answer. [`MockProvider::flaky`] fails a scripted number of times
+//! first, to exercise retries and the circuit breaker.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+
+use futures_util::stream::{self, StreamExt};
+use shared::{ChatStreamEvent, IntelligenceLevel, Language};
+
+use super::{ChatPrompt, Completion, EventStream, LlmProvider, ProviderError};
+
+pub const MOCK_MODEL: &str = "mock";
+
+#[derive(Default)]
+pub struct MockProvider {
+    /// Errors returned, in order, before any call succeeds.
+    failures: RefCell<VecDeque<ProviderError>>,
+    calls: Cell<u32>,
+}
+
+impl MockProvider {
+    pub fn flaky(failures: impl IntoIterator<Item = ProviderError>) -> Self {
+        Self {
+            failures: RefCell::new(failures.into_iter().collect()),
+            calls: Cell::new(0),
+        }
+    }
+
+    /// Parses a `MOCK_FAILURES` spec such as `"503,429,network"`.
+    pub fn from_spec(spec: &str) -> Self {
+        Self::flaky(spec.split(',').map(str::trim).filter(|s| !s.is_empty()).map(|item| {
+            match item.parse() {
+                Ok(status) => ProviderError::Status {
+                    status,
+                    retry_after: None,
+                    body: "simulated failure".to_string(),
+                },
+                Err(_) => ProviderError::Network(format!("simulated {item} failure")),
+            }
+        }))
+    }
+
+    /// Number of `generate`/`stream` calls received so far.
+    pub fn calls(&self) -> u32 {
+        self.calls.get()
+    }
+
+    fn next_outcome(&self) -> Result<(), ProviderError> {
+        self.calls.set(self.calls.get() + 1);
+        match self.failures.borrow_mut().pop_front() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl LlmProvider for MockProvider {
+    fn model(&self) -> &str {
+        MOCK_MODEL
+    }
+
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        self.next_outcome()?;
+        let text = mock_reply(prompt, 0);
+        let alternatives: Vec<String> = (1..prompt.candidates).map(|i| mock_reply(prompt, i)).collect();
+        let tokens_used = Some(
+            std::iter::once(&text)
+                .chain(&alternatives)
+                .map(|t| t.split_whitespace().count() as u32)
+                .sum(),
+        );
+        Ok(Completion {
+            text,
+            tokens_used,
+            alternatives,
+        })
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        self.next_outcome()?;
+        let text = mock_reply(prompt, 0);
+        let tokens_used = Some(text.split_whitespace().count() as u32);
+
+        // One delta per word, keeping the separating whitespace.
+        let mut events: Vec<ChatStreamEvent> = text
+            .split_inclusive(' ')
+            .map(|word| ChatStreamEvent::Delta {
+                text: word.to_string(),
+            })
+            .collect();
+        events.push(ChatStreamEvent::Done { tokens_used, model: None });
+
+        Ok(stream::iter(events).boxed_local())
+    }
+}
+
+/// Builds the canned reply for a prompt; each `candidate` picks the next catchphrase.
+fn mock_reply(prompt: &ChatPrompt, candidate: u8) -> String {
+    let custom: Vec<&str> = prompt
+        .persona
+        .iter()
+        .flat_map(|p| p.catchphrases.iter().map(String::as_str))
+        .collect();
+    let lines = if custom.is_empty() {
+        prompt.animal.catchphrases(prompt.language)
+    } else {
+        &custom[..]
+    };
+    let line = lines[(fnv1a(&prompt.message) as usize + usize::from(candidate)) % lines.len()];
+    format!("{line} {}", intelligence_tail(&prompt.intelligence, prompt.language))
+}
+
+fn intelligence_tail(intelligence: &IntelligenceLevel, language: Language) -> &'static str {
+    match (intelligence, language) {
+        (IntelligenceLevel::High, Language::Es) => "(Respuesta simulada con intelecto ALTO.)",
+        (IntelligenceLevel::High, Language::En) => "(Simulated reply with HIGH intellect.)",
+        (IntelligenceLevel::Medium, Language::Es) => "(Respuesta simulada.)",
+        (IntelligenceLevel::Medium, Language::En) => "(Simulated reply.)",
+        (IntelligenceLevel::Low, Language::Es) => "¿Eh?",
+        (IntelligenceLevel::Low, Language::En) => "Huh?",
+    }
+}
+
+/// 32-bit FNV-1a hash; stable across platforms and releases, unlike `DefaultHasher`.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use shared::{AnimalType, CustomPersona};
+
+    fn prompt(animal: AnimalType, message: &str) -> ChatPrompt {
+        ChatPrompt {
+            animal,
+            intelligence: IntelligenceLevel::Medium,
+            language: Language::Es,
+            system: String::new(),
+            history: vec![],
+            message: message.to_string(),
+            candidates: 1,
+            persona: None,
+        }
+    }
+
+    #[test]
+    fn replies_are_deterministic_and_in_character() {
+        let p = prompt(AnimalType::CHICKEN, "¿Qué hora es?");
+        let first = block_on(MockProvider::default().generate(&p)).unwrap();
+        let second = block_on(MockProvider::default().generate(&p)).unwrap();
+
+        assert_eq!(first, second);
+        assert!(AnimalType::CHICKEN.catchphrases(Language::Es)
+            .iter()
+            .any(|line| first.text.starts_with(line)));
+    }
+
+    #[test]
+    fn replies_follow_the_requested_language() {
+        let mut p = prompt(AnimalType::CAT, "Hello");
+        p.language = Language::En;
+        let completion = block_on(MockProvider::default().generate(&p)).unwrap();
+
+        assert!(AnimalType::CAT.catchphrases(Language::En)
+            .iter()
+            .any(|line| completion.text.starts_with(line)));
+    }
+
+    #[test]
+    fn custom_persona_catchphrases_replace_the_registry() {
+        let mut p = prompt(AnimalType::CAT, "Hola");
+        p.persona = Some(CustomPersona {
+            name: "Capibara".to_string(),
+            personality: "Muy tranquila.".to_string(),
+            catchphrases: vec!["¡Qué calma!".to_string()],
+        });
+        let completion = block_on(MockProvider::default().generate(&p)).unwrap();
+
+        assert!(completion.text.starts_with("¡Qué calma!"));
+    }
+
+    #[test]
+    fn candidates_are_distinct_rolls() {
+        let mut p = prompt(AnimalType::CHICKEN, "¿Qué hora es?");
+        let single = block_on(MockProvider::default().generate(&p)).unwrap();
+        p.candidates = 3;
+        let several = block_on(MockProvider::default().generate(&p)).unwrap();
+
+        assert_eq!(several.text, single.text);
+        assert_eq!(several.alternatives.len(), 2);
+        assert!(several.alternatives.iter().all(|alt| *alt != several.text));
+    }
+
+    #[test]
+    fn failure_spec_fails_first_then_answers() {
+        let mock = MockProvider::from_spec("503, network");
+        let p = prompt(AnimalType::DOG, "¿Paseo?");
+
+        assert!(matches!(
+            block_on(mock.generate(&p)),
+            Err(ProviderError::Status { status: 503, .. })
+        ));
+        assert!(matches!(block_on(mock.generate(&p)), Err(ProviderError::Network(_))));
+        assert!(block_on(mock.generate(&p)).is_ok());
+        assert_eq!(mock.calls(), 3);
+    }
+
+    #[test]
+    fn stream_reassembles_to_generated_text() {
+        let p = prompt(AnimalType::OCTOPUS, "¿Existe el libre albedrío?");
+        let completion = block_on(MockProvider::default().generate(&p)).unwrap();
+        let events: Vec<ChatStreamEvent> =
+            block_on(async { MockProvider::default().stream(&p).await.unwrap().collect().await });
+
+        let streamed: String = events
+            .iter()
+            .filter_map(|e| match e {
+                ChatStreamEvent::Delta { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(streamed, completion.text);
+        assert_eq!(
+            events.last(),
+            Some(&ChatStreamEvent::Done {
+                tokens_used: completion.tokens_used,
+                model: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! LLM provider abstraction.
+//!
+//! Chat handlers only talk to [`LlmProvider`]; the backend is picked at
+//! runtime from the `LLM_PROVIDER` setting. Providers reach the network
+//! through the host's [`HttpClient`].
+
+pub mod gemini;
+pub mod mock;
+pub mod ollama;
+pub mod openai;
+pub mod resilience;
+
+use std::rc::Rc;
+
+use futures_util::stream::{self, LocalBoxStream};
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use shared::{
+    AnimalType, ApiErrorCode, ChatMessage, ChatStreamEvent, CustomPersona, IntelligenceLevel,
+    Language,
+};
+
+use crate::Settings;
+
+use self::gemini::GeminiProvider;
+use self::mock::MockProvider;
+use self::ollama::OllamaProvider;
+use self::openai::OpenAiProvider;
+use self::resilience::{shared_breaker, Resilient, RetryPolicy, Timer};
+
+/// Streamed reply, already translated into our own event vocabulary.
+pub type EventStream = LocalBoxStream<'static, ChatStreamEvent>;
+
+/// Raw upstream response body, chunk by chunk.
+pub type ByteStream = LocalBoxStream<'static, Result<Vec<u8>, String>>;
+
+/// Everything a provider needs to produce a reply.
+#[derive(Debug, Clone)]
+pub struct ChatPrompt {
+    pub animal: AnimalType,
+    pub intelligence: IntelligenceLevel,
+    pub language: Language,
+    pub system: String,
+    pub history: Vec<ChatMessage>,
+    pub message: String,
+    /// Replies wanted from `generate` (at least 1); streaming always produces one.
+    pub candidates: u8,
+    /// User-defined persona standing in for `animal`, already validated.
+    pub persona: Option<CustomPersona>,
+}
+
+/// A complete (non-streamed) reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub text: String,
+    pub tokens_used: Option<u32>,
+    /// The other candidates when `ChatPrompt::candidates` asked for more than one.
+    pub alternatives: Vec<String>,
+}
+
+/// Why a provider call failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderError {
+    /// The upstream answered with a non-200 status.
+    Status {
+        status: u16,
+        /// Seconds from the upstream `Retry-After` header.
+        retry_after: Option<u32>,
+        body: String,
+    },
+    /// No response at all (DNS, connection reset, timeout...).
+    Network(String),
+    /// The circuit breaker is open; the upstream was not called.
+    CircuitOpen { retry_after: u32 },
+    /// The client disconnected and the upstream request was aborted. Never retried.
+    Cancelled,
+    /// Anything else, e.g. an unparseable payload. Never retried.
+    Other(String),
+}
+
+impl ProviderError {
+    /// Transient failures worth another attempt.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProviderError::Status { status, .. } => matches!(status, 429 | 500 | 503),
+            ProviderError::Network(_) => true,
+            ProviderError::CircuitOpen { .. }
+            | ProviderError::Cancelled
+            | ProviderError::Other(_) => false,
+        }
+    }
+}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        ProviderError::Other(message)
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::Status { status, body, .. } => {
+                write!(f, "Upstream returned status {status}: {body}")
+            }
+            ProviderError::Network(e) => write!(f, "Fetch failed: {e}"),
+            ProviderError::CircuitOpen { retry_after } => {
+                write!(f, "Circuit open after repeated failures, retry in {retry_after}s")
+            }
+            ProviderError::Cancelled => f.write_str("Request cancelled by the client"),
+            ProviderError::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+pub trait LlmProvider {
+    /// Model identifier reported in logs.
+    fn model(&self) -> &str;
+
+    /// Generate the full reply in one call.
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError>;
+
+    /// Generate the reply as a stream of `delta` events ending in `done` or `error`.
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError>;
+}
+
+// ═══════════════════════════════════════════════
+// Provider Selection
+// ═══════════════════════════════════════════════
+
+/// Builds the provider named by the `LLM_PROVIDER` setting (defaults to
+/// Gemini), wrapped in retries and this thread's circuit breaker.
+pub fn from_settings<T: Timer + 'static>(
+    settings: &dyn Settings,
+    http: Rc<dyn HttpClient>,
+    timer: T,
+) -> Result<Box<dyn LlmProvider>, String> {
+    let kind = var_or(settings, "LLM_PROVIDER", "gemini");
+
+    let provider: Box<dyn LlmProvider> = match kind.as_str() {
+        "gemini" => {
+            let api_key = secret(settings, "GEMINI_API_KEY")?;
+            Box::new(GeminiProvider::new(http, api_key))
+        }
+        "openai" => {
+            let api_key = secret(settings, "OPENAI_API_KEY")?;
+            Box::new(OpenAiProvider::new(
+                http,
+                var_or(settings, "OPENAI_BASE_URL", openai::DEFAULT_BASE_URL),
+                var_or(settings, "OPENAI_MODEL", openai::DEFAULT_MODEL),
+                api_key,
+            ))
+        }
+        "ollama" => Box::new(OllamaProvider::new(
+            http,
+            var_or(settings, "OLLAMA_BASE_URL", ollama::DEFAULT_BASE_URL),
+            var_or(settings, "OLLAMA_MODEL", ollama::DEFAULT_MODEL),
+        )),
+        // llama.cpp's `llama-server` speaks the OpenAI protocol; the key is
+        // only needed when it was started with `--api-key`.
+        "llamacpp" => Box::new(OpenAiProvider::new(
+            http,
+            var_or(settings, "LLAMACPP_BASE_URL", openai::LLAMACPP_BASE_URL),
+            var_or(settings, "LLAMACPP_MODEL", openai::LLAMACPP_MODEL),
+            settings.secret("LLAMACPP_API_KEY").unwrap_or_default(),
+        )),
+        "mock" => Box::new(MockProvider::from_spec(&var_or(settings, "MOCK_FAILURES", ""))),
+        other => return Err(format!("Unknown LLM_PROVIDER '{other}'")),
+    };
+
+    Ok(Box::new(Resilient::new(
+        provider,
+        RetryPolicy::default(),
+        shared_breaker(),
+        timer,
+    )))
+}
+
+fn var_or(settings: &dyn Settings, name: &str, default: &str) -> String {
+    settings
+        .var(name)
+        .unwrap_or_else(|| default.to_string())
+}
+
+fn secret(settings: &dyn Settings, name: &str) -> Result<String, String> {
+    settings
+        .secret(name)
+        .ok_or_else(|| format!("{name} secret not configured"))
+}
+
+// ═══════════════════════════════════════════════
+// HTTP Helpers
+// ═══════════════════════════════════════════════
+
+/// The host's way of making outgoing requests: `fetch` on Workers, an HTTP
+/// client library natively.
+#[async_trait::async_trait(?Send)]
+pub trait HttpClient {
+    /// POSTs `body` (already JSON) to `url` and returns the body of a 200.
+    ///
+    /// Any other status is a [`ProviderError::Status`] (see [`status_error`]);
+    /// a request dropped because the client went away is
+    /// [`ProviderError::Cancelled`].
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: String,
+    ) -> Result<ByteStream, ProviderError>;
+}
+
+/// POSTs `body` as JSON to `url` and returns the response body if it is a 200.
+async fn post_json(
+    http: &dyn HttpClient,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &impl Serialize,
+) -> Result<ByteStream, ProviderError> {
+    let body =
+        serde_json::to_string(body).map_err(|e| format!("Failed to serialize request: {e}"))?;
+    http.post_json(url, headers, body).await
+}
+
+/// Reads a whole response body and parses it as JSON.
+async fn read_json<T: DeserializeOwned>(mut body: ByteStream) -> Result<T, ProviderError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.next().await {
+        bytes.extend(chunk.map_err(ProviderError::Network)?);
+    }
+    serde_json::from_slice(&bytes).map_err(|e| ProviderError::Other(e.to_string()))
+}
+
+/// The error for a non-200 upstream answer, honouring its `Retry-After`.
+pub fn status_error(status: u16, retry_after: Option<&str>, body: String) -> ProviderError {
+    ProviderError::Status {
+        status,
+        retry_after: retry_after.and_then(parse_retry_after),
+        body,
+    }
+}
+
+/// Reads a `Retry-After` value in delta-seconds (HTTP dates are ignored).
+fn parse_retry_after(value: &str) -> Option<u32> {
+    value.trim().parse().ok()
+}
+
+/// Converts a provider's raw SSE chunks into `ChatStreamEvent`s.
+trait StreamRelay {
+    /// Consume raw upstream bytes, returning any events they complete.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ChatStreamEvent>;
+
+    /// Final `done` event once the upstream body ends.
+    fn finish(self) -> ChatStreamEvent;
+}
+
+/// Pipes an upstream SSE body through `relay`.
+fn relay_stream<R: StreamRelay + 'static>(upstream: ByteStream, relay: R) -> EventStream {
+    stream::unfold(Some((upstream, relay)), |state| async move {
+        let (mut upstream, mut relay) = state?;
+        match upstream.next().await {
+            Some(Ok(chunk)) => {
+                let events = relay.feed(&chunk);
+                Some((events, Some((upstream, relay))))
+            }
+            Some(Err(_)) => {
+                let event = ChatStreamEvent::Error {
+                    code: ApiErrorCode::UpstreamUnavailable,
+                    message: "AI service unavailable".to_string(),
+                };
+                Some((vec![event], None))
+            }
+            None => Some((vec![relay.finish()], None)),
+        }
+    })
+    .flat_map(stream::iter)
+    .boxed_local()
+}
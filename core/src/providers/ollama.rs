@@ -0,0 +1,300 @@
+//! Local models through Ollama's native chat API (`/api/chat`).
+//!
+//! Conversations never leave the machine running the model. Streamed
+//! replies arrive as newline-delimited JSON rather than SSE.
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use shared::{ApiErrorCode, ChatStreamEvent, Role};
+
+use super::{
+    post_json, read_json, relay_stream, ChatPrompt, Completion, EventStream, HttpClient,
+    LlmProvider, ProviderError, StreamRelay,
+};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MODEL: &str = "llama3.2";
+
+pub struct OllamaProvider {
+    http: Rc<dyn HttpClient>,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: Rc<dyn HttpClient>, base_url: String, model: String) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("{}/api/chat", self.base_url)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl LlmProvider for OllamaProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Ollama has no `n`: alternatives are never produced.
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        let body = post_json(
+            &*self.http,
+            &self.url(),
+            &[],
+            &OllamaRequest::from_prompt(&self.model, prompt, false),
+        )
+        .await?;
+
+        let api_response: OllamaResponse = read_json(body)
+            .await
+            .map_err(|e| format!("Failed to parse Ollama response: {e}"))?;
+
+        if let Some(error) = api_response.error {
+            return Err(ProviderError::Other(format!("Ollama error: {error}")));
+        }
+
+        let tokens_used = api_response.tokens_used();
+        let text = api_response
+            .message
+            .map(|m| m.content)
+            .filter(|text| !text.is_empty())
+            .ok_or_else(|| "No response text from Ollama".to_string())?;
+
+        Ok(Completion {
+            text,
+            tokens_used,
+            alternatives: Vec::new(),
+        })
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        let upstream = post_json(
+            &*self.http,
+            &self.url(),
+            &[],
+            &OllamaRequest::from_prompt(&self.model, prompt, true),
+        )
+        .await?;
+
+        Ok(relay_stream(upstream, OllamaStreamRelay::default()))
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Wire Types
+// ═══════════════════════════════════════════════
+
+#[derive(Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    options: OllamaOptions,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    /// Ollama's name for `max_tokens`.
+    num_predict: u32,
+    temperature: f32,
+    top_p: f32,
+}
+
+/// A whole reply, or one line of a streamed one.
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+impl OllamaResponse {
+    fn tokens_used(&self) -> Option<u32> {
+        match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            (prompt, reply) => Some(prompt.unwrap_or(0) + reply.unwrap_or(0)),
+        }
+    }
+}
+
+impl<'a> OllamaRequest<'a> {
+    fn from_prompt(model: &'a str, prompt: &ChatPrompt, stream: bool) -> Self {
+        let mut messages = vec![OllamaMessage {
+            role: "system".to_string(),
+            content: prompt.system.clone(),
+        }];
+
+        messages.extend(prompt.history.iter().map(|msg| OllamaMessage {
+            role: match msg.role {
+                Role::User => "user".to_string(),
+                Role::Assistant => "assistant".to_string(),
+            },
+            content: msg.content.clone(),
+        }));
+
+        messages.push(OllamaMessage {
+            role: "user".to_string(),
+            content: prompt.message.clone(),
+        });
+
+        OllamaRequest {
+            model,
+            messages,
+            stream,
+            options: OllamaOptions {
+                num_predict: 1024,
+                temperature: 0.9,
+                top_p: 0.95,
+            },
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Streaming
+// ═══════════════════════════════════════════════
+
+/// Translates newline-delimited JSON chunks into `ChatStreamEvent`s.
+#[derive(Default)]
+struct OllamaStreamRelay {
+    /// Bytes of a line not yet terminated by `\n`.
+    buffer: Vec<u8>,
+    tokens_used: Option<u32>,
+}
+
+impl StreamRelay for OllamaStreamRelay {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ChatStreamEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let Ok(parsed) = serde_json::from_slice::<OllamaResponse>(&line) else {
+                continue;
+            };
+
+            if let Some(error) = parsed.error {
+                events.push(ChatStreamEvent::Error {
+                    code: ApiErrorCode::UpstreamUnavailable,
+                    message: format!("Ollama error: {error}"),
+                });
+                continue;
+            }
+
+            if parsed.done {
+                self.tokens_used = parsed.tokens_used();
+            }
+
+            if let Some(message) = parsed.message
+                && !message.content.is_empty()
+            {
+                events.push(ChatStreamEvent::Delta {
+                    text: message.content,
+                });
+            }
+        }
+        events
+    }
+
+    fn finish(self) -> ChatStreamEvent {
+        ChatStreamEvent::Done {
+            tokens_used: self.tokens_used,
+            model: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{AnimalType, ChatMessage, IntelligenceLevel, Language};
+
+    #[test]
+    fn request_maps_history_onto_chat_messages() {
+        let prompt = ChatPrompt {
+            animal: AnimalType::CAT,
+            intelligence: IntelligenceLevel::Low,
+            language: Language::En,
+            system: "You are a cat.".to_string(),
+            history: vec![
+                ChatMessage::new(Role::User, "Hi"),
+                ChatMessage::new(Role::Assistant, "Meow"),
+            ],
+            message: "Hungry?".to_string(),
+            candidates: 3,
+            persona: None,
+        };
+
+        let json = serde_json::to_value(OllamaRequest::from_prompt("m", &prompt, true)).unwrap();
+        let messages: Vec<(&str, &str)> = json["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| (m["role"].as_str().unwrap(), m["content"].as_str().unwrap()))
+            .collect();
+
+        assert_eq!(
+            messages,
+            [
+                ("system", "You are a cat."),
+                ("user", "Hi"),
+                ("assistant", "Meow"),
+                ("user", "Hungry?"),
+            ]
+        );
+        assert_eq!(json["stream"], true);
+        assert_eq!(json["options"]["num_predict"], 1024);
+    }
+
+    #[test]
+    fn relay_joins_lines_split_across_chunks() {
+        let upstream = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Mi\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"au\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,",
+            "\"prompt_eval_count\":20,\"eval_count\":4}\n",
+        );
+        let (first, second) = upstream.split_at(30);
+
+        let mut relay = OllamaStreamRelay::default();
+        let mut events = relay.feed(first.as_bytes());
+        events.extend(relay.feed(second.as_bytes()));
+        events.push(relay.finish());
+
+        assert_eq!(
+            events,
+            vec![
+                ChatStreamEvent::Delta { text: "Mi".to_string() },
+                ChatStreamEvent::Delta { text: "au".to_string() },
+                ChatStreamEvent::Done { tokens_used: Some(24), model: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn relay_reports_errors_from_the_model_server() {
+        let mut relay = OllamaStreamRelay::default();
+        let events = relay.feed(b"{\"error\":\"model 'llama3.2' not found\"}\n");
+
+        assert!(matches!(
+            events.as_slice(),
+            [ChatStreamEvent::Error { code: ApiErrorCode::UpstreamUnavailable, message }]
+                if message.contains("not found")
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+//! OpenAI-compatible backend (`/chat/completions`), usable with any vendor
+//! that speaks the same protocol.
+
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use shared::{ChatStreamEvent, Role, SseDecoder};
+
+use super::{
+    post_json, read_json, relay_stream, ChatPrompt, Completion, EventStream, HttpClient,
+    LlmProvider, ProviderError, StreamRelay,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_MODEL: &str = "gpt-4o-mini";
+/// Where `llama-server` listens by default.
+pub const LLAMACPP_BASE_URL: &str = "http://localhost:8080/v1";
+/// `llama-server` serves a single model and ignores the name.
+pub const LLAMACPP_MODEL: &str = "local";
+
+pub struct OpenAiProvider {
+    http: Rc<dyn HttpClient>,
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(http: Rc<dyn HttpClient>, base_url: String, model: String, api_key: String) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
+    }
+
+    /// `Bearer` credentials, omitted for keyless local servers.
+    fn authorization(&self) -> Option<String> {
+        (!self.api_key.is_empty()).then(|| format!("Bearer {}", self.api_key))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl LlmProvider for OpenAiProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        let authorization = self.authorization();
+        let headers: Vec<(&str, &str)> = authorization
+            .iter()
+            .map(|value| ("Authorization", value.as_str()))
+            .collect();
+        let body = post_json(
+            &*self.http,
+            &self.url(),
+            &headers,
+            &OpenAiRequest::from_prompt(&self.model, prompt, false),
+        )
+        .await?;
+
+        let api_response: OpenAiResponse = read_json(body)
+            .await
+            .map_err(|e| format!("Failed to parse OpenAI response: {e}"))?;
+
+        let tokens_used = api_response.usage.and_then(|u| u.total_tokens);
+
+        // One choice per requested candidate (`n`).
+        let mut texts = api_response
+            .choices
+            .into_iter()
+            .filter_map(|c| c.message.and_then(|m| m.content));
+        let text = texts
+            .next()
+            .ok_or_else(|| "No response text from OpenAI".to_string())?;
+
+        Ok(Completion {
+            text,
+            tokens_used,
+            alternatives: texts.collect(),
+        })
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        let authorization = self.authorization();
+        let headers: Vec<(&str, &str)> = authorization
+            .iter()
+            .map(|value| ("Authorization", value.as_str()))
+            .collect();
+        let upstream = post_json(
+            &*self.http,
+            &self.url(),
+            &headers,
+            &OpenAiRequest::from_prompt(&self.model, prompt, true),
+        )
+        .await?;
+
+        Ok(relay_stream(upstream, OpenAiStreamRelay::default()))
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Wire Types
+// ═══════════════════════════════════════════════
+
+#[derive(Serialize)]
+struct OpenAiRequest<'a> {
+    model: &'a str,
+    messages: Vec<OpenAiMessage>,
+    max_tokens: u32,
+    temperature: f32,
+    top_p: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u8>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAiMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    #[serde(default)]
+    choices: Vec<OpenAiChoice>,
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChoice {
+    /// Present on non-streamed responses.
+    message: Option<OpenAiDelta>,
+    /// Present on streamed chunks.
+    delta: Option<OpenAiDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiDelta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiUsage {
+    total_tokens: Option<u32>,
+}
+
+impl<'a> OpenAiRequest<'a> {
+    fn from_prompt(model: &'a str, prompt: &ChatPrompt, stream: bool) -> Self {
+        let mut messages = vec![OpenAiMessage {
+            role: "system".to_string(),
+            content: prompt.system.clone(),
+        }];
+
+        messages.extend(prompt.history.iter().map(|msg| OpenAiMessage {
+            role: match msg.role {
+                Role::User => "user".to_string(),
+                Role::Assistant => "assistant".to_string(),
+            },
+            content: msg.content.clone(),
+        }));
+
+        messages.push(OpenAiMessage {
+            role: "user".to_string(),
+            content: prompt.message.clone(),
+        });
+
+        OpenAiRequest {
+            model,
+            messages,
+            max_tokens: 1024,
+            temperature: 0.9,
+            top_p: 0.95,
+            n: (!stream && prompt.candidates > 1).then_some(prompt.candidates),
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Streaming
+// ═══════════════════════════════════════════════
+
+/// Translates `chat.completion.chunk` events into `ChatStreamEvent`s.
+#[derive(Default)]
+struct OpenAiStreamRelay {
+    decoder: SseDecoder,
+    tokens_used: Option<u32>,
+}
+
+impl StreamRelay for OpenAiStreamRelay {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ChatStreamEvent> {
+        let mut events = Vec::new();
+        for data in self.decoder.push(chunk) {
+            if data == "[DONE]" {
+                continue;
+            }
+            let Ok(parsed) = serde_json::from_str::<OpenAiResponse>(&data) else {
+                continue;
+            };
+
+            if let Some(total) = parsed.usage.and_then(|u| u.total_tokens) {
+                self.tokens_used = Some(total);
+            }
+
+            let text: String = parsed
+                .choices
+                .into_iter()
+                .filter_map(|c| c.delta.and_then(|d| d.content))
+                .collect();
+
+            if !text.is_empty() {
+                events.push(ChatStreamEvent::Delta { text });
+            }
+        }
+        events
+    }
+
+    fn finish(self) -> ChatStreamEvent {
+        ChatStreamEvent::Done {
+            tokens_used: self.tokens_used,
+            model: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{AnimalType, ChatMessage, IntelligenceLevel, Language};
+
+    #[test]
+    fn request_puts_system_prompt_first() {
+        let prompt = ChatPrompt {
+            animal: AnimalType::OCTOPUS,
+            intelligence: IntelligenceLevel::High,
+            language: Language::Es,
+            system: "Eres un pulpo.".to_string(),
+            history: vec![
+                ChatMessage::new(Role::User, "Hola"),
+                ChatMessage::new(Role::Assistant, "Glub"),
+            ],
+            message: "¿Qué es la tinta?".to_string(),
+            candidates: 1,
+            persona: None,
+        };
+
+        let json = serde_json::to_value(OpenAiRequest::from_prompt("m", &prompt, false)).unwrap();
+        let roles: Vec<&str> = json["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert!(json.get("stream_options").is_none());
+        assert!(json.get("n").is_none());
+    }
+
+    #[test]
+    fn relay_skips_done_marker_and_reports_usage() {
+        let upstream = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Glub\"}}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"total_tokens\":9}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut relay = OpenAiStreamRelay::default();
+        let mut events = relay.feed(upstream.as_bytes());
+        events.push(relay.finish());
+
+        assert_eq!(
+            events,
+            vec![
+                ChatStreamEvent::Delta { text: "Glub".to_string() },
+                ChatStreamEvent::Done { tokens_used: Some(9), model: None },
+            ]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Retries with jittered exponential backoff, plus a circuit breaker, around
+//! any [`LlmProvider`].
+//!
+//! Only the call that opens the upstream request is retried; once a stream
+//! has started, mid-stream failures are reported as `error` events instead.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+
+
+use super::{ChatPrompt, Completion, EventStream, LlmProvider, ProviderError};
+
+/// Consecutive failed requests that open the circuit.
+const BREAKER_THRESHOLD: u32 = 5;
+/// How long the circuit stays open before letting a trial request through.
+const BREAKER_COOLDOWN_MS: u64 = 30_000;
+
+thread_local! {
+    /// Shared by every request served on this thread (the whole isolate on Workers).
+    static UPSTREAM_BREAKER: Rc<CircuitBreaker> =
+        Rc::new(CircuitBreaker::new(BREAKER_THRESHOLD, BREAKER_COOLDOWN_MS));
+}
+
+/// This thread's breaker, so one failing upstream trips it for every request.
+pub fn shared_breaker() -> Rc<CircuitBreaker> {
+    UPSTREAM_BREAKER.with(Rc::clone)
+}
+
+// ═══════════════════════════════════════════════
+// Retry Policy
+// ═══════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    /// Upper bound for a single wait, also for an upstream `Retry-After`.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 250,
+            max_delay_ms: 4_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `attempt` (1-based): exponential, capped,
+    /// and "equal jitter" so concurrent clients spread out. `jitter` is in `[0, 1)`.
+    pub fn backoff_ms(&self, attempt: u32, jitter: f64) -> u64 {
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16));
+        let capped = exponential.min(self.max_delay_ms);
+        capped / 2 + (capped as f64 / 2.0 * jitter.clamp(0.0, 1.0)) as u64
+    }
+
+    /// How long to wait before retrying after `error`, or `None` to give up.
+    ///
+    /// A `Retry-After` longer than `max_delay_ms` is not waited out: the
+    /// error is returned so the client can be told when to come back.
+    fn delay_after(&self, error: &ProviderError, attempt: u32, jitter: f64) -> Option<u64> {
+        if !error.is_retryable() || attempt >= self.max_attempts {
+            return None;
+        }
+        match error {
+            ProviderError::Status {
+                retry_after: Some(seconds),
+                ..
+            } => {
+                let ms = u64::from(*seconds) * 1_000;
+                (ms <= self.max_delay_ms).then_some(ms)
+            }
+            _ => Some(self.backoff_ms(attempt, jitter)),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Circuit Breaker
+// ═══════════════════════════════════════════════
+
+/// Fails fast after `threshold` consecutive failed requests, for `cooldown_ms`.
+///
+/// When the cooldown ends the circuit is half-open: the next request is let
+/// through as a trial and the others keep failing fast. A success closes the
+/// circuit, a failure opens it again straight away, and a trial that never
+/// reports back (cancelled, or refused for its own reasons) is followed by
+/// another one after a further cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown_ms: u64,
+    failures: Cell<u32>,
+    open_until: Cell<Option<u64>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown_ms: u64) -> Self {
+        Self {
+            threshold,
+            cooldown_ms,
+            failures: Cell::new(0),
+            open_until: Cell::new(None),
+        }
+    }
+
+    /// `Err(seconds)` until the circuit may be tried again; `Ok` for the
+    /// caller that gets to run the trial.
+    pub fn check(&self, now_ms: u64) -> Result<(), u32> {
+        match self.open_until.get() {
+            Some(until) if now_ms < until => Err((until - now_ms).div_ceil(1_000) as u32),
+            Some(_) => {
+                // Half-open: everyone else waits for the trial's outcome.
+                self.open_until.set(Some(now_ms + self.cooldown_ms));
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.failures.set(0);
+        self.open_until.set(None);
+    }
+
+    pub fn record_failure(&self, now_ms: u64) {
+        let failures = self.failures.get().saturating_add(1);
+        self.failures.set(failures);
+        if failures >= self.threshold {
+            self.open_until.set(Some(now_ms + self.cooldown_ms));
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Timer
+// ═══════════════════════════════════════════════
+
+/// Clock, randomness and sleeping: provided by the host, swapped out in tests.
+#[async_trait::async_trait(?Send)]
+pub trait Timer {
+    fn now_ms(&self) -> u64;
+
+    /// Uniform random value in `[0, 1)`.
+    fn jitter(&self) -> f64;
+
+    async fn sleep(&self, ms: u64);
+}
+
+// ═══════════════════════════════════════════════
+// Resilient Provider
+// ═══════════════════════════════════════════════
+
+/// Wraps a provider with [`RetryPolicy`] and a shared [`CircuitBreaker`].
+pub struct Resilient<P, T> {
+    inner: P,
+    policy: RetryPolicy,
+    breaker: Rc<CircuitBreaker>,
+    timer: T,
+}
+
+impl<P: LlmProvider, T: Timer> Resilient<P, T> {
+    pub fn new(inner: P, policy: RetryPolicy, breaker: Rc<CircuitBreaker>, timer: T) -> Self {
+        Self {
+            inner,
+            policy,
+            breaker,
+            timer,
+        }
+    }
+
+    async fn call<R, F, Fut>(&self, op: F) -> Result<R, ProviderError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, ProviderError>>,
+    {
+        if let Err(retry_after) = self.breaker.check(self.timer.now_ms()) {
+            return Err(ProviderError::CircuitOpen { retry_after });
+        }
+
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(error) => match self.policy.delay_after(&error, attempt, self.timer.jitter()) {
+                    Some(delay) => {
+                        self.timer.sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => {
+                        if error.is_retryable() {
+                            self.breaker.record_failure(self.timer.now_ms());
+                        }
+                        return Err(error);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<P: LlmProvider, T: Timer> LlmProvider for Resilient<P, T> {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        self.call(|| self.inner.generate(prompt)).await
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        self.call(|| self.inner.stream(prompt)).await
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<P: LlmProvider + ?Sized> LlmProvider for Box<P> {
+    fn model(&self) -> &str {
+        (**self).model()
+    }
+
+    async fn generate(&self, prompt: &ChatPrompt) -> Result<Completion, ProviderError> {
+        (**self).generate(prompt).await
+    }
+
+    async fn stream(&self, prompt: &ChatPrompt) -> Result<EventStream, ProviderError> {
+        (**self).stream(prompt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use futures::executor::block_on;
+    use shared::{AnimalType, IntelligenceLevel, Language};
+    use std::cell::RefCell;
+
+    /// Virtual clock: sleeping advances time instantly and is recorded.
+    #[derive(Default)]
+    struct FakeTimer {
+        now: Cell<u64>,
+        sleeps: RefCell<Vec<u64>>,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl Timer for Rc<FakeTimer> {
+        fn now_ms(&self) -> u64 {
+            self.now.get()
+        }
+
+        fn jitter(&self) -> f64 {
+            0.5
+        }
+
+        async fn sleep(&self, ms: u64) {
+            self.sleeps.borrow_mut().push(ms);
+            self.now.set(self.now.get() + ms);
+        }
+    }
+
+    fn prompt() -> ChatPrompt {
+        ChatPrompt {
+            animal: AnimalType::DOG,
+            intelligence: IntelligenceLevel::Medium,
+            language: Language::En,
+            system: String::new(),
+            history: vec![],
+            message: "Fetch!".to_string(),
+            candidates: 1,
+            persona: None,
+        }
+    }
+
+    fn status(status: u16, retry_after: Option<u32>) -> ProviderError {
+        ProviderError::Status {
+            status,
+            retry_after,
+            body: "simulated".to_string(),
+        }
+    }
+
+    fn resilient(
+        failures: Vec<ProviderError>,
+        breaker: Rc<CircuitBreaker>,
+    ) -> (Resilient<MockProvider, Rc<FakeTimer>>, Rc<FakeTimer>) {
+        let timer = Rc::new(FakeTimer::default());
+        let provider = Resilient::new(
+            MockProvider::flaky(failures),
+            RetryPolicy::default(),
+            breaker,
+            timer.clone(),
+        );
+        (provider, timer)
+    }
+
+    fn fresh_breaker() -> Rc<CircuitBreaker> {
+        Rc::new(CircuitBreaker::new(BREAKER_THRESHOLD, BREAKER_COOLDOWN_MS))
+    }
+
+    #[test]
+    fn transient_failures_are_retried_with_growing_backoff() {
+        let failures = vec![status(503, None), ProviderError::Network("reset".to_string())];
+        let (provider, timer) = resilient(failures, fresh_breaker());
+
+        assert!(block_on(provider.generate(&prompt())).is_ok());
+        assert_eq!(provider.inner.calls(), 3);
+
+        let sleeps = timer.sleeps.borrow();
+        assert_eq!(sleeps.len(), 2);
+        assert!(sleeps[1] > sleeps[0], "{sleeps:?}");
+    }
+
+    #[test]
+    fn streams_are_retried_before_the_first_byte() {
+        let (provider, _) = resilient(vec![status(500, None)], fresh_breaker());
+
+        assert!(block_on(provider.stream(&prompt())).is_ok());
+        assert_eq!(provider.inner.calls(), 2);
+    }
+
+    #[test]
+    fn upstream_retry_after_is_honoured() {
+        let (provider, timer) = resilient(vec![status(429, Some(2))], fresh_breaker());
+
+        assert!(block_on(provider.generate(&prompt())).is_ok());
+        assert_eq!(*timer.sleeps.borrow(), vec![2_000]);
+    }
+
+    #[test]
+    fn long_retry_after_is_returned_instead_of_waited() {
+        let (provider, timer) = resilient(vec![status(429, Some(120))], fresh_breaker());
+
+        let error = block_on(provider.generate(&prompt())).unwrap_err();
+        assert_eq!(error, status(429, Some(120)));
+        assert_eq!(provider.inner.calls(), 1);
+        assert!(timer.sleeps.borrow().is_empty());
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let (provider, _) = resilient(vec![status(400, None)], fresh_breaker());
+
+        assert!(block_on(provider.generate(&prompt())).is_err());
+        assert_eq!(provider.inner.calls(), 1);
+    }
+
+    #[test]
+    fn cancelled_requests_are_not_retried_or_counted() {
+        let breaker = Rc::new(CircuitBreaker::new(1, 10_000));
+        let (provider, _) = resilient(vec![ProviderError::Cancelled], breaker.clone());
+
+        assert_eq!(block_on(provider.generate(&prompt())).unwrap_err(), ProviderError::Cancelled);
+        assert_eq!(provider.inner.calls(), 1);
+        assert_eq!(breaker.check(0), Ok(()));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let (provider, _) = resilient(vec![status(503, None); 5], fresh_breaker());
+
+        assert_eq!(block_on(provider.generate(&prompt())).unwrap_err(), status(503, None));
+        assert_eq!(provider.inner.calls(), RetryPolicy::default().max_attempts);
+    }
+
+    #[test]
+    fn breaker_opens_after_repeated_failures_and_recovers() {
+        let breaker = Rc::new(CircuitBreaker::new(2, 10_000));
+        let attempts = RetryPolicy::default().max_attempts as usize;
+        let (provider, timer) = resilient(vec![status(503, None); attempts * 2], breaker);
+
+        // Two exhausted requests open the circuit...
+        for _ in 0..2 {
+            assert!(block_on(provider.generate(&prompt())).is_err());
+        }
+        let calls = provider.inner.calls();
+
+        // ...so the next one fails fast without touching the upstream.
+        let error = block_on(provider.generate(&prompt())).unwrap_err();
+        assert!(matches!(error, ProviderError::CircuitOpen { retry_after } if retry_after > 0));
+        assert_eq!(provider.inner.calls(), calls);
+
+        // After the cooldown a trial request goes through and closes it.
+        timer.now.set(timer.now.get() + 10_000);
+        assert!(block_on(provider.generate(&prompt())).is_ok());
+        assert!(block_on(provider.generate(&prompt())).is_ok());
+    }
+
+    #[test]
+    fn half_open_circuit_lets_a_single_trial_through() {
+        let breaker = CircuitBreaker::new(1, 10_000);
+        breaker.record_failure(0);
+        assert_eq!(breaker.check(5_000), Err(5));
+
+        assert_eq!(breaker.check(10_000), Ok(()));
+        assert_eq!(breaker.check(10_001), Err(10));
+
+        // The trial failed: open again for a full cooldown.
+        breaker.record_failure(12_000);
+        assert_eq!(breaker.check(21_000), Err(1));
+
+        assert_eq!(breaker.check(22_000), Ok(()));
+        breaker.record_success();
+        assert_eq!(breaker.check(22_001), Ok(()));
+        assert_eq!(breaker.check(22_002), Ok(()));
+    }
+
+    #[test]
+    fn backoff_is_exponential_capped_and_jittered() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.backoff_ms(1, 0.0), 125);
+        assert_eq!(policy.backoff_ms(1, 1.0), 250);
+        assert_eq!(policy.backoff_ms(2, 1.0), 500);
+        assert_eq!(policy.backoff_ms(30, 1.0), policy.max_delay_ms);
+        assert_eq!(policy.backoff_ms(30, 0.0), policy.max_delay_ms / 2);
+    }
+}
@@ -0,0 +1,246 @@
+//! Per-client rate limiting: a per-minute burst limit plus a daily message budget.
+//!
+//! The counting rules live here; hosts pick the client key and the
+//! [`RateLimitStore`] (a Durable Object on Cloudflare, [`MemoryStore`] otherwise).
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Settings;
+
+const MINUTE_MS: u64 = 60_000;
+const DAY_MS: u64 = 86_400_000;
+
+const DEFAULT_PER_MINUTE: u32 = 10;
+const DEFAULT_PER_DAY: u32 = 200;
+
+// ═══════════════════════════════════════════════
+// Limits & Decisions
+// ═══════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    pub per_minute: u32,
+    pub per_day: u32,
+}
+
+impl RateLimits {
+    /// Reads `RATE_LIMIT_PER_MINUTE` / `RATE_LIMIT_PER_DAY`, with defaults.
+    pub fn from_settings(settings: &dyn Settings) -> Self {
+        let read = |name: &str, default: u32| {
+            settings
+                .var(name)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            per_minute: read("RATE_LIMIT_PER_MINUTE", DEFAULT_PER_MINUTE),
+            per_day: read("RATE_LIMIT_PER_DAY", DEFAULT_PER_DAY),
+        }
+    }
+}
+
+/// Which window a decision reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateScope {
+    Minute,
+    Day,
+}
+
+impl RateScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateScope::Minute => "minute",
+            RateScope::Day => "day",
+        }
+    }
+}
+
+/// Outcome of one acquire, reported on the most constrained window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateDecision {
+    pub allowed: bool,
+    pub scope: RateScope,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until that window resets.
+    pub reset_secs: u32,
+}
+
+/// Fixed-window counters for one client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counters {
+    minute_window: u64,
+    minute_count: u32,
+    day_window: u64,
+    day_count: u32,
+}
+
+impl Counters {
+    /// Counts one message at `now_ms` unless either window is full.
+    pub fn acquire(&mut self, limits: &RateLimits, now_ms: u64) -> RateDecision {
+        let minute = now_ms / MINUTE_MS;
+        let day = now_ms / DAY_MS;
+        if self.minute_window != minute {
+            self.minute_window = minute;
+            self.minute_count = 0;
+        }
+        if self.day_window != day {
+            self.day_window = day;
+            self.day_count = 0;
+        }
+
+        let allowed = self.minute_count < limits.per_minute && self.day_count < limits.per_day;
+        if allowed {
+            self.minute_count += 1;
+            self.day_count += 1;
+        }
+
+        let minute_left = limits.per_minute.saturating_sub(self.minute_count);
+        let day_left = limits.per_day.saturating_sub(self.day_count);
+        let reset_secs = |window_ms: u64| (window_ms - now_ms % window_ms).div_ceil(1_000) as u32;
+
+        if day_left <= minute_left {
+            RateDecision {
+                allowed,
+                scope: RateScope::Day,
+                limit: limits.per_day,
+                remaining: day_left,
+                reset_secs: reset_secs(DAY_MS),
+            }
+        } else {
+            RateDecision {
+                allowed,
+                scope: RateScope::Minute,
+                limit: limits.per_minute,
+                remaining: minute_left,
+                reset_secs: reset_secs(MINUTE_MS),
+            }
+        }
+    }
+
+    /// Start of the next day window, when the counters can be dropped.
+    pub fn expires_at_ms(&self) -> u64 {
+        (self.day_window + 1) * DAY_MS
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Storage
+// ═══════════════════════════════════════════════
+
+#[async_trait::async_trait(?Send)]
+pub trait RateLimitStore {
+    /// Atomically counts one message for `client`, or refuses it.
+    async fn acquire(
+        &self,
+        client: &str,
+        limits: &RateLimits,
+        now_ms: u64,
+    ) -> Result<RateDecision, String>;
+}
+
+/// Process-local counters: the native server's store, and the worker's
+/// fallback under `wrangler dev`.
+#[derive(Default)]
+pub struct MemoryStore {
+    counters: Mutex<HashMap<String, Counters>>,
+}
+
+impl MemoryStore {
+    /// [`RateLimitStore::acquire`] without the future, for multi-threaded hosts.
+    pub fn acquire_now(&self, client: &str, limits: &RateLimits, now_ms: u64) -> RateDecision {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget clients whose day window is over.
+        counters.retain(|_, c| c.expires_at_ms() > now_ms);
+        counters
+            .entry(client.to_string())
+            .or_default()
+            .acquire(limits, now_ms)
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl RateLimitStore for MemoryStore {
+    async fn acquire(
+        &self,
+        client: &str,
+        limits: &RateLimits,
+        now_ms: u64,
+    ) -> Result<RateDecision, String> {
+        Ok(self.acquire_now(client, limits, now_ms))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<S: RateLimitStore + ?Sized> RateLimitStore for Rc<S> {
+    async fn acquire(
+        &self,
+        client: &str,
+        limits: &RateLimits,
+        now_ms: u64,
+    ) -> Result<RateDecision, String> {
+        (**self).acquire(client, limits, now_ms).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    const LIMITS: RateLimits = RateLimits {
+        per_minute: 3,
+        per_day: 5,
+    };
+
+    #[test]
+    fn minute_burst_is_enforced_and_resets() {
+        let store = MemoryStore::default();
+        let acquire = |now| block_on(store.acquire("ip:1", &LIMITS, now)).unwrap();
+
+        for remaining in [2, 1, 0] {
+            let decision = acquire(1_000);
+            assert!(decision.allowed);
+            assert_eq!((decision.scope, decision.remaining), (RateScope::Minute, remaining));
+        }
+
+        let refused = acquire(15_000);
+        assert!(!refused.allowed);
+        assert_eq!((refused.scope, refused.limit), (RateScope::Minute, 3));
+        assert_eq!(refused.reset_secs, 45);
+
+        assert!(acquire(MINUTE_MS).allowed);
+    }
+
+    #[test]
+    fn daily_budget_outlasts_minute_windows() {
+        let store = MemoryStore::default();
+        let acquire = |now| block_on(store.acquire("token:a", &LIMITS, now)).unwrap();
+
+        for minute in 0..5 {
+            assert!(acquire(minute * MINUTE_MS).allowed);
+        }
+
+        let refused = acquire(10 * MINUTE_MS);
+        assert!(!refused.allowed);
+        assert_eq!((refused.scope, refused.remaining), (RateScope::Day, 0));
+        assert_eq!(refused.reset_secs as u64, (DAY_MS - 10 * MINUTE_MS) / 1_000);
+
+        assert!(acquire(DAY_MS).allowed);
+    }
+
+    #[test]
+    fn clients_are_counted_separately() {
+        let store = MemoryStore::default();
+        for _ in 0..3 {
+            assert!(block_on(store.acquire("ip:1", &LIMITS, 0)).unwrap().allowed);
+        }
+        assert!(!block_on(store.acquire("ip:1", &LIMITS, 0)).unwrap().allowed);
+        assert!(block_on(store.acquire("ip:2", &LIMITS, 0)).unwrap().allowed);
+    }
+}
@@ -0,0 +1,117 @@
+//! Automatic chat titles: after the first exchange the model names the chat
+//! in the animal's own voice, so the sidebar shows more than "New Conversation".
+
+use shared::{Language, TitleRequest, MAX_TITLE_LENGTH};
+
+use crate::providers::{ChatPrompt, LlmProvider, ProviderError};
+use crate::{build_custom_system_prompt, build_system_prompt, fence_safe};
+
+/// Asks the model for a title for the exchange in `request`.
+pub async fn generate(
+    provider: &dyn LlmProvider,
+    request: TitleRequest,
+) -> Result<String, ProviderError> {
+    let language = request.language;
+    let voice = match &request.persona {
+        Some(persona) => build_custom_system_prompt(persona, &request.intelligence, language, None),
+        None => build_system_prompt(&request.animal, &request.intelligence, language, None),
+    };
+    let (user, animal) = match language {
+        Language::Es => ("Usuario", "Tú"),
+        Language::En => ("User", "You"),
+    };
+
+    let prompt = ChatPrompt {
+        system: format!("{voice}\n\n{}", titler_instructions(language)),
+        animal: request.animal,
+        intelligence: request.intelligence,
+        language,
+        history: vec![],
+        message: format!(
+            "<conversation>\n{user}: {}\n{animal}: {}\n</conversation>",
+            fence_safe(&request.message),
+            fence_safe(&request.reply)
+        ),
+        candidates: 1,
+        persona: request.persona,
+    };
+
+    let completion = provider.generate(&prompt).await?;
+    clean_title(&completion.text)
+        .ok_or_else(|| ProviderError::Other("Empty chat title".to_string()))
+}
+
+/// First line of the model's answer, without wrapping quotes, a `Title:`
+/// label or a final full stop, cut to [`MAX_TITLE_LENGTH`] characters.
+pub fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = ["Título:", "Titulo:", "Title:"]
+        .iter()
+        .find_map(|label| line.strip_prefix(label))
+        .unwrap_or(line);
+    let line = line
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '«' | '»' | '“' | '”' | '*' | '#'))
+        .trim_end_matches('.')
+        .trim();
+
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_control()).collect();
+    let title = if chars.len() > MAX_TITLE_LENGTH {
+        let cut: String = chars[..MAX_TITLE_LENGTH - 1].iter().collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        chars.into_iter().collect()
+    };
+    (!title.is_empty()).then_some(title)
+}
+
+fn titler_instructions(language: Language) -> &'static str {
+    match language {
+        Language::Es => {
+            "El usuario te pasa el comienzo de vuestra conversación entre <conversation> y </conversation>. \
+             Ponle un título breve, de dos a seis palabras, en español y con tu propia voz de animal. \
+             Responde solo con el título, sin comillas ni punto final."
+        }
+        Language::En => {
+            "The user gives you the start of your conversation between <conversation> and </conversation>. \
+             Give it a short title, two to six words long, in English and in your own animal voice. \
+             Reply with the title only, without quotes or a final full stop."
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use futures::executor::block_on;
+    use shared::{AnimalType, IntelligenceLevel};
+
+    #[test]
+    fn titles_are_one_clean_line() {
+        assert_eq!(clean_title("\"Siesta al sol.\"\n\nMiau"), Some("Siesta al sol".to_string()));
+        assert_eq!(clean_title("  Title: **Fetch forever**"), Some("Fetch forever".to_string()));
+        assert_eq!(clean_title("  \n\"\"  "), None);
+
+        let long = clean_title(&"miau ".repeat(30)).unwrap();
+        assert_eq!(long.chars().count(), MAX_TITLE_LENGTH);
+        assert!(long.ends_with("miau…"));
+    }
+
+    #[test]
+    fn title_comes_from_the_model_in_one_call() {
+        let mock = MockProvider::default();
+        let request = TitleRequest {
+            message: "¿Te gusta dormir?".to_string(),
+            reply: "Miau, muchísimo.".to_string(),
+            animal: AnimalType::CAT,
+            intelligence: IntelligenceLevel::Medium,
+            language: Language::Es,
+            persona: None,
+        };
+
+        let title = block_on(generate(&mock, request)).unwrap();
+        assert!(!title.is_empty() && title.chars().count() <= MAX_TITLE_LENGTH);
+        assert_eq!(mock.calls(), 1);
+    }
+}
@@ -1,32 +1,29 @@
-use gloo_storage::{LocalStorage, Storage};
+use std::rc::Rc;
+
 use leptos::prelude::*;
-use serde::{Deserialize, Serialize};
-use shared::{AnimalType, ChatSession, Language};
+use leptos::task::spawn_local;
+use shared::{AppState, ChatSession, CustomAnimal, Language};
 use crate::i18n::get_translations;
+use crate::storage::{estimate_usage, Recovery, StorageUsage, Store, FLUSH_DELAY};
 
-use crate::components::chat_area::ChatArea;
+use crate::components::chat_area::{ChatArea, HighlightedMessage};
+use crate::components::persona_editor::{PersonaEditor, PersonaDraft};
+use crate::components::recovery_banner::RecoveryBanner;
 use crate::components::sidebar::Sidebar;
 use crate::components::update_banner::UpdateBanner;
 
-const STORAGE_KEY: &str = "ai_animal_chats_v1";
-
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
-pub struct AppState {
-    pub chats: Vec<ChatSession>,
-    pub active_chat_id: Option<String>,
-    #[serde(default)]
-    pub language: Language,
-}
-
 #[component]
 pub fn App() -> impl IntoView {
-    let initial_state: AppState = LocalStorage::get(STORAGE_KEY).unwrap_or_default();
-
-    let chats: RwSignal<Vec<ChatSession>> = RwSignal::new(initial_state.chats);
-    let active_chat_id: RwSignal<Option<String>> = RwSignal::new(initial_state.active_chat_id);
-    let language: RwSignal<Language> = RwSignal::new(initial_state.language);
+    let recovery: RwSignal<Option<Recovery>> = RwSignal::new(None);
+    let chats: RwSignal<Vec<ChatSession>> = RwSignal::new(Vec::new());
+    let active_chat_id: RwSignal<Option<String>> = RwSignal::new(None);
+    let language: RwSignal<Language> = RwSignal::new(Language::default());
+    let custom_animals: RwSignal<Vec<CustomAnimal>> = RwSignal::new(Vec::new());
+    let storage_usage: RwSignal<Option<StorageUsage>> = RwSignal::new(None);
+    let persona_draft: RwSignal<Option<PersonaDraft>> = RwSignal::new(None);
     let sidebar_open: RwSignal<bool> = RwSignal::new(false);
     let is_thinking: RwSignal<bool> = RwSignal::new(false);
+    let highlighted: RwSignal<Option<HighlightedMessage>> = RwSignal::new(None);
 
     let i18n = Memo::new(move |_| get_translations(language.get()));
 
@@ -38,32 +35,127 @@ pub fn App() -> impl IntoView {
             .unwrap_or_default()
     });
 
+    // The custom animal playing the active chat, if any (it may have been deleted).
+    let custom_animal = Memo::new(move |_| {
+        let custom_id = active_chat_id
+            .get()
+            .and_then(|id| chats.get().into_iter().find(|c| c.id == id))
+            .and_then(|c| c.custom_animal)?;
+        custom_animals.get().into_iter().find(|a| a.id == custom_id)
+    });
+
     provide_context(chats);
     provide_context(active_chat_id);
     provide_context(language);
     provide_context(sidebar_open);
     provide_context(is_thinking);
     provide_context(animal);
+    provide_context(custom_animals);
+    provide_context(custom_animal);
+    provide_context(persona_draft);
+    provide_context(recovery);
+    provide_context(storage_usage);
+    provide_context(highlighted);
     provide_context(i18n);
 
-    Effect::new(move || {
+    // Opening the store is async, so the signals start empty and nothing is
+    // saved until it has loaded.
+    let store: StoredValue<Option<Rc<Store>>, LocalStorage> = StoredValue::new_local(None);
+    spawn_local(async move {
+        let (opened, state, loaded_recovery) = Store::open().await;
+        chats.set(state.chats);
+        active_chat_id.set(state.active_chat_id);
+        language.set(state.language);
+        custom_animals.set(state.custom_animals);
+        recovery.set(loaded_recovery);
+        store.set_value(Some(Rc::new(opened)));
+        storage_usage.set(estimate_usage().await);
+    });
+
+    let flush = move || {
+        let Some(store) = store.get_value() else {
+            return;
+        };
+        // Without a backup, the unreadable data is the only copy: don't overwrite it yet.
+        if recovery.with_untracked(|r| r.as_ref().is_some_and(|r| r.backup_key.is_none())) {
+            return;
+        }
         let state = AppState {
-            chats: chats.get(),
-            active_chat_id: active_chat_id.get(),
-            language: language.get(),
+            chats: chats.get_untracked(),
+            active_chat_id: active_chat_id.get_untracked(),
+            language: language.get_untracked(),
+            custom_animals: custom_animals.get_untracked(),
         };
-        let _ = LocalStorage::set(STORAGE_KEY, state);
+        spawn_local(async move {
+            if let Err(e) = store.save(&state).await {
+                leptos::logging::error!("Failed to save chats: {e}");
+            }
+            storage_usage.set(estimate_usage().await);
+        });
+    };
+
+    // Debounced: each change restarts the timer, so bursts (like a streamed
+    // reply) are written once.
+    let pending: StoredValue<Option<TimeoutHandle>> = StoredValue::new(None);
+    Effect::new(move || {
+        chats.track();
+        active_chat_id.track();
+        language.track();
+        custom_animals.track();
+        recovery.track();
+        if let Some(handle) = pending.get_value() {
+            handle.clear();
+        }
+        let handle = set_timeout_with_handle(
+            move || {
+                pending.set_value(None);
+                flush();
+            },
+            FLUSH_DELAY,
+        );
+        pending.set_value(handle.ok());
+    });
+
+    // Write pending changes right away when the tab is hidden, as it may not come back.
+    let on_hidden = window_event_listener(leptos::ev::visibilitychange, move |_| {
+        if document().hidden()
+            && let Some(handle) = pending.get_value()
+        {
+            handle.clear();
+            pending.set_value(None);
+            flush();
+        }
     });
+    on_cleanup(move || on_hidden.remove());
 
     Effect::new(move || {
-        let theme = match animal.get() {
-            AnimalType::Cat => "cat",
-            AnimalType::Octopus => "octopus",
-            AnimalType::Elephant => "elephant",
-            AnimalType::Chicken => "chicken",
+        // Theme colours come from the animal registry (or the custom animal's
+        // accent) as inline custom properties.
+        let (theme, style) = match custom_animal.get() {
+            Some(custom) => (
+                "custom",
+                custom
+                    .css_vars()
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {value};"))
+                    .collect::<String>(),
+            ),
+            None => {
+                let animal = animal.get();
+                (
+                    animal.id(),
+                    animal
+                        .theme()
+                        .css_vars()
+                        .iter()
+                        .map(|(name, value)| format!("{name}: {value};"))
+                        .collect(),
+                )
+            }
         };
         if let Some(body) = document().body() {
             let _ = body.set_attribute("data-theme", theme);
+            let _ = body.set_attribute("style", &style);
         }
     });
 
@@ -72,6 +164,8 @@ pub fn App() -> impl IntoView {
             <Sidebar />
             <ChatArea />
             <UpdateBanner />
+            <RecoveryBanner />
+            <PersonaEditor />
         </div>
     }
 }
@@ -1,20 +1,182 @@
 use crate::components::animal_card::AnimalCard;
-use crate::components::chat_bubble::{ChatBubble, ThinkingBubble};
-use crate::config::api_base_url;
+use crate::components::chat_bubble::{BubbleAuthor, ChatBubble, ThinkingBubble};
+use crate::config::{api_base_url, client_token};
+use futures_util::StreamExt;
 use leptos::task::spawn_local;
 use leptos::prelude::*;
-use shared::{AnimalType, ChatSession, ChatMessage, Role, ChatRequest, ChatResponse, Language};
-use gloo_net::http::Request;
-use crate::i18n::Translations;
+use shared::{
+    AnimalType, ApiError, ApiErrorCode, Avatar, ChatSession, ChatMessage, CustomAnimal, Role,
+    ChatRequest, ChatResponse, ChatStreamEvent, ConversationSummary, Language, MessageMeta,
+    SseDecoder, TitleRequest, TitleResponse, offline_reply, outbox,
+};
+use gloo_net::http::{Request, Response};
+use crate::i18n::{format_wait, Translations};
+use crate::outbox::{is_online, request_background_sync, ConnectionStatus, FLUSH_EVENT};
+use std::time::Duration;
+
+/// Candidates asked for when regenerating, so there is more than one roll to flip through.
+const REGENERATE_CANDIDATES: u8 = 2;
+
+/// How long a highlighted message stays highlighted.
+const HIGHLIGHT_DURATION: Duration = Duration::from_millis(2500);
+
+/// A message to scroll to and flash, e.g. a search result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightedMessage(pub String);
 
 fn add_message(chats: RwSignal<Vec<ChatSession>>, chat_id: &str, msg: ChatMessage) {
     chats.update(|v| {
         if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id) {
             chat.messages.push(msg);
+            chat.touch();
+        }
+    });
+}
+
+/// Appends a streamed chunk to the last message of the chat.
+fn append_to_last_message(chats: RwSignal<Vec<ChatSession>>, chat_id: &str, chunk: &str) {
+    chats.update(|v| {
+        if let Some(last) = v
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .and_then(|c| c.messages.last_mut())
+        {
+            last.content.push_str(chunk);
+        }
+    });
+}
+
+/// Reads a `/api/chat/stream` body, calling `on_event` for every decoded event.
+async fn read_chat_stream(
+    res: Response,
+    mut on_event: impl FnMut(ChatStreamEvent),
+) -> Result<(), String> {
+    let body = res.body().ok_or("Empty response body")?;
+    let mut stream = wasm_streams::ReadableStream::from_raw(body).into_stream();
+    let mut decoder = SseDecoder::new();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Stream read failed: {e:?}"))?;
+        let bytes = js_sys::Uint8Array::new(&chunk).to_vec();
+        for data in decoder.push(&bytes) {
+            match serde_json::from_str::<ChatStreamEvent>(&data) {
+                Ok(event) => on_event(event),
+                Err(e) => return Err(format!("Malformed stream event: {e}")),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds a regenerated reply's answers as new versions of the chat's last
+/// message, showing the first of them. A failed request changes nothing and
+/// hands its error back to be shown: versions go out as history.
+fn apply_regenerated(
+    chat: &mut ChatSession,
+    result: Result<ChatResponse, ApiError>,
+) -> Result<ChatResponse, ApiError> {
+    let reply = result?;
+    if let Some(summary) = &reply.summary {
+        chat.summary = Some(summary.clone());
+    }
+    chat.touch();
+    if let Some(last) = chat.messages.last_mut() {
+        let first_new = last.versions();
+        for text in std::iter::once(&reply.response).chain(&reply.alternatives) {
+            last.push_version(text.as_str());
+        }
+        last.select_version(first_new);
+    }
+    Ok(reply)
+}
+
+/// Records which model wrote the chat's last reply, what it cost and how long it took.
+fn record_reply(
+    chats: RwSignal<Vec<ChatSession>>,
+    chat_id: &str,
+    model: Option<String>,
+    tokens_used: Option<u32>,
+    started_at: f64,
+) {
+    chats.update(|v| {
+        let Some(chat) = v.iter_mut().find(|c| c.id == chat_id) else {
+            return;
+        };
+        chat.touch();
+        if let Some(last) = chat.messages.last_mut() {
+            last.meta.model = model;
+            last.meta.tokens_used = tokens_used;
+            last.meta.latency_ms = Some((js_sys::Date::now() - started_at).max(0.0) as u32);
+            last.meta.offline = false;
+        }
+    });
+}
+
+/// Flags the chat's last message as a reply the user stopped.
+fn mark_last_cancelled(chats: RwSignal<Vec<ChatSession>>, chat_id: &str) {
+    chats.update(|v| {
+        if let Some(last) = v
+            .iter_mut()
+            .find(|c| c.id == chat_id)
+            .and_then(|c| c.messages.last_mut())
+        {
+            last.cancelled = true;
+        }
+    });
+}
+
+/// Caches a summary returned by the worker on the chat.
+fn store_summary(chats: RwSignal<Vec<ChatSession>>, chat_id: &str, summary: ConversationSummary) {
+    chats.update(|v| {
+        if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id) {
+            chat.summary = Some(summary);
         }
     });
 }
 
+/// Drops the last message if it is the user's, returning it (used when a send is refused).
+fn take_unanswered_message(chats: RwSignal<Vec<ChatSession>>, chat_id: &str) -> Option<ChatMessage> {
+    let mut taken = None;
+    chats.update(|v| {
+        if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id)
+            && chat.messages.last().is_some_and(|m| m.role == Role::User)
+        {
+            taken = chat.messages.pop();
+        }
+    });
+    taken
+}
+
+/// Asks the worker to name a chat after its first exchange; `None` on any failure.
+async fn fetch_title(req: &TitleRequest) -> Option<String> {
+    let api_url = format!("{}/title", api_base_url());
+    let res = Request::post(&api_url)
+        .header("X-Client-Token", &client_token())
+        .json(req)
+        .ok()?
+        .send()
+        .await
+        .ok()?;
+    if !res.ok() {
+        leptos::logging::warn!("Chat title request failed: HTTP {}", res.status());
+        return None;
+    }
+    res.json::<TitleResponse>().await.ok().map(|r| r.title)
+}
+
+/// Turns a failed request into an `ApiError`, reading the worker's JSON body when there is one.
+async fn response_error(response: Result<Response, gloo_net::Error>) -> ApiError {
+    match response {
+        Ok(res) => res.json::<ApiError>().await.unwrap_or_else(|_| {
+            ApiError::new(ApiErrorCode::UpstreamUnavailable, format!("HTTP {}", res.status()))
+        }),
+        Err(e) if !window().navigator().on_line() => {
+            ApiError::new(ApiErrorCode::Offline, e.to_string())
+        }
+        Err(e) => ApiError::new(ApiErrorCode::UpstreamUnavailable, e.to_string()),
+    }
+}
+
 /// Main chat area with messages, empty state, and input bar.
 #[component]
 pub fn ChatArea() -> impl IntoView {
@@ -22,86 +184,565 @@ pub fn ChatArea() -> impl IntoView {
     let active_chat_id = use_context::<RwSignal<Option<String>>>().expect("active_chat_id context");
     let sidebar_open = use_context::<RwSignal<bool>>().expect("sidebar_open context");
     let is_thinking = use_context::<RwSignal<bool>>().expect("is_thinking");
-    let _language = use_context::<RwSignal<Language>>().expect("language");
+    let language = use_context::<RwSignal<Language>>().expect("language");
     let i18n = use_context::<Memo<Translations>>().expect("i18n");
     
     let animal = use_context::<Memo<AnimalType>>().expect("AnimalType");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
+    let custom_animal = use_context::<Memo<Option<CustomAnimal>>>().expect("custom animal");
+    let highlighted = use_context::<RwSignal<Option<HighlightedMessage>>>().expect("highlighted message");
 
     let input_value = RwSignal::new(String::new());
 
+    // Rate-limit cooldown: sending is blocked until `cooldown_until` (epoch ms).
+    let cooldown_until = RwSignal::new(Option::<f64>::None);
+    let now = RwSignal::new(js_sys::Date::now());
+    let ticker = StoredValue::new(Option::<IntervalHandle>::None);
+    let cooldown_secs = Memo::new(move |_| {
+        cooldown_until
+            .get()
+            .map(|until| ((until - now.get()) / 1000.0).ceil() as i64)
+            .filter(|secs| *secs > 0)
+            .map(|secs| secs as u32)
+    });
+
+    let start_cooldown = move |seconds: u32| {
+        now.set(js_sys::Date::now());
+        cooldown_until.set(Some(js_sys::Date::now() + f64::from(seconds) * 1000.0));
+        if let Some(handle) = ticker.get_value() {
+            handle.clear();
+        }
+        let handle = set_interval_with_handle(
+            move || {
+                now.set(js_sys::Date::now());
+                if cooldown_secs.get_untracked().is_none() {
+                    cooldown_until.set(None);
+                    if let Some(handle) = ticker.get_value() {
+                        handle.clear();
+                    }
+                }
+            },
+            Duration::from_secs(1),
+        );
+        ticker.set_value(handle.ok());
+    };
+
     let active_chat = Memo::new(move |_| {
         let id = active_chat_id.get();
         chats.get().into_iter().find(|c| Some(c.id.clone()) == id)
     });
 
-    let send_message = move || {
-        let text = input_value.get();
-        if text.trim().is_empty() || is_thinking.get() {
+    // Scrolls to a highlighted message once it is on screen, and lets the
+    // highlight fade after a moment.
+    Effect::new(move || {
+        let Some(HighlightedMessage(id)) = highlighted.get() else {
             return;
-        }
-
-        let current_id = match active_chat_id.get() {
-            Some(id) => id,
-            None => return,
         };
+        let dom_id = format!("msg-{id}");
+        set_timeout(
+            move || {
+                if let Some(element) = document().get_element_by_id(&dom_id) {
+                    let options = web_sys::ScrollIntoViewOptions::new();
+                    options.set_behavior(web_sys::ScrollBehavior::Smooth);
+                    options.set_block(web_sys::ScrollLogicalPosition::Center);
+                    element.scroll_into_view_with_scroll_into_view_options(&options);
+                }
+            },
+            Duration::ZERO,
+        );
+        set_timeout(
+            move || {
+                if highlighted.get_untracked().is_some_and(|h| h.0 == id) {
+                    highlighted.set(None);
+                }
+            },
+            HIGHLIGHT_DURATION,
+        );
+    });
+
+    let persona_of = move |chat: &ChatSession| {
+        chat.custom_animal.as_ref().and_then(|custom_id| {
+            custom_animals.get_untracked().into_iter().find(|a| &a.id == custom_id).map(|a| a.persona)
+        })
+    };
 
-        let user_msg = ChatMessage {
-            role: Role::User,
-            content: text.clone(),
+    // Replaces the placeholder title once the first turn is answered,
+    // unless the user named the chat meanwhile.
+    let name_chat = move |chat_id: String| {
+        let Some(chat) = chats.with_untracked(|v| v.iter().find(|c| c.id == chat_id).cloned()) else {
+            return;
+        };
+        if !chat.wants_title() {
+            return;
+        }
+        let path = chat.messages.messages();
+        let req = TitleRequest {
+            message: path[0].content.clone(),
+            reply: path[1].content.clone(),
+            animal: chat.animal,
+            intelligence: chat.intelligence,
+            language: chat.language,
+            persona: persona_of(&chat),
         };
+        spawn_local(async move {
+            let Some(title) = fetch_title(&req).await else {
+                return;
+            };
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id)
+                    && !chat.renamed
+                {
+                    chat.title = title;
+                }
+            });
+        });
+    };
+
+    // Snapshot of who answers in `chat` right now, stored on each reply.
+    let reply_meta = move |chat: &ChatSession| MessageMeta {
+        animal: Some(chat.animal),
+        intelligence: Some(chat.intelligence),
+        custom_animal: chat.custom_animal.clone(),
+        persona_name: persona_of(chat).map(|p| p.name),
+        ..MessageMeta::now()
+    };
+
+    // The avatar of the animal that wrote a reply; replies saved without
+    // metadata are credited to the chat's current animal.
+    let author_of = move |meta: &MessageMeta, chat: &ChatSession| {
+        let custom_id = if meta.animal.is_some() { &meta.custom_animal } else { &chat.custom_animal };
+        let custom = custom_id.as_ref().and_then(|id| {
+            custom_animals.get().into_iter().find(|a| &a.id == id)
+        });
+        match (custom, &meta.persona_name) {
+            (Some(custom), _) => BubbleAuthor::Custom { name: custom.persona.name, avatar: custom.avatar },
+            // Deleted since: keep its name.
+            (None, Some(name)) => BubbleAuthor::Custom { name: name.clone(), avatar: Avatar::default() },
+            (None, None) => BubbleAuthor::Animal(meta.animal.unwrap_or(chat.animal)),
+        }
+    };
+
+    // Set while a regenerated answer is pending, to show the thinking bubble.
+    let regenerating = RwSignal::new(false);
+
+    // A failed request with no message to show it in; the next request clears it.
+    let request_error = RwSignal::new(Option::<String>::None);
+
+    // User message being edited; sending forks a new branch in its place.
+    let editing = RwSignal::new(Option::<String>::None);
+    let online = RwSignal::new(is_online());
+    let syncing = RwSignal::new(false);
+
+    // Aborts the request in flight; the worker drops its upstream call when we hang up.
+    let in_flight = StoredValue::new_local(Option::<web_sys::AbortController>::None);
+
+    let start_request = move || {
+        request_error.set(None);
+        let controller = web_sys::AbortController::new().expect("AbortController");
+        let signal = controller.signal();
+        in_flight.set_value(Some(controller));
+        signal
+    };
 
-        // 1. Add User Message
-        add_message(chats, &current_id, user_msg);
+    let stop = move || {
+        if let Some(controller) = in_flight.get_value() {
+            controller.abort();
+        }
+    };
 
+    let update_active_chat = move |f: &dyn Fn(&mut ChatSession)| {
+        if let Some(current_id) = active_chat_id.get_untracked() {
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == current_id) {
+                    f(chat);
+                }
+            });
+        }
+    };
+
+    let select_version = move |message_id: &str, version: usize| {
+        update_active_chat(&|chat| {
+            if let Some(msg) = chat.messages.message_mut(message_id) {
+                msg.select_version(version);
+            }
+        });
+    };
+
+    let switch_branch = move |message_id: &str| {
+        update_active_chat(&|chat| chat.switch_branch(message_id));
+    };
+
+    let start_editing = move |message_id: String, content: String| {
+        editing.set(Some(message_id));
+        input_value.set(content);
+    };
+
+    let cancel_editing = move || {
+        editing.set(None);
         input_value.set(String::new());
+    };
+
+    // Switching chats abandons an edit in progress.
+    Effect::new(move |_| {
+        active_chat_id.track();
+        if editing.get_untracked().is_some() {
+            cancel_editing();
+        }
+    });
+
+    // Re-asks the last question and keeps the new answers next to the old one.
+    let regenerate = move || {
+        if is_thinking.get_untracked() || cooldown_secs.get_untracked().is_some() {
+            return;
+        }
+        let Some(chat) = active_chat.get_untracked() else {
+            return;
+        };
+        let path = chat.messages.messages();
+        let len = path.len();
+        if len < 2 || path[len - 1].role != Role::Assistant || path[len - 2].role != Role::User {
+            return;
+        }
+
+        let (history, summary) = chat.request_history(len - 2);
+        let req = ChatRequest {
+            message: path[len - 2].content.clone(),
+            animal: chat.animal,
+            intelligence: chat.intelligence,
+            language: chat.language,
+            history,
+            candidates: Some(REGENERATE_CANDIDATES),
+            summary,
+            persona: persona_of(&chat),
+        };
+        let chat_id = chat.id;
+
         is_thinking.set(true);
+        regenerating.set(true);
+        let signal = start_request();
+        let started_at = js_sys::Date::now();
 
         spawn_local(async move {
-            let chat_opt = chats.get().into_iter().find(|c| c.id == current_id);
-            if let Some(chat) = chat_opt {
-                let req = ChatRequest {
-                    message: text,
-                    animal: chat.animal,
-                    intelligence: chat.intelligence,
-                    history: chat.messages[..chat.messages.len()-1].to_vec(),
-                };
-
-                let api_url = format!("{}/chat", api_base_url());
-                let response = Request::post(&api_url)
-                    .json(&req)
-                    .expect("Failed to serialize request")
-                    .send()
-                    .await;
-
-                match response {
-                    Ok(res) if res.ok() => {
-                        if let Ok(data) = res.json::<ChatResponse>().await {
-                            let assistant_msg = ChatMessage {
-                                role: Role::Assistant,
-                                content: data.response,
-                            };
+            let api_url = format!("{}/chat", api_base_url());
+            let response = Request::post(&api_url)
+                .header("X-Client-Token", &client_token())
+                .abort_signal(Some(&signal))
+                .json(&req)
+                .expect("Failed to serialize request")
+                .send()
+                .await;
+
+            let result = match response {
+                Ok(res) if res.ok() => res
+                    .json::<ChatResponse>()
+                    .await
+                    .map_err(|e| ApiError::new(ApiErrorCode::UpstreamUnavailable, e.to_string())),
+                other => Err(response_error(other).await),
+            };
+
+            match result {
+                // Stopped: the versions already there are left as they were.
+                Err(_) if signal.aborted() => {}
+                Err(error) if error.code == ApiErrorCode::RateLimited => {
+                    start_cooldown(error.retry_after.unwrap_or(60));
+                }
+                result => {
+                    let mut applied = None;
+                    chats.update(|v| {
+                        if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id) {
+                            applied = Some(apply_regenerated(chat, result));
+                        }
+                    });
+                    match applied {
+                        Some(Ok(reply)) => {
+                            record_reply(chats, &chat_id, reply.model, reply.tokens_used, started_at);
+                        }
+                        Some(Err(error)) => {
+                            request_error.set(Some(i18n.get_untracked().api_error(&error)));
+                        }
+                        // The chat was deleted meanwhile.
+                        None => {}
+                    }
+                }
+            }
+            in_flight.set_value(None);
+            regenerating.set(false);
+            is_thinking.set(false);
+        });
+    };
+
+    // Streams the reply to the user message that ends `current_id`'s active
+    // branch. Resolves to `false` when it could not be sent and went back to
+    // the outbox. The caller sets `is_thinking`.
+    let request_reply = move |current_id: String, queued: bool| async move {
+        let chat = chats.with_untracked(|v| v.iter().find(|c| c.id == current_id).cloned());
+        let Some((chat, text)) = chat.and_then(|chat| {
+            let text = chat.messages.last().filter(|m| m.role == Role::User)?.content.clone();
+            Some((chat, text))
+        }) else {
+            is_thinking.set(false);
+            return true;
+        };
+        let signal = start_request();
+        let started_at = js_sys::Date::now();
+        let mut sent = true;
+
+        let (history, summary) = chat.request_history(chat.messages.len() - 1);
+        let req = ChatRequest {
+            message: text.clone(),
+            animal: chat.animal,
+            intelligence: chat.intelligence,
+            language: chat.language,
+            history,
+            candidates: None,
+            summary,
+            persona: persona_of(&chat),
+        };
+        let meta = reply_meta(&chat);
+
+        let api_url = format!("{}/chat/stream", api_base_url());
+        let response = Request::post(&api_url)
+            .header("X-Client-Token", &client_token())
+            .abort_signal(Some(&signal))
+            .json(&req)
+            .expect("Failed to serialize request")
+            .send()
+            .await;
+
+        // The assistant bubble is created on the first chunk and grows in place.
+        let mut started = false;
+        let mut failure: Option<ApiError> = None;
+
+        match response {
+            Ok(res) if res.ok() => {
+                let result = read_chat_stream(res, |event| match event {
+                    ChatStreamEvent::Summary { summary } => {
+                        store_summary(chats, &current_id, summary);
+                    }
+                    ChatStreamEvent::Delta { text } => {
+                        if started {
+                            append_to_last_message(chats, &current_id, &text);
+                        } else {
+                            started = true;
                             // 2. Add Assistant Message
-                            add_message(chats, &current_id, assistant_msg);
+                            let reply = ChatMessage::new(Role::Assistant, text).with_meta(meta.clone());
+                            add_message(chats, &current_id, reply);
                         }
                     }
-                    _ => {
-                        let error_msg = ChatMessage {
-                            role: Role::Assistant,
-                            content: i18n.get().error_message.to_string(),
-                        };
-                        // 3. Add Error Message
-                        add_message(chats, &current_id, error_msg);
+                    ChatStreamEvent::Done { tokens_used, model } => {
+                        if started {
+                            record_reply(chats, &current_id, model, tokens_used, started_at);
+                        }
                     }
+                    ChatStreamEvent::Error { code, message } => {
+                        failure = Some(ApiError::new(code, message));
+                    }
+                })
+                .await;
+                if failure.is_none() && (result.is_err() || !started) {
+                    let message = result.err().unwrap_or_else(|| "Empty stream".to_string());
+                    failure = Some(ApiError::new(ApiErrorCode::UpstreamUnavailable, message));
                 }
             }
-            is_thinking.set(false);
+            other => failure = Some(response_error(other).await),
+        }
+
+        let unsent = failure.as_ref().filter(|_| !started).map(|e| e.code);
+        if signal.aborted() {
+            // Stopped: keep what arrived, or an empty reply so turns still alternate.
+            if started {
+                mark_last_cancelled(chats, &current_id);
+            } else {
+                let empty = ChatMessage::new(Role::Assistant, "").with_meta(meta);
+                add_message(chats, &current_id, ChatMessage { cancelled: true, ..empty });
+            }
+        } else if unsent == Some(ApiErrorCode::Offline) || (queued && unsent == Some(ApiErrorCode::RateLimited)) {
+            // Nothing was answered: the message waits in the outbox.
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == current_id) {
+                    chat.requeue();
+                }
+            });
+            if let Some(error) = failure.as_ref().filter(|e| e.code == ApiErrorCode::RateLimited) {
+                start_cooldown(error.retry_after.unwrap_or(60));
+            } else {
+                online.set(is_online());
+                request_background_sync();
+            }
+            sent = false;
+        } else if let Some(error) = failure.as_ref().filter(|_| unsent == Some(ApiErrorCode::RateLimited)) {
+            // Nothing was answered: hand the message back and count down instead.
+            if take_unanswered_message(chats, &current_id).is_some() {
+                input_value.set(text);
+            }
+            start_cooldown(error.retry_after.unwrap_or(60));
+        } else if let Some(error) = failure {
+            let error_text = i18n.get_untracked().api_error(&error);
+            if started {
+                // Keep the partial reply and flag the interruption inline.
+                append_to_last_message(chats, &current_id, &format!("\n\n{error_text}"));
+            } else {
+                let error_msg = ChatMessage::new(Role::Assistant, error_text).with_meta(meta);
+                // 3. Add Error Message
+                add_message(chats, &current_id, error_msg);
+            }
+        } else {
+            name_chat(current_id.clone());
+        }
+        in_flight.set_value(None);
+        is_thinking.set(false);
+        sent
+    };
+
+    // Sends the outbox, oldest first, stopping at the first message that has
+    // to wait again.
+    let flush_outbox = move || {
+        if syncing.get_untracked()
+            || is_thinking.get_untracked()
+            || cooldown_secs.get_untracked().is_some()
+            || !is_online()
+        {
+            return;
+        }
+        syncing.set(true);
+        spawn_local(async move {
+            while let Some(entry) = chats.with_untracked(|v| outbox(v).into_iter().next()) {
+                let mut taken = false;
+                chats.update(|v| {
+                    if let Some(chat) = v.iter_mut().find(|c| c.id == entry.chat_id) {
+                        taken = chat.take_pending().is_some();
+                    }
+                });
+                if !taken {
+                    break;
+                }
+                is_thinking.set(true);
+                if !request_reply(entry.chat_id, true).await {
+                    break;
+                }
+            }
+            syncing.set(false);
+        });
+    };
+
+    // Answers the pending message from the persona engine instead of waiting
+    // for the connection; the reply is tagged and can be re-asked later.
+    let answer_offline = move || {
+        update_active_chat(&|chat| {
+            let Some(text) = chat.take_pending() else {
+                return;
+            };
+            let reply = offline_reply(chat, persona_of(chat).as_ref(), &text);
+            let meta = MessageMeta { offline: true, ..reply_meta(chat) };
+            chat.messages.push(ChatMessage::new(Role::Assistant, reply).with_meta(meta));
+            chat.touch();
+        });
+    };
+
+    let send_message = move || {
+        let text = input_value.get();
+        if text.trim().is_empty() || is_thinking.get() || cooldown_secs.get().is_some() {
+            return;
+        }
+
+        let current_id = match active_chat_id.get() {
+            Some(id) => id,
+            None => return,
+        };
+        input_value.set(String::new());
+
+        // Offline, or behind a message still waiting: it goes to the outbox.
+        let queue = !is_online()
+            || chats.with(|v| v.iter().any(|c| c.id == current_id && c.pending_message().is_some()));
+
+        // 1. Add User Message (on a new branch when editing an earlier one)
+        let mut forked = false;
+        if let Some(edited_id) = editing.get() {
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == current_id) {
+                    forked = chat.edit_message(&edited_id, text.clone()).is_some();
+                    if forked && queue {
+                        chat.requeue();
+                    }
+                }
+            });
+            editing.set(None);
+        }
+        if !forked && queue {
+            let mut queued = false;
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == current_id) {
+                    queued = chat.queue_message(&text, MessageMeta::now());
+                }
+            });
+            if !queued {
+                // The pending message is full: keep this one in the input box.
+                input_value.set(text);
+                request_error.set(Some(i18n.get_untracked().outbox_full.to_string()));
+                return;
+            }
+        } else if !forked {
+            let user_msg = ChatMessage::new(Role::User, text.clone()).with_meta(MessageMeta::now());
+            add_message(chats, &current_id, user_msg);
+        }
+
+        if queue {
+            online.set(is_online());
+            request_background_sync();
+            flush_outbox();
+            return;
+        }
+
+        is_thinking.set(true);
+        spawn_local(async move {
+            request_reply(current_id, false).await;
         });
     };
 
+    // Back online (or woken by Background Sync): send what was queued.
+    let on_online = window_event_listener(leptos::ev::online, move |_| {
+        online.set(true);
+        flush_outbox();
+    });
+    let on_offline = window_event_listener(leptos::ev::offline, move |_| online.set(false));
+    let on_sync = window_event_listener_untyped(FLUSH_EVENT, move |_| flush_outbox());
+    on_cleanup(move || {
+        on_online.remove();
+        on_offline.remove();
+        on_sync.remove();
+    });
+
+    // Messages left queued by a previous visit go out once the chats have loaded.
+    Effect::new(move |flushed: Option<bool>| {
+        if flushed == Some(true) {
+            return true;
+        }
+        let loaded = chats.with(|v| !v.is_empty());
+        if loaded {
+            flush_outbox();
+        }
+        loaded
+    });
+
+    // Once a rate-limit wait ends, queued messages can try again.
+    Effect::new(move |was_waiting: Option<bool>| {
+        let waiting = cooldown_secs.get().is_some();
+        if was_waiting == Some(true) && !waiting {
+            flush_outbox();
+        }
+        waiting
+    });
+
+    let connection = Memo::new(move |_| match (online.get(), syncing.get()) {
+        (false, _) => ConnectionStatus::Offline,
+        (true, true) => ConnectionStatus::Syncing,
+        (true, false) => ConnectionStatus::Online,
+    });
+
     view! {
         <main class="chat-area">
-            // Header bar (mobile only)
-            <div class="header-bar">
+            // Header bar (mobile only, or on any screen while offline or syncing)
+            <div class="header-bar" class:connection-degraded=move || connection.get() != ConnectionStatus::Online>
                 <button
                     class="hamburger-btn"
                     on:click=move |_| {
@@ -114,6 +755,24 @@ pub fn ChatArea() -> impl IntoView {
                     <span class="material-symbols-outlined">{"menu"}</span>
                 </button>
                 <h1>{move || i18n.get().app_title}</h1>
+                <span
+                    class="connection-status"
+                    class:offline=move || connection.get() == ConnectionStatus::Offline
+                    class:syncing=move || connection.get() == ConnectionStatus::Syncing
+                    role="status"
+                    title=move || i18n.get().connection_label(connection.get())
+                >
+                    <span class="material-symbols-outlined">
+                        {move || match connection.get() {
+                            ConnectionStatus::Online => "cloud_done",
+                            ConnectionStatus::Offline => "cloud_off",
+                            ConnectionStatus::Syncing => "cloud_sync",
+                        }}
+                    </span>
+                    <span class="connection-status-label">
+                        {move || i18n.get().connection_label(connection.get())}
+                    </span>
+                </span>
             </div>
 
             // Animal watermark
@@ -127,13 +786,11 @@ pub fn ChatArea() -> impl IntoView {
                             view! {
                                 <div class="empty-state">
                                     <div class="empty-state-title">
-                                        {move || {
-                                            match animal.get() {
-                                                AnimalType::Cat => i18n.get().cat_sound,
-                                                AnimalType::Octopus => i18n.get().octopus_sound,
-                                                AnimalType::Elephant => i18n.get().elephant_sound,
-                                                AnimalType::Chicken => i18n.get().chicken_sound,
-                                            }
+                                        {move || match custom_animal.get() {
+                                            // Custom animals greet with their first catchphrase.
+                                            Some(custom) => custom.persona.catchphrases.into_iter().next()
+                                                .unwrap_or(custom.persona.name),
+                                            None => animal.get().sound(language.get()).to_string(),
                                         }}
                                     </div>
                                     <div class="empty-state-subtitle">
@@ -142,13 +799,74 @@ pub fn ChatArea() -> impl IntoView {
                                 </div>
                             }.into_any()
                         } else {
-                            chat.messages.into_iter().map(|msg| {
-                                let role_str = match msg.role {
-                                    Role::User => "user",
-                                    Role::Assistant => "assistant",
-                                };
-                                view! {
-                                    <ChatBubble role=role_str.to_string() content=msg.content />
+                            let path = chat.messages.path();
+                            let last = path.len() - 1;
+                            let idle = !is_thinking.get();
+                            path.into_iter().enumerate().map(|(index, node)| {
+                                let id = node.id().to_string();
+                                let msg = node.message.clone();
+                                let dom_id = format!("msg-{id}");
+                                let highlight_id = id.clone();
+                                let is_highlighted = Signal::derive(move || {
+                                    highlighted.with(|h| h.as_ref().is_some_and(|h| h.0 == highlight_id))
+                                });
+                                match msg.role {
+                                    // User messages: edit, and flip between the branches forked here.
+                                    Role::User => {
+                                        let siblings: Vec<String> = chat.messages.siblings(&id)
+                                            .into_iter().map(String::from).collect();
+                                        let selected = siblings.iter().position(|s| *s == id).unwrap_or(0);
+                                        let content = msg.content.clone();
+                                        let on_edit = idle.then(|| Callback::new(move |_| {
+                                            start_editing(id.clone(), content.clone())
+                                        }));
+                                        let on_answer_offline = (msg.pending && index == last)
+                                            .then(|| Callback::new(move |_| answer_offline()));
+                                        view! {
+                                            <ChatBubble
+                                                role="user"
+                                                dom_id=dom_id
+                                                highlighted=is_highlighted
+                                                versions=siblings.len()
+                                                selected=selected
+                                                on_select=Callback::new(move |branch: usize| {
+                                                    if let Some(sibling) = siblings.get(branch) {
+                                                        switch_branch(sibling);
+                                                    }
+                                                })
+                                                on_edit=on_edit
+                                                on_answer_offline=on_answer_offline
+                                                pending=msg.pending
+                                                meta=msg.meta
+                                                content=msg.content
+                                            />
+                                        }
+                                    }
+                                    // Assistant messages: flip between regenerated versions.
+                                    Role::Assistant => {
+                                        // Offline replies are re-asked from the model once back online.
+                                        let on_regenerate = (index == last && idle && !msg.meta.offline)
+                                            .then(|| Callback::new(move |_| regenerate()));
+                                        let on_ask_model = (index == last && idle && msg.meta.offline && online.get())
+                                            .then(|| Callback::new(move |_| regenerate()));
+                                        let author = author_of(&msg.meta, &chat);
+                                        view! {
+                                            <ChatBubble
+                                                role="assistant"
+                                                dom_id=dom_id
+                                                highlighted=is_highlighted
+                                                versions=msg.versions()
+                                                selected=msg.selected
+                                                on_select=Callback::new(move |version| select_version(&id, version))
+                                                on_regenerate=on_regenerate
+                                                on_ask_model=on_ask_model
+                                                cancelled=msg.cancelled
+                                                author=author
+                                                meta=msg.meta
+                                                content=msg.content
+                                            />
+                                        }
+                                    }
                                 }
                             }).collect::<Vec<_>>().into_any()
                         }
@@ -162,14 +880,48 @@ pub fn ChatArea() -> impl IntoView {
                     }
                 }}
 
-                // Thinking indicator
-                <Show when=move || is_thinking.get()>
+                // Thinking indicator (until the first streamed chunk arrives)
+                <Show when=move || {
+                    is_thinking.get()
+                        && (regenerating.get()
+                            || active_chat
+                                .get()
+                                .and_then(|c| c.messages.last().map(|m| m.role == Role::User && !m.pending))
+                                .unwrap_or(false))
+                }>
                     <ThinkingBubble />
                 </Show>
             </div>
 
             // Input bar
             <div class="chat-input-container">
+                <Show when=move || editing.get().is_some()>
+                    <div class="edit-banner" role="status">
+                        <span class="material-symbols-outlined">{"edit"}</span>
+                        <span>{move || i18n.get().editing_message}</span>
+                        <button class="edit-banner-cancel" on:click=move |_| cancel_editing()>
+                            {move || i18n.get().cancel}
+                        </button>
+                    </div>
+                </Show>
+                <Show when=move || request_error.get().is_some()>
+                    <div class="error-banner" role="alert">
+                        <span class="material-symbols-outlined">{"error"}</span>
+                        <span>{move || request_error.get().unwrap_or_default()}</span>
+                        <button class="edit-banner-cancel" on:click=move |_| request_error.set(None)>
+                            {move || i18n.get().dismiss}
+                        </button>
+                    </div>
+                </Show>
+                <Show when=move || cooldown_secs.get().is_some()>
+                    <div class="rate-limit-banner" role="status">
+                        <span class="material-symbols-outlined">{"hourglass_top"}</span>
+                        {move || {
+                            let wait = format_wait(cooldown_secs.get().unwrap_or_default());
+                            i18n.get().error_rate_limited.replace("{wait}", &wait)
+                        }}
+                    </div>
+                </Show>
                 <div class="chat-input-wrapper">
                     <input
                         type="text"
@@ -184,16 +936,70 @@ pub fn ChatArea() -> impl IntoView {
                             }
                         }
                     />
-                    <button 
-                        class="send-btn" 
-                        aria-label="Enviar mensaje"
-                        on:click=move |_| send_message()
-                        disabled=move || is_thinking.get()
+                    <Show
+                        when=move || is_thinking.get()
+                        fallback=move || view! {
+                            <button
+                                class="send-btn"
+                                aria-label="Enviar mensaje"
+                                on:click=move |_| send_message()
+                                disabled=move || cooldown_secs.get().is_some()
+                            >
+                                <span class="material-symbols-outlined">{"send"}</span>
+                            </button>
+                        }
                     >
-                        <span class="material-symbols-outlined">{"send"}</span>
-                    </button>
+                        <button
+                            class="send-btn stop"
+                            aria-label=move || i18n.get().stop
+                            title=move || i18n.get().stop
+                            on:click=move |_| stop()
+                        >
+                            <span class="material-symbols-outlined">{"stop"}</span>
+                        </button>
+                    </Show>
                 </div>
             </div>
         </main>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::IntelligenceLevel;
+
+    fn answered_chat() -> ChatSession {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::High, Language::En);
+        chat.messages.push(ChatMessage::new(Role::User, "Hi"));
+        chat.messages.push(ChatMessage::new(Role::Assistant, "Meow"));
+        chat
+    }
+
+    #[test]
+    fn failed_regenerate_adds_no_version() {
+        let mut chat = answered_chat();
+        let before = chat.clone();
+
+        let error = ApiError::new(ApiErrorCode::UpstreamUnavailable, "Upstream down");
+        assert_eq!(apply_regenerated(&mut chat, Err(error.clone())).unwrap_err(), error);
+        assert_eq!(chat, before);
+    }
+
+    #[test]
+    fn regenerated_answers_become_versions() {
+        let mut chat = answered_chat();
+        let reply = ChatResponse {
+            response: "Purr".to_string(),
+            tokens_used: None,
+            alternatives: vec!["Hiss".to_string()],
+            summary: None,
+            model: None,
+        };
+
+        apply_regenerated(&mut chat, Ok(reply)).unwrap();
+        let last = chat.messages.last().unwrap();
+        assert_eq!(last.versions(), 3);
+        assert_eq!(last.content, "Purr");
+    }
+}
@@ -1,4 +1,14 @@
+use crate::components::animal_card::CustomAvatar;
+use crate::i18n::{format_timestamp, Translations};
 use leptos::prelude::*;
+use shared::{AnimalType, Avatar, Language, MessageMeta};
+
+/// Who wrote an assistant bubble, drawn next to it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BubbleAuthor {
+    Animal(AnimalType),
+    Custom { name: String, avatar: Avatar },
+}
 
 /// A single chat message bubble.
 #[component]
@@ -9,16 +19,238 @@ pub fn ChatBubble(
     /// The message content
     #[prop(into)]
     content: String,
+    /// Number of versions of this answer (see `ChatMessage::versions`)
+    #[prop(default = 1)]
+    versions: usize,
+    /// Index of the version shown
+    #[prop(default = 0)]
+    selected: usize,
+    /// Shows another version; enables the arrows when there are several
+    #[prop(optional)]
+    on_select: Option<Callback<usize>>,
+    /// Asks for another answer; only set on the last reply
+    #[prop(optional_no_strip)]
+    on_regenerate: Option<Callback<()>>,
+    /// Starts editing this (user) message
+    #[prop(optional_no_strip)]
+    on_edit: Option<Callback<()>>,
+    /// The user stopped this reply before it finished
+    #[prop(optional)]
+    cancelled: bool,
+    /// Written offline, waiting in the outbox
+    #[prop(optional)]
+    pending: bool,
+    /// Answers this pending message with an offline reply instead of waiting
+    #[prop(optional_no_strip)]
+    on_answer_offline: Option<Callback<()>>,
+    /// Re-asks the model for an offline reply; only set once back online
+    #[prop(optional_no_strip)]
+    on_ask_model: Option<Callback<()>>,
+    /// Timestamp, model and usage, shown under the bubble
+    #[prop(optional)]
+    meta: MessageMeta,
+    /// Avatar drawn beside the bubble
+    #[prop(optional)]
+    author: Option<BubbleAuthor>,
+    /// Element id, so the bubble can be scrolled to
+    #[prop(optional, into)]
+    dom_id: Option<String>,
+    /// Flashes the bubble, e.g. after jumping to it from a search result
+    #[prop(optional, into)]
+    highlighted: Signal<bool>,
 ) -> impl IntoView {
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+    let language = use_context::<RwSignal<Language>>().expect("language");
     let role_class = role.clone();
 
+    let avatar = author.map(|author| match author {
+        BubbleAuthor::Animal(animal) => view! {
+            <div
+                class="bubble-avatar"
+                title=move || animal.label(language.get())
+                inner_html=animal.svg()
+            ></div>
+        }
+        .into_any(),
+        BubbleAuthor::Custom { name, avatar } => view! {
+            <div class="bubble-avatar" title=name>
+                <CustomAvatar avatar=avatar />
+            </div>
+        }
+        .into_any(),
+    });
+
+    // Model, tokens and latency, as a tooltip on the time.
+    let details = [
+        meta.model.clone(),
+        meta.tokens_used.map(|tokens| format!("{tokens} tokens")),
+        meta.latency_ms.map(|ms| format!("{:.1} s", f64::from(ms) / 1000.0)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+    let time = meta.created_at.map(|at| {
+        let epoch_ms = at.timestamp_millis() as f64;
+        let datetime = at.to_rfc3339();
+        view! {
+            <time class="bubble-time" datetime=datetime title=details>
+                {move || format_timestamp(epoch_ms, language.get())}
+            </time>
+        }
+    });
+
     // Convert markdown to HTML
     let html_content = markdown::to_html(&content);
 
-    view! {
-        <div class={format!("bubble-row {}", role_class)}>
+    let switcher = on_select.filter(|_| versions > 1).map(|select| {
+        view! {
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().previous_version
+                title=move || i18n.get().previous_version
+                disabled=selected == 0
+                on:click=move |_| select.run(selected.saturating_sub(1))
+            >
+                <span class="material-symbols-outlined">{"chevron_left"}</span>
+            </button>
+            <span class="bubble-version">{format!("{}/{versions}", selected + 1)}</span>
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().next_version
+                title=move || i18n.get().next_version
+                disabled=selected + 1 >= versions
+                on:click=move |_| select.run(selected + 1)
+            >
+                <span class="material-symbols-outlined">{"chevron_right"}</span>
+            </button>
+        }
+    });
+
+    let regenerate = on_regenerate.map(|regenerate| {
+        view! {
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().regenerate
+                title=move || i18n.get().regenerate
+                on:click=move |_| regenerate.run(())
+            >
+                <span class="material-symbols-outlined">{"refresh"}</span>
+            </button>
+        }
+    });
+
+    let edit = on_edit.map(|edit| {
+        view! {
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().edit_message
+                title=move || i18n.get().edit_message
+                on:click=move |_| edit.run(())
+            >
+                <span class="material-symbols-outlined">{"edit"}</span>
+            </button>
+        }
+    });
+
+    let answer_offline = on_answer_offline.map(|answer| {
+        view! {
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().answer_offline
+                title=move || i18n.get().answer_offline
+                on:click=move |_| answer.run(())
+            >
+                <span class="material-symbols-outlined">{"pets"}</span>
+            </button>
+        }
+    });
+
+    let ask_model = on_ask_model.map(|ask| {
+        view! {
+            <button
+                class="bubble-action-btn"
+                aria-label=move || i18n.get().ask_model
+                title=move || i18n.get().ask_model
+                on:click=move |_| ask.run(())
+            >
+                <span class="material-symbols-outlined">{"cloud"}</span>
+            </button>
+        }
+    });
+
+    let stopped = cancelled.then(|| {
+        view! {
+            <span class="bubble-stopped">
+                <span class="material-symbols-outlined">{"stop_circle"}</span>
+                {move || i18n.get().reply_stopped}
+            </span>
+        }
+    });
+
+    let queued = pending.then(|| {
+        view! {
+            <span class="bubble-pending">
+                <span class="material-symbols-outlined">{"schedule"}</span>
+                {move || i18n.get().message_pending}
+            </span>
+        }
+    });
+
+    let offline = meta.offline;
+    let unplugged = offline.then(|| {
+        view! {
+            <span class="bubble-offline">
+                <span class="material-symbols-outlined">{"wifi_off"}</span>
+                {move || i18n.get().offline_reply}
+            </span>
+        }
+    });
+
+    // A reply stopped before its first word has nothing to show but the tag.
+    let bubble = (!content.is_empty()).then(|| {
+        view! {
             <div class={format!("bubble {}", role)} inner_html=html_content>
             </div>
+        }
+    });
+
+    let has_actions = switcher.is_some()
+        || regenerate.is_some()
+        || edit.is_some()
+        || stopped.is_some()
+        || queued.is_some()
+        || unplugged.is_some()
+        || answer_offline.is_some()
+        || ask_model.is_some()
+        || time.is_some();
+
+    view! {
+        <div
+            class={format!("bubble-row {}", role_class)}
+            id=dom_id
+            class:highlighted=move || highlighted.get()
+            class:pending=pending
+            class:offline=offline
+        >
+            {avatar}
+            <div class="bubble-column">
+                {bubble}
+                {has_actions.then(|| view! {
+                    <div class="bubble-actions">
+                        {time}
+                        {stopped}
+                        {queued}
+                        {unplugged}
+                        {answer_offline}
+                        {edit}
+                        {switcher}
+                        {regenerate}
+                        {ask_model}
+                    </div>
+                })}
+            </div>
         </div>
     }
 }
@@ -0,0 +1,202 @@
+use crate::components::chat_area::HighlightedMessage;
+use crate::components::custom_select::{CustomSelect, SelectOption};
+use crate::i18n::{format_timestamp, Translations};
+use leptos::prelude::*;
+use shared::{
+    search, AnimalFilter, AnimalType, ChatSession, CustomAnimal, IntelligenceLevel, Language,
+    SearchFilters,
+};
+
+/// Select values of custom animals are their id behind this prefix.
+const CUSTOM_PREFIX: &str = "custom:";
+
+/// Search box for the sidebar, with optional filters for animal,
+/// intelligence and last activity.
+#[component]
+pub fn SearchBox(query: RwSignal<String>, filters: RwSignal<SearchFilters>) -> impl IntoView {
+    let language = use_context::<RwSignal<Language>>().expect("language");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+
+    let show_filters = RwSignal::new(false);
+
+    let animal_options = Memo::new(move |_| {
+        std::iter::once(SelectOption { value: String::new(), label: i18n.get().filter_any_animal.to_string() })
+            .chain(AnimalType::all().iter().map(|a| SelectOption {
+                value: a.id().to_string(),
+                label: a.label(language.get()).to_string(),
+            }))
+            .chain(custom_animals.get().into_iter().map(|a| SelectOption {
+                value: format!("{CUSTOM_PREFIX}{}", a.id),
+                label: a.persona.name,
+            }))
+            .collect::<Vec<_>>()
+    });
+
+    let intelligence_options = Memo::new(move |_| {
+        std::iter::once(SelectOption { value: String::new(), label: i18n.get().filter_any_intelligence.to_string() })
+            .chain(IntelligenceLevel::all().iter().map(|level| SelectOption {
+                value: level.id().to_string(),
+                label: level.label(language.get()).to_string(),
+            }))
+            .collect::<Vec<_>>()
+    });
+
+    let set_animal = move |val: String| {
+        filters.update(|f| {
+            f.animal = match val.strip_prefix(CUSTOM_PREFIX) {
+                Some(id) => Some(AnimalFilter::Custom(id.to_string())),
+                None => AnimalType::from_id(&val).map(AnimalFilter::Builtin),
+            }
+        });
+    };
+
+    let set_intelligence = move |val: String| {
+        filters.update(|f| {
+            f.intelligence = IntelligenceLevel::from_id(&val)
+        });
+    };
+
+    view! {
+        <div class="search-box">
+            <div class="search-input-wrapper">
+                <span class="material-symbols-outlined">{"search"}</span>
+                <input
+                    type="search"
+                    class="search-input"
+                    placeholder=move || i18n.get().search_placeholder
+                    aria-label=move || i18n.get().search_placeholder
+                    prop:value=move || query.get()
+                    on:input=move |ev| query.set(event_target_value(&ev))
+                />
+                <button
+                    class="config-icon-btn"
+                    class:active=move || show_filters.get() || !filters.with(SearchFilters::is_empty)
+                    title=move || i18n.get().search_filters
+                    aria-label=move || i18n.get().search_filters
+                    aria-expanded=move || show_filters.get().to_string()
+                    on:click=move |_| show_filters.update(|v| *v = !*v)
+                >
+                    <span class="material-symbols-outlined">{"tune"}</span>
+                </button>
+            </div>
+            <Show when=move || show_filters.get()>
+                <div class="search-filters">
+                    <CustomSelect
+                        value=Signal::derive(move || match filters.get().animal {
+                            Some(AnimalFilter::Builtin(animal)) => animal.id().to_string(),
+                            Some(AnimalFilter::Custom(id)) => format!("{CUSTOM_PREFIX}{id}"),
+                            None => String::new(),
+                        })
+                        options=Signal::derive(move || animal_options.get())
+                        on_change=Callback::new(set_animal)
+                    />
+                    <CustomSelect
+                        value=Signal::derive(move || {
+                            filters.get().intelligence.map(|l| l.id()).unwrap_or_default().to_string()
+                        })
+                        options=Signal::derive(move || intelligence_options.get())
+                        on_change=Callback::new(set_intelligence)
+                    />
+                    <div class="search-dates">
+                        <label>
+                            {move || i18n.get().filter_from}
+                            <input
+                                type="date"
+                                prop:value=move || filters.get().from.map(|d| d.to_string()).unwrap_or_default()
+                                on:change=move |ev| {
+                                    let date = event_target_value(&ev).parse().ok();
+                                    filters.update(|f| f.from = date);
+                                }
+                            />
+                        </label>
+                        <label>
+                            {move || i18n.get().filter_to}
+                            <input
+                                type="date"
+                                prop:value=move || filters.get().to.map(|d| d.to_string()).unwrap_or_default()
+                                on:change=move |ev| {
+                                    let date = event_target_value(&ev).parse().ok();
+                                    filters.update(|f| f.to = date);
+                                }
+                            />
+                        </label>
+                    </div>
+                    <Show when=move || !filters.with(SearchFilters::is_empty)>
+                        <button class="search-clear" on:click=move |_| filters.set(SearchFilters::default())>
+                            {move || i18n.get().clear_filters}
+                        </button>
+                    </Show>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+/// Ranked matches for the sidebar search; picking one opens the chat and
+/// highlights the matching message.
+#[component]
+pub fn SearchResults(query: RwSignal<String>, filters: RwSignal<SearchFilters>) -> impl IntoView {
+    let chats = use_context::<RwSignal<Vec<ChatSession>>>().expect("chats");
+    let active_chat_id = use_context::<RwSignal<Option<String>>>().expect("active_chat_id");
+    let sidebar_open = use_context::<RwSignal<bool>>().expect("sidebar_open");
+    let language = use_context::<RwSignal<Language>>().expect("language");
+    let highlighted = use_context::<RwSignal<Option<HighlightedMessage>>>().expect("highlighted message");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+
+    let hits = Memo::new(move |_| chats.with(|chats| search(chats, &query.get(), &filters.get())));
+
+    let open = move |chat_id: String, message_id: Option<String>| {
+        if let Some(message_id) = &message_id {
+            // Matches on another branch bring that branch up first.
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id)
+                    && chat.messages.depth(message_id).is_none()
+                {
+                    chat.switch_branch(message_id);
+                }
+            });
+        }
+        active_chat_id.set(Some(chat_id));
+        highlighted.set(message_id.map(HighlightedMessage));
+        sidebar_open.set(false);
+    };
+
+    view! {
+        <div class="chat-list search-results">
+            <Show when=move || !hits.with(Vec::is_empty) fallback=move || view! {
+                <div class="search-empty">{move || i18n.get().no_results}</div>
+            }>
+                <For
+                    each=move || hits.get()
+                    key=|hit| (hit.chat_id.clone(), hit.message_id.clone())
+                    children=move |hit| {
+                        let chat = chats.with_untracked(|v| v.iter().find(|c| c.id == hit.chat_id).cloned());
+                        let (title, when) = chat
+                            .map(|c| (c.title.clone(), c.updated_at.timestamp_millis() as f64))
+                            .unwrap_or_default();
+                        let chat_id = hit.chat_id.clone();
+                        let message_id = hit.message_id.clone();
+                        view! {
+                            <button
+                                class="chat-item search-result"
+                                class:active=move || active_chat_id.get().as_deref() == Some(hit.chat_id.as_str())
+                                on:click=move |_| open(chat_id.clone(), message_id.clone())
+                            >
+                                <span class="search-result-header">
+                                    <span class="chat-item-title">{title}</span>
+                                    <time class="search-result-date">
+                                        {move || format_timestamp(when, language.get())}
+                                    </time>
+                                </span>
+                                {hit.snippet.map(|snippet| view! {
+                                    <span class="search-result-snippet">{snippet}</span>
+                                })}
+                            </button>
+                        }
+                    }
+                />
+            </Show>
+        </div>
+    }
+}
@@ -1,6 +1,12 @@
 use leptos::prelude::*;
-use shared::{AnimalType, IntelligenceLevel, ChatSession, Language};
+use shared::{AnimalType, Avatar, IntelligenceLevel, ChatSession, CustomAnimal, Language};
 use crate::components::custom_select::{CustomSelect, SelectOption};
+use crate::components::persona_editor::PersonaDraft;
+use crate::i18n::Translations;
+use crate::storage::{format_bytes, StorageUsage};
+
+/// Select values of custom animals are their id behind this prefix.
+const CUSTOM_PREFIX: &str = "custom:";
 
 /// Configuration panel with Animal, Intelligence, and Language dropdowns.
 #[component]
@@ -10,6 +16,11 @@ pub fn ConfigPanel() -> impl IntoView {
     let language = use_context::<RwSignal<Language>>().expect("language");
 
     let animal = use_context::<Memo<AnimalType>>().expect("AnimalType");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
+    let custom_animal = use_context::<Memo<Option<CustomAnimal>>>().expect("custom animal");
+    let persona_draft = use_context::<RwSignal<Option<PersonaDraft>>>().expect("persona draft");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+    let storage_usage = use_context::<RwSignal<Option<StorageUsage>>>().expect("storage usage");
 
     let current_settings = Memo::new(move |_| {
         active_chat_id.get().and_then(|id| {
@@ -21,16 +32,15 @@ pub fn ConfigPanel() -> impl IntoView {
 
     let update_animal = move |val: String| {
         if let Some(id) = active_chat_id.get() {
-            let new_animal = match val.as_str() {
-                "cat" => AnimalType::Cat,
-                "octopus" => AnimalType::Octopus,
-                "elephant" => AnimalType::Elephant,
-                "chicken" => AnimalType::Chicken,
-                _ => AnimalType::Cat,
-            };
             chats.update(|v| {
                 if let Some(chat) = v.iter_mut().find(|c| c.id == id) {
-                    chat.animal = new_animal;
+                    match val.strip_prefix(CUSTOM_PREFIX) {
+                        Some(custom_id) => chat.custom_animal = Some(custom_id.to_string()),
+                        None => {
+                            chat.animal = AnimalType::from_id(&val).unwrap_or_default();
+                            chat.custom_animal = None;
+                        }
+                    }
                 }
             });
         }
@@ -59,14 +69,29 @@ pub fn ConfigPanel() -> impl IntoView {
             _ => Language::Es,
         };
         language.set(new_lang);
+        // The active conversation follows the UI language so replies switch too.
+        if let Some(id) = active_chat_id.get() {
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == id) {
+                    chat.language = new_lang;
+                }
+            });
+        }
     };
 
-    let animal_options = Memo::new(move |_| vec![
-        SelectOption { value: "cat".to_string(), label: AnimalType::Cat.label(language.get()).to_string() },
-        SelectOption { value: "octopus".to_string(), label: AnimalType::Octopus.label(language.get()).to_string() },
-        SelectOption { value: "elephant".to_string(), label: AnimalType::Elephant.label(language.get()).to_string() },
-        SelectOption { value: "chicken".to_string(), label: AnimalType::Chicken.label(language.get()).to_string() },
-    ]);
+    let animal_options = Memo::new(move |_| {
+        AnimalType::all()
+            .iter()
+            .map(|a| SelectOption { value: a.id().to_string(), label: a.label(language.get()).to_string() })
+            .chain(custom_animals.get().into_iter().map(|a| SelectOption {
+                value: format!("{CUSTOM_PREFIX}{}", a.id),
+                label: match &a.avatar {
+                    Avatar::Emoji(emoji) => format!("{emoji} {}", a.persona.name),
+                    Avatar::Svg(_) => a.persona.name.clone(),
+                },
+            }))
+            .collect::<Vec<_>>()
+    });
 
     let intelligence_options = Memo::new(move |_| vec![
         SelectOption { value: "high".to_string(), label: IntelligenceLevel::High.label(language.get()).to_string() },
@@ -84,15 +109,35 @@ pub fn ConfigPanel() -> impl IntoView {
             <div class="config-row">
                 <span class="material-symbols-outlined">{"pets"}</span>
                 <CustomSelect
-                    value=Signal::derive(move || match animal.get() {
-                        AnimalType::Cat => "cat",
-                        AnimalType::Octopus => "octopus",
-                        AnimalType::Elephant => "elephant",
-                        AnimalType::Chicken => "chicken",
-                    }.to_string())
+                    value=Signal::derive(move || match custom_animal.get() {
+                        Some(custom) => format!("{CUSTOM_PREFIX}{}", custom.id),
+                        None => animal.get().id().to_string(),
+                    })
                     options=Signal::derive(move || animal_options.get())
                     on_change=Callback::new(update_animal)
                 />
+                <Show when=move || custom_animal.get().is_some()>
+                    <button
+                        class="config-icon-btn"
+                        title=move || i18n.get().edit_animal
+                        aria-label=move || i18n.get().edit_animal
+                        on:click=move |_| {
+                            if let Some(custom) = custom_animal.get() {
+                                persona_draft.set(Some(PersonaDraft::edit(custom)));
+                            }
+                        }
+                    >
+                        <span class="material-symbols-outlined">{"edit"}</span>
+                    </button>
+                </Show>
+                <button
+                    class="config-icon-btn"
+                    title=move || i18n.get().new_animal
+                    aria-label=move || i18n.get().new_animal
+                    on:click=move |_| persona_draft.set(Some(PersonaDraft::new()))
+                >
+                    <span class="material-symbols-outlined">{"add"}</span>
+                </button>
             </div>
 
             <div class="config-row">
@@ -119,6 +164,27 @@ pub fn ConfigPanel() -> impl IntoView {
                     on_change=Callback::new(update_language)
                 />
             </div>
+
+            {move || storage_usage.get().map(|usage| {
+                let near_quota = usage.fraction() > 0.8;
+                view! {
+                    <div class="config-row storage-usage" title=move || i18n.get().storage_used>
+                        <span class="material-symbols-outlined">{"database"}</span>
+                        <div class="storage-meter">
+                            <div class="storage-meter-track">
+                                <div
+                                    class="storage-meter-fill"
+                                    class:near-quota=near_quota
+                                    style:width=format!("{:.1}%", usage.fraction() * 100.0)
+                                ></div>
+                            </div>
+                            <span class="storage-meter-label">
+                                {format!("{} / {}", format_bytes(usage.usage), format_bytes(usage.quota))}
+                            </span>
+                        </div>
+                    </div>
+                }
+            })}
         </div>
     }
 }
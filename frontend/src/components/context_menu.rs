@@ -1,4 +1,5 @@
 use leptos::prelude::*;
+use crate::export::ExportFormat;
 use crate::i18n::Translations;
 
 /// A simple context menu for chat items.
@@ -6,10 +7,27 @@ use crate::i18n::Translations;
 pub fn ContextMenu(
     #[prop(into)] visible: Signal<bool>,
     on_close: Callback<()>,
+    #[prop(into)] pinned: Signal<bool>,
+    #[prop(into)] archived: Signal<bool>,
     on_rename: Callback<()>,
+    on_pin: Callback<()>,
+    on_archive: Callback<()>,
+    on_tags: Callback<()>,
+    on_export: Callback<ExportFormat>,
     on_delete: Callback<()>,
 ) -> impl IntoView {
     let i18n = use_context::<Memo<Translations>>().expect("i18n");
+    let export_item = move |format: ExportFormat, icon: &'static str, label: fn(&Translations) -> &'static str| {
+        view! {
+            <button class="menu-item" on:click=move |_| {
+                on_export.run(format);
+                on_close.run(());
+            }>
+                <span class="material-symbols-outlined">{icon}</span>
+                {move || label(&i18n.get())}
+            </button>
+        }
+    };
     view! {
         <Show when=move || visible.get()>
             <>
@@ -22,6 +40,32 @@ pub fn ContextMenu(
                         <span class="material-symbols-outlined">{"edit"}</span>
                         {move || i18n.get().rename}
                     </button>
+                    <button class="menu-item" on:click=move |_| {
+                        on_pin.run(());
+                        on_close.run(());
+                    }>
+                        <span class="material-symbols-outlined">{"keep"}</span>
+                        {move || if pinned.get() { i18n.get().unpin } else { i18n.get().pin }}
+                    </button>
+                    <button class="menu-item" on:click=move |_| {
+                        on_tags.run(());
+                        on_close.run(());
+                    }>
+                        <span class="material-symbols-outlined">{"sell"}</span>
+                        {move || i18n.get().edit_tags}
+                    </button>
+                    <button class="menu-item" on:click=move |_| {
+                        on_archive.run(());
+                        on_close.run(());
+                    }>
+                        <span class="material-symbols-outlined">
+                            {move || if archived.get() { "unarchive" } else { "archive" }}
+                        </span>
+                        {move || if archived.get() { i18n.get().unarchive } else { i18n.get().archive }}
+                    </button>
+                    {export_item(ExportFormat::Json, "data_object", |t| t.export_json)}
+                    {export_item(ExportFormat::Markdown, "description", |t| t.export_markdown)}
+                    {export_item(ExportFormat::Html, "html", |t| t.export_html)}
                     <button class="menu-item delete" on:click=move |_| {
                         on_delete.run(());
                         on_close.run(());
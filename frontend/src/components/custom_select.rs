@@ -55,7 +55,7 @@ pub fn CustomSelect(
                             let is_selected_val = val.clone();
                             let on_click_val = val.clone();
                             
-                            let select_fn = select_option.clone();
+                            let select_fn = select_option;
                             let value_sig = value;
                             
                             view! {
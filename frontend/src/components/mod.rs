@@ -1,8 +1,11 @@
 pub mod animal_card;
 pub mod chat_area;
 pub mod chat_bubble;
+pub mod chat_search;
 pub mod config_panel;
 pub mod context_menu;
 pub mod sidebar;
 pub mod custom_select;
+pub mod persona_editor;
+pub mod recovery_banner;
 pub mod update_banner;
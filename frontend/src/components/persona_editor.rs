@@ -0,0 +1,251 @@
+use crate::components::animal_card::CustomAvatar;
+use crate::i18n::Translations;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use shared::{
+    Avatar, ChatSession, CustomAnimal, CustomPersona, MAX_PERSONA_CATCHPHRASES,
+    MAX_PERSONA_NAME_LENGTH, MAX_PERSONA_PERSONALITY_LENGTH,
+};
+
+/// Uploaded avatars are stored in LocalStorage with the chats, so keep them small.
+const MAX_AVATAR_SVG_BYTES: usize = 32 * 1024;
+
+/// The custom animal being edited; `is_new` until it has been saved once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonaDraft {
+    pub animal: CustomAnimal,
+    pub is_new: bool,
+}
+
+impl PersonaDraft {
+    pub fn new() -> Self {
+        Self {
+            animal: CustomAnimal::new(CustomPersona::default(), Avatar::default(), "#2E7D32".to_string()),
+            is_new: true,
+        }
+    }
+
+    pub fn edit(animal: CustomAnimal) -> Self {
+        Self { animal, is_new: false }
+    }
+}
+
+/// Modal editor for user-defined animals, open while a [`PersonaDraft`] is set.
+#[component]
+pub fn PersonaEditor() -> impl IntoView {
+    let draft = use_context::<RwSignal<Option<PersonaDraft>>>().expect("persona draft");
+
+    move || draft.get().map(|d| view! { <PersonaForm draft=d /> })
+}
+
+#[component]
+fn PersonaForm(draft: PersonaDraft) -> impl IntoView {
+    let chats = use_context::<RwSignal<Vec<ChatSession>>>().expect("chats");
+    let active_chat_id = use_context::<RwSignal<Option<String>>>().expect("active_chat_id");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
+    let editor = use_context::<RwSignal<Option<PersonaDraft>>>().expect("persona draft");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+
+    let PersonaDraft { animal, is_new } = draft;
+    let id = StoredValue::new(animal.id);
+    let name = RwSignal::new(animal.persona.name);
+    let personality = RwSignal::new(animal.persona.personality);
+    let catchphrases = RwSignal::new(animal.persona.catchphrases.join("\n"));
+    let avatar = RwSignal::new(animal.avatar);
+    let accent = RwSignal::new(animal.accent);
+    let upload_failed = RwSignal::new(false);
+
+    let build = move || CustomAnimal {
+        id: id.get_value(),
+        persona: CustomPersona {
+            name: name.get().trim().to_string(),
+            personality: personality.get().trim().to_string(),
+            catchphrases: catchphrases
+                .get()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect(),
+        },
+        avatar: avatar.get(),
+        accent: accent.get(),
+    };
+
+    let is_valid = Memo::new(move |_| build().persona.validate().is_ok());
+
+    let close = move || editor.set(None);
+
+    let save = move || {
+        let animal = build();
+        if animal.persona.validate().is_err() {
+            return;
+        }
+        let saved_id = animal.id.clone();
+        custom_animals.update(|v| match v.iter_mut().find(|a| a.id == animal.id) {
+            Some(existing) => *existing = animal,
+            None => v.push(animal),
+        });
+        // The chat being configured switches to the animal just saved.
+        if let Some(chat_id) = active_chat_id.get() {
+            chats.update(|v| {
+                if let Some(chat) = v.iter_mut().find(|c| c.id == chat_id) {
+                    chat.custom_animal = Some(saved_id);
+                }
+            });
+        }
+        close();
+    };
+
+    let delete = move || {
+        let deleted_id = id.get_value();
+        custom_animals.update(|v| v.retain(|a| a.id != deleted_id));
+        // Chats that used it fall back to their built-in animal.
+        chats.update(|v| {
+            for chat in v.iter_mut().filter(|c| c.custom_animal.as_deref() == Some(deleted_id.as_str())) {
+                chat.custom_animal = None;
+            }
+        });
+        close();
+    };
+
+    let on_upload = move |ev: leptos::ev::Event| {
+        let input: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        if file.size() > MAX_AVATAR_SVG_BYTES as f64 {
+            upload_failed.set(true);
+            return;
+        }
+        spawn_local(async move {
+            let text = wasm_bindgen_futures::JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|v| v.as_string());
+            match text {
+                Some(svg) if is_svg_markup(&svg) => {
+                    upload_failed.set(false);
+                    avatar.set(Avatar::Svg(svg));
+                }
+                _ => upload_failed.set(true),
+            }
+        });
+    };
+
+    let emoji_value = move || match avatar.get() {
+        Avatar::Emoji(emoji) => emoji,
+        Avatar::Svg(_) => String::new(),
+    };
+
+    view! {
+        <div class="modal-overlay" on:click=move |_| close()></div>
+        <div class="modal persona-editor" role="dialog" aria-modal="true" aria-labelledby="persona-editor-title">
+            <h2 id="persona-editor-title" class="modal-title">
+                {move || if is_new { i18n.get().new_animal } else { i18n.get().edit_animal }}
+            </h2>
+
+            <label class="form-field">
+                <span class="form-label">{move || i18n.get().persona_name}</span>
+                <input
+                    type="text"
+                    class="form-input"
+                    maxlength=MAX_PERSONA_NAME_LENGTH
+                    prop:value=move || name.get()
+                    on:input=move |ev| name.set(event_target_value(&ev))
+                />
+            </label>
+
+            <label class="form-field">
+                <span class="form-label">{move || i18n.get().persona_personality}</span>
+                <textarea
+                    class="form-input"
+                    rows="5"
+                    maxlength=MAX_PERSONA_PERSONALITY_LENGTH
+                    placeholder=move || i18n.get().persona_personality_hint
+                    prop:value=move || personality.get()
+                    on:input=move |ev| personality.set(event_target_value(&ev))
+                ></textarea>
+                <span class="form-hint">
+                    {move || format!("{}/{MAX_PERSONA_PERSONALITY_LENGTH}", personality.get().chars().count())}
+                </span>
+            </label>
+
+            <label class="form-field">
+                <span class="form-label">{move || i18n.get().persona_catchphrases}</span>
+                <textarea
+                    class="form-input"
+                    rows="3"
+                    prop:value=move || catchphrases.get()
+                    on:input=move |ev| catchphrases.set(event_target_value(&ev))
+                ></textarea>
+                <span class="form-hint">
+                    {move || format!("{} {MAX_PERSONA_CATCHPHRASES}", i18n.get().persona_catchphrases_hint)}
+                </span>
+            </label>
+
+            <div class="form-row">
+                <div class="form-field">
+                    <span class="form-label">{move || i18n.get().persona_avatar}</span>
+                    <div class="persona-avatar-row">
+                        <div class="persona-avatar-preview">
+                            <CustomAvatar avatar=Signal::derive(move || avatar.get()) />
+                        </div>
+                        <input
+                            type="text"
+                            class="form-input persona-emoji-input"
+                            maxlength="16"
+                            aria-label=move || i18n.get().persona_avatar
+                            prop:value=emoji_value
+                            on:input=move |ev| {
+                                let emoji = event_target_value(&ev);
+                                if !emoji.trim().is_empty() {
+                                    avatar.set(Avatar::Emoji(emoji.trim().to_string()));
+                                }
+                            }
+                        />
+                        <label class="modal-btn secondary">
+                            <span class="material-symbols-outlined">{"upload"}</span>
+                            {move || i18n.get().persona_upload_svg}
+                            <input type="file" accept=".svg,image/svg+xml" hidden on:change=on_upload />
+                        </label>
+                    </div>
+                </div>
+
+                <label class="form-field">
+                    <span class="form-label">{move || i18n.get().persona_accent}</span>
+                    <input
+                        type="color"
+                        class="persona-color-input"
+                        prop:value=move || accent.get()
+                        on:input=move |ev| accent.set(event_target_value(&ev))
+                    />
+                </label>
+            </div>
+
+            <Show when=move || upload_failed.get()>
+                <p class="form-error">{move || i18n.get().persona_svg_invalid}</p>
+            </Show>
+
+            <div class="modal-actions">
+                {(!is_new).then(|| view! {
+                    <button class="modal-btn danger" on:click=move |_| delete()>
+                        {move || i18n.get().delete}
+                    </button>
+                })}
+                <button class="modal-btn secondary" on:click=move |_| close()>
+                    {move || i18n.get().cancel}
+                </button>
+                <button class="modal-btn primary" disabled=move || !is_valid.get() on:click=move |_| save()>
+                    {move || i18n.get().save}
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// Cheap sanity check on uploaded markup; it is only ever shown through `<img>`.
+fn is_svg_markup(text: &str) -> bool {
+    let text = text.trim_start();
+    (text.starts_with("<svg") || text.starts_with("<?xml")) && text.contains("</svg>")
+}
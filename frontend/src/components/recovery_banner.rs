@@ -0,0 +1,48 @@
+use crate::storage::Recovery;
+use crate::download::save_file;
+use crate::i18n::Translations;
+use leptos::prelude::*;
+
+/// Banner shown when the saved chats could not be read.
+///
+/// The unreadable data was set aside rather than replaced, and can be
+/// downloaded from here to recover it by hand.
+#[component]
+pub fn RecoveryBanner() -> impl IntoView {
+    let recovery = use_context::<RwSignal<Option<Recovery>>>().expect("recovery");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+
+    let on_download = move |_| {
+        if let Some(recovery) = recovery.get_untracked()
+            && let Err(e) = save_file("chats-backup.json", "application/json", &recovery.raw)
+        {
+            leptos::logging::error!("Backup download failed: {e}");
+        }
+    };
+
+    view! {
+        <Show when=move || recovery.with(Option::is_some)>
+            <div class="recovery-banner" role="alert">
+                <span class="material-symbols-outlined">{"warning"}</span>
+                <span>
+                    {move || {
+                        let backed_up = recovery.with(|r| r.as_ref().is_some_and(|r| r.backup_key.is_some()));
+                        if backed_up { i18n.get().recovery_backed_up } else { i18n.get().recovery_not_backed_up }
+                    }}
+                </span>
+                <button type="button" class="sw-update-btn" on:click=on_download>
+                    {move || i18n.get().recovery_download}
+                </button>
+                <button
+                    type="button"
+                    class="recovery-dismiss"
+                    aria-label=move || i18n.get().dismiss
+                    title=move || i18n.get().dismiss
+                    on:click=move |_| recovery.set(None)
+                >
+                    <span class="material-symbols-outlined">{"close"}</span>
+                </button>
+            </div>
+        </Show>
+    }
+}
@@ -1,8 +1,15 @@
+use crate::components::chat_search::{SearchBox, SearchResults};
 use crate::components::config_panel::ConfigPanel;
 use crate::components::context_menu::ContextMenu;
+use crate::export::{export_all, export_chat, ExportFormat};
 use crate::i18n::Translations;
 use leptos::prelude::*;
-use shared::{AnimalType, ChatSession, IntelligenceLevel, Language};
+use leptos::task::spawn_local;
+use chrono::{FixedOffset, Utc};
+use shared::{
+    all_tags, group_chats, merge_export, parse_export, AnimalType, ChatGroup, ChatSession, CustomAnimal,
+    IntelligenceLevel, Language, SearchFilters,
+};
 
 /// Sidebar with chat button, chat history list, and config panel.
 #[component]
@@ -11,14 +18,20 @@ pub fn Sidebar() -> impl IntoView {
     let chats = use_context::<RwSignal<Vec<ChatSession>>>().expect("chats");
     let active_chat_id = use_context::<RwSignal<Option<String>>>().expect("active_chat_id");
     let language = use_context::<RwSignal<Language>>().expect("language");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
     let i18n = use_context::<Memo<Translations>>().expect("i18n");
 
     let menu_open_for = RwSignal::new(Option::<String>::None);
+    let query = RwSignal::new(String::new());
+    let filters = RwSignal::new(SearchFilters::default());
+    let tag_filter = RwSignal::new(Option::<String>::None);
+    let show_archived = RwSignal::new(false);
+    let searching = move || !query.with(|q| q.trim().is_empty()) || !filters.with(SearchFilters::is_empty);
 
     let on_new_chat = move |_| {
         let current_lang = language.get();
         let mut new_chat =
-            ChatSession::new(AnimalType::Cat, IntelligenceLevel::Medium, current_lang);
+            ChatSession::new(AnimalType::default(), IntelligenceLevel::Medium, current_lang);
         new_chat.title = i18n.get().new_conversation.to_string();
         let id = new_chat.id.clone();
 
@@ -27,24 +40,64 @@ pub fn Sidebar() -> impl IntoView {
         sidebar_open.set(false);
     };
 
-    let delete_chat = move |id: String| {
-        chats.update(|v| v.retain(|c| c.id != id));
-        if active_chat_id.get() == Some(id) {
-            active_chat_id.set(chats.get().first().map(|c| c.id.clone()));
+    let tags = Memo::new(move |_| chats.with(|chats| all_tags(chats)));
+    // A tag nobody uses any more stops filtering.
+    Effect::new(move |_| {
+        if let Some(tag) = tag_filter.get()
+            && !tags.with(|tags| tags.contains(&tag))
+        {
+            tag_filter.set(None);
+        }
+    });
+
+    let groups = Memo::new(move |_| {
+        let offset = local_offset();
+        let today = Utc::now().with_timezone(&offset).date_naive();
+        chats.with(|chats| {
+            group_chats(chats, today, offset, tag_filter.get().as_deref())
+                .into_iter()
+                .map(|(group, chats)| (group, chats.into_iter().map(|c| c.id.clone()).collect::<Vec<_>>()))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let on_export_all = move |_| {
+        if let Err(e) = chats.with(|chats| custom_animals.with(|animals| export_all(chats, animals))) {
+            leptos::logging::error!("Export failed: {e}");
         }
     };
 
-    let rename_chat = move |id: String| {
-        let prompt_text = i18n.get().rename_dialog_title;
-        if let Some(Some(new_name)) = window().prompt_with_message(prompt_text).ok() {
-            if !new_name.trim().is_empty() {
-                chats.update(|v| {
-                    if let Some(chat) = v.iter_mut().find(|c| c.id == id) {
-                        chat.title = new_name.trim().to_string();
+    let on_import = move |ev: leptos::ev::Event| {
+        let input: web_sys::HtmlInputElement = event_target(&ev);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        // Lets the same file be picked again.
+        input.set_value("");
+        spawn_local(async move {
+            let text = wasm_bindgen_futures::JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|v| v.as_string())
+                .unwrap_or_default();
+            let message = match parse_export(&text) {
+                Ok(export) => {
+                    let mut report = None;
+                    chats.update(|chats| {
+                        custom_animals.update(|animals| report = Some(merge_export(chats, animals, export)));
+                    });
+                    if active_chat_id.get_untracked().is_none() {
+                        active_chat_id.set(chats.with_untracked(|v| v.first().map(|c| c.id.clone())));
                     }
-                });
-            }
-        }
+                    i18n.get_untracked().import_report(&report.unwrap_or_default())
+                }
+                Err(e) => {
+                    leptos::logging::warn!("Import failed: {e}");
+                    i18n.get_untracked().import_failed.to_string()
+                }
+            };
+            let _ = window().alert_with_message(&message);
+        });
     };
 
     view! {
@@ -59,54 +112,97 @@ pub fn Sidebar() -> impl IntoView {
                 </button>
             </div>
 
-            <div class="sidebar-section-title">{move || i18n.get().chats_title}</div>
-
-            <div class="chat-list">
-                <For
-                    each=move || chats.get()
-                    key=|chat| chat.id.clone()
-                    children=move |chat| {
-                        let id = chat.id.clone();
+            <SearchBox query=query filters=filters />
 
-                        let id_for_click = id.clone();
-                        let id_for_signal = id.clone();
-                        let id_for_menu_click = id.clone();
-                        let id_for_rename = id.clone();
-                        let id_for_delete = id.clone();
-
-                        let is_active = move || active_chat_id.get() == Some(id.clone());
-                        let is_menu_open = Signal::derive(move || menu_open_for.get() == Some(id_for_signal.clone()));
-                        let id_for_title = id_for_rename.clone();
+            <div class="sidebar-section-header">
+                <div class="sidebar-section-title">{move || i18n.get().chats_title}</div>
+                <button
+                    class="config-icon-btn"
+                    title=move || i18n.get().export_all
+                    aria-label=move || i18n.get().export_all
+                    on:click=on_export_all
+                >
+                    <span class="material-symbols-outlined">{"download"}</span>
+                </button>
+                <label
+                    class="config-icon-btn"
+                    title=move || i18n.get().import_chats
+                    aria-label=move || i18n.get().import_chats
+                >
+                    <span class="material-symbols-outlined">{"upload"}</span>
+                    <input type="file" accept=".json,application/json" hidden on:change=on_import />
+                </label>
+            </div>
 
-                        view! {
-                            <div
-                                class="chat-item"
-                                class:active=is_active
-                                on:click=move |_| {
-                                    active_chat_id.set(Some(id_for_click.clone()));
-                                    sidebar_open.set(false);
+            <Show when=searching>
+                <SearchResults query=query filters=filters />
+            </Show>
+            <div class="chat-list" style:display=move || searching().then_some("none")>
+                <Show when=move || !tags.with(Vec::is_empty)>
+                    <div class="tag-filter">
+                        <button
+                            class="tag-chip"
+                            class:active=move || tag_filter.with(Option::is_none)
+                            on:click=move |_| tag_filter.set(None)
+                        >
+                            {move || i18n.get().all_tags}
+                        </button>
+                        <For
+                            each=move || tags.get()
+                            key=|tag| tag.clone()
+                            children=move |tag| {
+                                let tag_for_click = tag.clone();
+                                let label = format!("#{tag}");
+                                view! {
+                                    <button
+                                        class="tag-chip"
+                                        class:active=move || tag_filter.get().as_deref() == Some(tag.as_str())
+                                        on:click=move |_| tag_filter.set(Some(tag_for_click.clone()))
+                                    >
+                                        {label}
+                                    </button>
                                 }
-                            >
-                                <span class="chat-item-title">{move || chats.get().iter().find(|c| c.id == id_for_title).map(|c| c.title.clone()).unwrap_or_default()}</span>
-                                <button
-                                    class="chat-item-menu-btn"
-                                    on:click=move |ev| {
-                                        ev.stop_propagation();
-                                        let current_id = id_for_menu_click.clone();
-                                        menu_open_for.update(|v| {
-                                            *v = if *v == Some(current_id.clone()) { None } else { Some(current_id) };
-                                        });
-                                    }
-                                    aria-label="Opciones del chat"
-                                >
-                                    <span class="material-symbols-outlined">{"more_vert"}</span>
-                                </button>
-                                <ContextMenu
-                                    visible=is_menu_open
-                                    on_close=Callback::new(move |_| menu_open_for.set(None))
-                                    on_rename=Callback::new(move |_| rename_chat(id_for_rename.clone()))
-                                    on_delete=Callback::new(move |_| delete_chat(id_for_delete.clone()))
-                                />
+                            }
+                        />
+                    </div>
+                </Show>
+                <For
+                    each=move || groups.with(|groups| groups.iter().map(|(group, _)| *group).collect::<Vec<_>>())
+                    key=|group| *group
+                    children=move |group| {
+                        let ids = move || {
+                            groups.with(|groups| {
+                                groups.iter().find(|(g, _)| *g == group).map(|(_, ids)| ids.clone()).unwrap_or_default()
+                            })
+                        };
+                        let collapsed = move || group == ChatGroup::Archived && !show_archived.get();
+                        let label = move || group_label(&i18n.get(), group);
+                        view! {
+                            <div class="chat-group">
+                                {if group == ChatGroup::Archived {
+                                    view! {
+                                        <button
+                                            class="chat-group-title chat-group-toggle"
+                                            aria-expanded=move || show_archived.get().to_string()
+                                            on:click=move |_| show_archived.update(|v| *v = !*v)
+                                        >
+                                            <span class="material-symbols-outlined">
+                                                {move || if show_archived.get() { "expand_more" } else { "chevron_right" }}
+                                            </span>
+                                            {label}
+                                            <span class="chat-group-count">{move || ids().len()}</span>
+                                        </button>
+                                    }.into_any()
+                                } else {
+                                    view! { <div class="chat-group-title">{label}</div> }.into_any()
+                                }}
+                                <Show when=move || !collapsed()>
+                                    <For
+                                        each=ids
+                                        key=|id| id.clone()
+                                        children=move |id| view! { <ChatItem id=id menu_open_for=menu_open_for /> }
+                                    />
+                                </Show>
                             </div>
                         }
                     }
@@ -123,3 +219,129 @@ pub fn Sidebar() -> impl IntoView {
         ></div>
     }
 }
+
+/// One row of the chat list, with its context menu.
+#[component]
+fn ChatItem(id: String, menu_open_for: RwSignal<Option<String>>) -> impl IntoView {
+    let sidebar_open = use_context::<RwSignal<bool>>().expect("sidebar_open");
+    let chats = use_context::<RwSignal<Vec<ChatSession>>>().expect("chats");
+    let active_chat_id = use_context::<RwSignal<Option<String>>>().expect("active_chat_id");
+    let custom_animals = use_context::<RwSignal<Vec<CustomAnimal>>>().expect("custom animals");
+    let i18n = use_context::<Memo<Translations>>().expect("i18n");
+
+    let id = StoredValue::new(id);
+    let chat = move |f: &dyn Fn(&ChatSession) -> String| {
+        chats.with(|v| v.iter().find(|c| c.id == id.get_value()).map(f).unwrap_or_default())
+    };
+    let flag = move |f: fn(&ChatSession) -> bool| {
+        Signal::derive(move || chats.with(|v| v.iter().find(|c| c.id == id.get_value()).is_some_and(f)))
+    };
+    let pinned = flag(|c| c.pinned);
+    let archived = flag(|c| c.archived);
+    let update = move |f: &dyn Fn(&mut ChatSession)| {
+        chats.update(|v| {
+            if let Some(chat) = v.iter_mut().find(|c| c.id == id.get_value()) {
+                f(chat);
+            }
+        });
+    };
+
+    let is_active = move || active_chat_id.get() == Some(id.get_value());
+    let is_menu_open = Signal::derive(move || menu_open_for.get() == Some(id.get_value()));
+
+    let rename = move |_| {
+        let prompt_text = i18n.get().rename_dialog_title;
+        if let Ok(Some(new_name)) = window().prompt_with_message(prompt_text)
+            && !new_name.trim().is_empty()
+        {
+            update(&|chat| {
+                chat.title = new_name.trim().to_string();
+                chat.renamed = true;
+            });
+        }
+    };
+
+    let edit_tags = move |_| {
+        let current = chat(&|c| c.tags.join(", "));
+        if let Ok(Some(input)) = window().prompt_with_message_and_default(i18n.get().tags_dialog_title, &current) {
+            update(&|chat| chat.set_tags(&input));
+        }
+    };
+
+    let export = move |format: ExportFormat| {
+        let Some(chat) = chats.with(|v| v.iter().find(|c| c.id == id.get_value()).cloned()) else {
+            return;
+        };
+        if let Err(e) = custom_animals.with(|animals| export_chat(&chat, animals, format)) {
+            leptos::logging::error!("Export failed: {e}");
+        }
+    };
+
+    let delete = move |_| {
+        let id = id.get_value();
+        chats.update(|v| v.retain(|c| c.id != id));
+        if active_chat_id.get() == Some(id) {
+            active_chat_id.set(chats.get().first().map(|c| c.id.clone()));
+        }
+    };
+
+    view! {
+        <div
+            class="chat-item"
+            class:active=is_active
+            class:archived=move || archived.get()
+            on:click=move |_| {
+                active_chat_id.set(Some(id.get_value()));
+                sidebar_open.set(false);
+            }
+        >
+            <span class="chat-item-title">{move || chat(&|c| c.title.clone())}</span>
+            <span class="chat-item-tags">
+                {move || chat(&|c| c.tags.iter().map(|t| format!("#{t}")).collect::<Vec<_>>().join(" "))}
+            </span>
+            <button
+                class="chat-item-menu-btn"
+                on:click=move |ev| {
+                    ev.stop_propagation();
+                    let current_id = id.get_value();
+                    menu_open_for.update(|v| {
+                        *v = if *v == Some(current_id.clone()) { None } else { Some(current_id) };
+                    });
+                }
+                aria-label="Opciones del chat"
+            >
+                <span class="material-symbols-outlined">{"more_vert"}</span>
+            </button>
+            <ContextMenu
+                visible=is_menu_open
+                pinned=pinned
+                archived=archived
+                on_close=Callback::new(move |_| menu_open_for.set(None))
+                on_rename=Callback::new(rename)
+                on_pin=Callback::new(move |_| update(&|chat| chat.pinned = !chat.pinned))
+                on_archive=Callback::new(move |_| update(&|chat| chat.archived = !chat.archived))
+                on_tags=Callback::new(edit_tags)
+                on_export=Callback::new(export)
+                on_delete=Callback::new(delete)
+            />
+        </div>
+    }
+}
+
+fn group_label(t: &Translations, group: ChatGroup) -> &'static str {
+    match group {
+        ChatGroup::Pinned => t.group_pinned,
+        ChatGroup::Today => t.group_today,
+        ChatGroup::Yesterday => t.group_yesterday,
+        ChatGroup::LastWeek => t.group_last_week,
+        ChatGroup::Older => t.group_older,
+        ChatGroup::Archived => t.group_archived,
+    }
+}
+
+/// The browser's current UTC offset.
+fn local_offset() -> FixedOffset {
+    // Minutes *behind* UTC, so west of it.
+    let minutes = js_sys::Date::new_0().get_timezone_offset() as i32;
+    FixedOffset::west_opt(minutes * 60).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+}
@@ -23,10 +23,10 @@ pub fn UpdateBanner() -> impl IntoView {
     });
 
     let on_update_click = move |_| {
-        if let Some(window) = document().default_view() {
-            if let Ok(event) = web_sys::CustomEvent::new("swSkipWaiting") {
-                let _ = window.dispatch_event(&event);
-            }
+        if let Some(window) = document().default_view()
+            && let Ok(event) = web_sys::CustomEvent::new("swSkipWaiting")
+        {
+            let _ = window.dispatch_event(&event);
         }
     };
 
@@ -1,7 +1,7 @@
-/// Configuration for API endpoints
-///
-/// In development (trunk serve), uses relative path which gets proxied by Trunk
-/// In production (release build), uses the full API domain
+//! Configuration for API endpoints
+//!
+//! In development (trunk serve), uses relative path which gets proxied by Trunk
+//! In production (release build), uses the full API domain
 
 /// Get the base URL for API calls
 pub fn api_base_url() -> &'static str {
@@ -18,3 +18,16 @@ pub fn api_base_url() -> &'static str {
     }
 }
 
+const CLIENT_TOKEN_KEY: &str = "ai_animal_client_token";
+
+/// Anonymous per-browser id sent as `X-Client-Token`; the worker rate-limits
+/// on it when it cannot see the client IP.
+pub fn client_token() -> String {
+    use gloo_storage::{LocalStorage, Storage};
+
+    LocalStorage::get(CLIENT_TOKEN_KEY).unwrap_or_else(|_| {
+        let token = uuid::Uuid::new_v4().to_string();
+        let _ = LocalStorage::set(CLIENT_TOKEN_KEY, &token);
+        token
+    })
+}
@@ -0,0 +1,126 @@
+//! Downloading chats as files and importing JSON exports.
+
+use shared::{
+    export_file_name, export_json, speaker, to_markdown, ChatSession, CustomAnimal, Language,
+};
+
+use crate::download::save_file;
+
+/// File formats a single chat can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Lossless, and the only one that can be imported back.
+    Json,
+    Markdown,
+    /// A standalone page in the chat's animal theme.
+    Html,
+}
+
+/// Downloads `chat` in `format`.
+pub fn export_chat(chat: &ChatSession, custom_animals: &[CustomAnimal], format: ExportFormat) -> Result<(), String> {
+    let (ext, mime, contents) = match format {
+        ExportFormat::Json => (
+            "json",
+            "application/json",
+            export_json(std::slice::from_ref(chat), custom_animals).map_err(|e| e.to_string())?,
+        ),
+        ExportFormat::Markdown => ("md", "text/markdown", to_markdown(chat, custom_animals)),
+        ExportFormat::Html => ("html", "text/html", chat_html(chat, custom_animals)),
+    };
+    save_file(&export_file_name(chat, ext), mime, &contents)
+}
+
+/// Downloads every chat as one JSON export.
+pub fn export_all(chats: &[ChatSession], custom_animals: &[CustomAnimal]) -> Result<(), String> {
+    let contents = export_json(chats, custom_animals).map_err(|e| e.to_string())?;
+    save_file("inteligencia-animal-chats.json", "application/json", &contents)
+}
+
+/// A self-contained HTML page with the active branch of `chat`.
+fn chat_html(chat: &ChatSession, custom_animals: &[CustomAnimal]) -> String {
+    let lang = chat.language;
+    let custom = chat
+        .custom_animal
+        .as_deref()
+        .and_then(|id| custom_animals.iter().find(|a| a.id == id));
+    let (animal, vars) = match custom {
+        Some(custom) => (custom.persona.name.clone(), custom.css_vars()),
+        None => (
+            chat.animal.label(lang).to_string(),
+            chat.animal
+                .theme()
+                .css_vars()
+                .iter()
+                .map(|&(name, value)| (name, value.to_string()))
+                .collect(),
+        ),
+    };
+    let vars: String = vars.iter().map(|(name, value)| format!("{name}: {value};")).collect();
+    let intelligence_label = match lang {
+        Language::Es => "Inteligencia",
+        Language::En => "Intelligence",
+    };
+
+    let messages: String = chat
+        .messages
+        .messages()
+        .iter()
+        .map(|message| {
+            let role = if message.role == shared::Role::User { "user" } else { "assistant" };
+            format!(
+                "<section class=\"message {role}\"><h2>{}</h2><div class=\"bubble\">{}</div></section>\n",
+                escape(&speaker(chat, message, custom_animals)),
+                // Raw HTML in the text is escaped by the markdown renderer.
+                markdown::to_html(&message.content),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+:root {{ {vars} }}
+body {{ margin: 0; background: var(--clr-bg); color: var(--clr-text); font-family: system-ui, sans-serif; line-height: 1.5; }}
+main {{ max-width: 760px; margin: 0 auto; padding: 2rem 1rem; }}
+header {{ border-bottom: 1px solid var(--clr-border); margin-bottom: 1.5rem; }}
+h1 {{ color: var(--clr-text-brand); margin: 0 0 .25rem; }}
+header p {{ color: var(--clr-text-secondary); margin: 0 0 1rem; }}
+.message {{ display: flex; flex-direction: column; margin-bottom: 1rem; }}
+.message h2 {{ font-size: .8rem; color: var(--clr-text-muted); margin: 0 0 .25rem; }}
+.bubble {{ padding: .75rem 1rem; border-radius: 1rem; background: var(--clr-bubble-ai); max-width: 85%; }}
+.bubble > :first-child {{ margin-top: 0; }}
+.bubble > :last-child {{ margin-bottom: 0; }}
+.user {{ align-items: flex-end; }}
+.user .bubble {{ background: var(--clr-bubble-user); color: var(--clr-on-primary); }}
+pre {{ overflow-x: auto; }}
+</style>
+</head>
+<body>
+<main>
+<header>
+<h1>{title}</h1>
+<p>{animal} · {intelligence_label}: {intelligence} · {date}</p>
+</header>
+{messages}</main>
+</body>
+</html>
+"#,
+        lang = lang.code(),
+        title = escape(&chat.title),
+        animal = escape(&animal),
+        intelligence = chat.intelligence.label(lang),
+        date = chat.created_at.format("%Y-%m-%d"),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,4 +1,5 @@
-use shared::Language;
+use shared::{ApiError, ApiErrorCode, ImportReport, Language};
+use crate::outbox::ConnectionStatus;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Translations {
@@ -16,15 +17,82 @@ pub struct Translations {
     pub cancel: &'static str,
     pub confirm: &'static str,
     pub rename_dialog_title: &'static str,
-    pub cat_sound: &'static str,
-    pub octopus_sound: &'static str,
-    pub elephant_sound: &'static str,
-    pub chicken_sound: &'static str,
     pub select_chat: &'static str,
     pub select_chat_subtitle: &'static str,
     pub error_message: &'static str,
+    pub error_invalid_request: &'static str,
+    pub error_empty_message: &'static str,
+    pub error_message_too_long: &'static str,
+    pub error_history_too_large: &'static str,
+    pub error_invalid_history: &'static str,
+    pub error_invalid_persona: &'static str,
+    pub error_config: &'static str,
+    /// `{wait}` is replaced with the server's `retry_after` (see [`format_wait`]).
+    pub error_rate_limited: &'static str,
+    pub error_offline: &'static str,
     pub app_title: &'static str,
     pub new_conversation: &'static str,
+    pub save: &'static str,
+    pub new_animal: &'static str,
+    pub edit_animal: &'static str,
+    pub persona_name: &'static str,
+    pub persona_personality: &'static str,
+    pub persona_personality_hint: &'static str,
+    pub persona_catchphrases: &'static str,
+    pub persona_catchphrases_hint: &'static str,
+    pub persona_avatar: &'static str,
+    pub persona_upload_svg: &'static str,
+    pub persona_svg_invalid: &'static str,
+    pub persona_accent: &'static str,
+    pub regenerate: &'static str,
+    pub previous_version: &'static str,
+    pub next_version: &'static str,
+    pub edit_message: &'static str,
+    pub editing_message: &'static str,
+    pub stop: &'static str,
+    pub reply_stopped: &'static str,
+    pub recovery_backed_up: &'static str,
+    pub recovery_not_backed_up: &'static str,
+    pub recovery_download: &'static str,
+    pub dismiss: &'static str,
+    pub storage_used: &'static str,
+    pub export_json: &'static str,
+    pub export_markdown: &'static str,
+    pub export_html: &'static str,
+    pub export_all: &'static str,
+    pub import_chats: &'static str,
+    pub import_failed: &'static str,
+    pub import_summary: &'static str,
+    pub import_conflicts: &'static str,
+    pub search_placeholder: &'static str,
+    pub search_filters: &'static str,
+    pub filter_any_animal: &'static str,
+    pub filter_any_intelligence: &'static str,
+    pub filter_from: &'static str,
+    pub filter_to: &'static str,
+    pub clear_filters: &'static str,
+    pub no_results: &'static str,
+    pub pin: &'static str,
+    pub unpin: &'static str,
+    pub archive: &'static str,
+    pub unarchive: &'static str,
+    pub edit_tags: &'static str,
+    pub tags_dialog_title: &'static str,
+    pub all_tags: &'static str,
+    pub group_pinned: &'static str,
+    pub group_today: &'static str,
+    pub group_yesterday: &'static str,
+    pub group_last_week: &'static str,
+    pub group_older: &'static str,
+    pub group_archived: &'static str,
+    pub message_pending: &'static str,
+    pub outbox_full: &'static str,
+    pub connection_online: &'static str,
+    pub connection_offline: &'static str,
+    pub connection_syncing: &'static str,
+    pub answer_offline: &'static str,
+    pub offline_reply: &'static str,
+    pub ask_model: &'static str,
 }
 
 pub fn get_translations(lang: Language) -> Translations {
@@ -44,15 +112,81 @@ pub fn get_translations(lang: Language) -> Translations {
             cancel: "Cancelar",
             confirm: "Confirmar",
             rename_dialog_title: "Renombrar Chat",
-            cat_sound: "¡Miau!",
-            octopus_sound: "¡Glub!",
-            elephant_sound: "¡Barroo!",
-            chicken_sound: "¡Cocoricó!",
             select_chat: "Selecciona un chat",
             select_chat_subtitle: "o crea uno nuevo para empezar",
             error_message: "Lo siento, mi cerebro animal se ha bloqueado. Intenta de nuevo. 😵‍💫",
+            error_invalid_request: "No he entendido esa petición. Recarga la página e inténtalo de nuevo.",
+            error_empty_message: "El mensaje está vacío. ¡Escríbeme algo!",
+            error_message_too_long: "Ese mensaje es demasiado largo para mi cabecita. Prueba con uno más corto.",
+            error_history_too_large: "Esta conversación ya es muy larga. Empieza un chat nuevo para seguir.",
+            error_invalid_history: "El historial de esta conversación está dañado. Empieza un chat nuevo.",
+            error_invalid_persona: "La personalidad de este animal no es válida. Revísala en el editor.",
+            error_config: "El servidor no está bien configurado. Inténtalo más tarde.",
+            error_rate_limited: "Demasiados mensajes seguidos. Podrás escribir de nuevo en {wait}.",
+            error_offline: "Sin conexión a internet. Comprueba tu red e inténtalo de nuevo.",
             app_title: "IA | Inteligencia Animal",
             new_conversation: "Nueva Conversación",
+            save: "Guardar",
+            new_animal: "Crear animal",
+            edit_animal: "Editar animal",
+            persona_name: "Nombre",
+            persona_personality: "Personalidad",
+            persona_personality_hint: "Describe cómo es, cómo habla y qué le gusta...",
+            persona_catchphrases: "Frases típicas",
+            persona_catchphrases_hint: "Una por línea. Máximo de frases:",
+            persona_avatar: "Avatar (emoji o SVG)",
+            persona_upload_svg: "Subir SVG",
+            persona_svg_invalid: "El archivo debe ser un SVG de menos de 32 KB.",
+            persona_accent: "Color",
+            regenerate: "Regenerar respuesta",
+            previous_version: "Versión anterior",
+            next_version: "Versión siguiente",
+            edit_message: "Editar mensaje",
+            editing_message: "Editando un mensaje: al enviarlo se crea una rama nueva",
+            stop: "Detener respuesta",
+            reply_stopped: "Detenido",
+            recovery_backed_up: "No se pudieron leer tus chats guardados. Hemos apartado una copia en este navegador para que no se pierdan.",
+            recovery_not_backed_up: "No se pudieron leer tus chats guardados ni apartar una copia. Descárgala: no se guardará nada hasta que cierres este aviso.",
+            recovery_download: "Descargar copia",
+            dismiss: "Cerrar",
+            storage_used: "Espacio usado por tus chats en este navegador",
+            export_json: "Exportar JSON",
+            export_markdown: "Exportar Markdown",
+            export_html: "Exportar HTML",
+            export_all: "Exportar todos los chats",
+            import_chats: "Importar chats",
+            import_failed: "No se pudo importar el archivo: no es una exportación de chats válida.",
+            import_summary: "Chats importados: {added}. Ya estaban: {unchanged}.",
+            import_conflicts: "Ya existen con otro contenido y se conservó la versión de este navegador: {titles}",
+            search_placeholder: "Buscar en los chats",
+            search_filters: "Filtros de búsqueda",
+            filter_any_animal: "Cualquier animal",
+            filter_any_intelligence: "Cualquier inteligencia",
+            filter_from: "Desde",
+            filter_to: "Hasta",
+            clear_filters: "Quitar filtros",
+            no_results: "Ningún chat coincide con la búsqueda",
+            pin: "Fijar",
+            unpin: "Desfijar",
+            archive: "Archivar",
+            unarchive: "Desarchivar",
+            edit_tags: "Etiquetas",
+            tags_dialog_title: "Etiquetas separadas por comas",
+            all_tags: "Todas",
+            group_pinned: "Fijados",
+            group_today: "Hoy",
+            group_yesterday: "Ayer",
+            group_last_week: "Últimos 7 días",
+            group_older: "Anteriores",
+            group_archived: "Archivados",
+            message_pending: "Pendiente de envío",
+            outbox_full: "El mensaje pendiente está lleno: envía este cuando haya salido.",
+            connection_online: "Conectado",
+            connection_offline: "Sin conexión: los mensajes se enviarán al volver",
+            connection_syncing: "Enviando mensajes pendientes…",
+            answer_offline: "Responder sin conexión",
+            offline_reply: "Respuesta sin conexión",
+            ask_model: "Preguntar al modelo",
         },
         Language::En => Translations {
             new_chat: "New Chat",
@@ -69,15 +203,152 @@ pub fn get_translations(lang: Language) -> Translations {
             cancel: "Cancel",
             confirm: "Confirm",
             rename_dialog_title: "Rename Chat",
-            cat_sound: "Meow!",
-            octopus_sound: "Glub!",
-            elephant_sound: "Pawoo!",
-            chicken_sound: "Cluck!",
             select_chat: "Select a chat",
             select_chat_subtitle: "or create a new one to start",
             error_message: "Sorry, my animal brain is frozen. Try again. 😵‍💫",
+            error_invalid_request: "I didn't understand that request. Reload the page and try again.",
+            error_empty_message: "The message is empty. Write me something!",
+            error_message_too_long: "That message is too long for my little head. Try a shorter one.",
+            error_history_too_large: "This conversation is already very long. Start a new chat to continue.",
+            error_invalid_history: "This conversation's history is damaged. Start a new chat.",
+            error_invalid_persona: "This animal's personality is not valid. Check it in the editor.",
+            error_config: "The server is not configured correctly. Try again later.",
+            error_rate_limited: "Too many messages in a row. You can write again in {wait}.",
+            error_offline: "No internet connection. Check your network and try again.",
             app_title: "AI | Animal Intelligence",
             new_conversation: "New Conversation",
+            save: "Save",
+            new_animal: "Create animal",
+            edit_animal: "Edit animal",
+            persona_name: "Name",
+            persona_personality: "Personality",
+            persona_personality_hint: "Describe what it is like, how it talks and what it likes...",
+            persona_catchphrases: "Catchphrases",
+            persona_catchphrases_hint: "One per line. Maximum phrases:",
+            persona_avatar: "Avatar (emoji or SVG)",
+            persona_upload_svg: "Upload SVG",
+            persona_svg_invalid: "The file must be an SVG under 32 KB.",
+            persona_accent: "Colour",
+            regenerate: "Regenerate reply",
+            previous_version: "Previous version",
+            next_version: "Next version",
+            edit_message: "Edit message",
+            editing_message: "Editing a message: sending it starts a new branch",
+            stop: "Stop reply",
+            reply_stopped: "Stopped",
+            recovery_backed_up: "Your saved chats couldn't be read. A copy was set aside in this browser so they aren't lost.",
+            recovery_not_backed_up: "Your saved chats couldn't be read or set aside. Download the copy: nothing is saved until you close this notice.",
+            recovery_download: "Download copy",
+            dismiss: "Close",
+            storage_used: "Space your chats use in this browser",
+            export_json: "Export JSON",
+            export_markdown: "Export Markdown",
+            export_html: "Export HTML",
+            export_all: "Export all chats",
+            import_chats: "Import chats",
+            import_failed: "The file couldn't be imported: it isn't a valid chat export.",
+            import_summary: "Chats imported: {added}. Already here: {unchanged}.",
+            import_conflicts: "Already here with different contents, so this browser's copy was kept: {titles}",
+            search_placeholder: "Search chats",
+            search_filters: "Search filters",
+            filter_any_animal: "Any animal",
+            filter_any_intelligence: "Any intelligence",
+            filter_from: "From",
+            filter_to: "To",
+            clear_filters: "Clear filters",
+            no_results: "No chats match your search",
+            pin: "Pin",
+            unpin: "Unpin",
+            archive: "Archive",
+            unarchive: "Unarchive",
+            edit_tags: "Tags",
+            tags_dialog_title: "Comma-separated tags",
+            all_tags: "All",
+            group_pinned: "Pinned",
+            group_today: "Today",
+            group_yesterday: "Yesterday",
+            group_last_week: "Previous 7 days",
+            group_older: "Older",
+            group_archived: "Archived",
+            message_pending: "Waiting to send",
+            outbox_full: "The waiting message is full: send this one once it has gone out.",
+            connection_online: "Online",
+            connection_offline: "Offline: messages will be sent when you're back",
+            connection_syncing: "Sending queued messages…",
+            answer_offline: "Answer offline",
+            offline_reply: "Offline reply",
+            ask_model: "Ask the model",
         },
     }
 }
+
+impl Translations {
+    /// What the header says about the connection.
+    pub fn connection_label(&self, status: ConnectionStatus) -> &'static str {
+        match status {
+            ConnectionStatus::Online => self.connection_online,
+            ConnectionStatus::Offline => self.connection_offline,
+            ConnectionStatus::Syncing => self.connection_syncing,
+        }
+    }
+
+    /// Localized, user-facing text for an API error.
+    pub fn api_error(&self, error: &ApiError) -> String {
+        match error.code {
+            ApiErrorCode::InvalidRequest => self.error_invalid_request.to_string(),
+            ApiErrorCode::EmptyMessage => self.error_empty_message.to_string(),
+            ApiErrorCode::MessageTooLong => self.error_message_too_long.to_string(),
+            ApiErrorCode::HistoryTooLarge => self.error_history_too_large.to_string(),
+            ApiErrorCode::InvalidHistory => self.error_invalid_history.to_string(),
+            ApiErrorCode::InvalidPersona => self.error_invalid_persona.to_string(),
+            ApiErrorCode::ConfigError => self.error_config.to_string(),
+            ApiErrorCode::UpstreamUnavailable => self.error_message.to_string(),
+            ApiErrorCode::RateLimited => self
+                .error_rate_limited
+                .replace("{wait}", &format_wait(error.retry_after.unwrap_or(60))),
+            ApiErrorCode::Offline => self.error_offline.to_string(),
+        }
+    }
+
+    /// What an import did, for an alert.
+    pub fn import_report(&self, report: &ImportReport) -> String {
+        let mut text = self
+            .import_summary
+            .replace("{added}", &report.added.len().to_string())
+            .replace("{unchanged}", &report.unchanged.to_string());
+        if !report.conflicts.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.import_conflicts.replace("{titles}", &report.conflicts.join(", ")));
+        }
+        text
+    }
+}
+
+/// Local time of a message: `14:05` today, with the date before that.
+pub fn format_timestamp(epoch_ms: f64, lang: Language) -> String {
+    let date = js_sys::Date::new(&epoch_ms.into());
+    let today = js_sys::Date::new_0().to_date_string() == date.to_date_string();
+
+    let options = js_sys::Object::new();
+    let set = |key: &str, value: &str| {
+        let _ = js_sys::Reflect::set(&options, &key.into(), &value.into());
+    };
+    set("timeStyle", "short");
+    if !today {
+        set("dateStyle", "short");
+    }
+    let locale = match lang {
+        Language::Es => "es",
+        Language::En => "en",
+    };
+    date.to_locale_string(locale, &options).into()
+}
+
+/// Compact countdown text: `45 s`, `3 min 20 s`, `2 h 05 min`.
+pub fn format_wait(seconds: u32) -> String {
+    match seconds {
+        0..60 => format!("{seconds} s"),
+        60..3_600 => format!("{} min {:02} s", seconds / 60, seconds % 60),
+        _ => format!("{} h {:02} min", seconds / 3_600, seconds % 3_600 / 60),
+    }
+}
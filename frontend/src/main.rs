@@ -3,7 +3,11 @@ use leptos::prelude::*;
 mod app;
 mod components;
 mod config;
+mod download;
+mod export;
 mod i18n;
+mod outbox;
+mod storage;
 
 fn main() {
     console_error_panic_hook::set_once();
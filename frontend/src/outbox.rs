@@ -0,0 +1,34 @@
+//! Bridge between the chat outbox and the Service Worker.
+//!
+//! Queued messages live on their chats (see `shared::outbox`). The page
+//! sends them itself when `online` fires; where Background Sync exists,
+//! `index.html` also registers a sync, and the Service Worker answers it by
+//! dispatching [`FLUSH_EVENT`] on the page.
+
+/// Dispatched on `window` when the Service Worker asks for the outbox to be sent.
+pub const FLUSH_EVENT: &str = "outboxFlush";
+/// Dispatched on `window` when a message is queued, to register a Background Sync.
+const QUEUED_EVENT: &str = "outboxQueued";
+
+/// How the header describes the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Online,
+    Offline,
+    /// Back online and sending queued messages.
+    Syncing,
+}
+
+/// What the browser believes; a `true` can still fail, a `false` cannot succeed.
+pub fn is_online() -> bool {
+    web_sys::window().is_none_or(|w| w.navigator().on_line())
+}
+
+/// Asks for a Background Sync, so the outbox is sent even if `online` is missed.
+pub fn request_background_sync() {
+    if let Some(window) = web_sys::window()
+        && let Ok(event) = web_sys::CustomEvent::new(QUEUED_EVENT)
+    {
+        let _ = window.dispatch_event(&event);
+    }
+}
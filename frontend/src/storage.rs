@@ -0,0 +1,515 @@
+//! Where the app state lives between visits.
+//!
+//! IndexedDB keeps one record per chat plus a small settings record, and a
+//! save only writes the records that changed since the last one. Browsers
+//! without IndexedDB fall back to the single localStorage blob used before,
+//! which is also imported once into IndexedDB the first time it opens.
+//!
+//! Both stores go through the versioned format in `shared::state`, so data
+//! from older builds is migrated and unreadable data is quarantined instead
+//! of being overwritten.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::prelude::window;
+use serde_json::{json, Value};
+use shared::{
+    decode_state, encode_state, upgrade_chat, upgrade_state, AppState, ChatSession, STATE_VERSION,
+};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+/// The localStorage blob; the `_v1` predates the versioned envelope and is kept.
+const STORAGE_KEY: &str = "ai_animal_chats_v1";
+/// Unreadable localStorage blobs are copied to this prefix plus a timestamp.
+const QUARANTINE_KEY_PREFIX: &str = "ai_animal_chats_quarantine_";
+
+const DB_NAME: &str = "ai_animal";
+const DB_VERSION: u32 = 1;
+/// Chat id → `ChatSession` JSON.
+const CHATS: &str = "chats";
+/// [`SETTINGS_KEY`] → everything in `AppState` but the chats, plus the chat order.
+const SETTINGS: &str = "settings";
+const SETTINGS_KEY: &str = "state";
+/// Timestamp → unreadable state JSON.
+const QUARANTINE: &str = "quarantine";
+
+/// Changes are written this long after the last one, so a streamed reply
+/// is saved once rather than on every chunk.
+pub const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// Saved data that could not be loaded, offered back to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    pub raw: String,
+    /// Where the backup was written; `None` if that failed too (usually the
+    /// storage quota), in which case nothing is saved until the banner is dismissed.
+    pub backup_key: Option<String>,
+}
+
+/// Bytes used by this origin and the most it may use, as estimated by the browser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StorageUsage {
+    pub usage: f64,
+    pub quota: f64,
+}
+
+impl StorageUsage {
+    /// Share of the quota in use, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.quota > 0.0 { (self.usage / self.quota).clamp(0.0, 1.0) } else { 0.0 }
+    }
+}
+
+/// A byte count for display, like `1.2 MB`.
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut value = bytes / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{value:.1} {}", UNITS[unit])
+    } else {
+        format!("{value:.0} {}", UNITS[unit])
+    }
+}
+
+/// Asks the browser how much storage this origin uses.
+pub async fn estimate_usage() -> Option<StorageUsage> {
+    let promise = window().navigator().storage().estimate().ok()?;
+    let estimate: web_sys::StorageEstimate = JsFuture::from(promise).await.ok()?.unchecked_into();
+    Some(StorageUsage {
+        usage: estimate.get_usage()?,
+        quota: estimate.get_quota()?,
+    })
+}
+
+// ─── Store ───
+
+/// The backend the state is saved to.
+pub enum Store {
+    IndexedDb(IdbStore),
+    LocalStorage,
+}
+
+impl Store {
+    /// Opens IndexedDB (importing the localStorage blob the first time) or
+    /// falls back to localStorage, and loads the saved state.
+    pub async fn open() -> (Store, AppState, Option<Recovery>) {
+        let opened = match IdbStore::open().await {
+            Ok(store) => store.load().await.map(|(state, recovery)| (store, state, recovery)),
+            Err(e) => Err(e),
+        };
+        match opened {
+            Ok((store, state, recovery)) => (Store::IndexedDb(store), state, recovery),
+            Err(e) => {
+                leptos::logging::warn!("IndexedDB unavailable, using localStorage: {e}");
+                let (state, recovery) = load_local();
+                (Store::LocalStorage, state, recovery)
+            }
+        }
+    }
+
+    pub async fn save(&self, state: &AppState) -> Result<(), String> {
+        match self {
+            Store::IndexedDb(store) => store.save(state).await,
+            Store::LocalStorage => {
+                let raw = encode_state(state).map_err(|e| e.to_string())?;
+                LocalStorage::raw()
+                    .set_item(STORAGE_KEY, &raw)
+                    .map_err(|e| format!("{e:?}"))
+            }
+        }
+    }
+}
+
+/// Loads the localStorage blob; unreadable data is quarantined next to it.
+fn load_local() -> (AppState, Option<Recovery>) {
+    let Ok(Some(raw)) = LocalStorage::raw().get_item(STORAGE_KEY) else {
+        return (AppState::default(), None);
+    };
+    match decode_state(&raw) {
+        Ok(state) => (state, None),
+        Err(e) => {
+            leptos::logging::error!("{e}");
+            let key = format!("{QUARANTINE_KEY_PREFIX}{}", js_sys::Date::now() as u64);
+            let backup_key = LocalStorage::raw().set_item(&key, &raw).is_ok().then_some(key);
+            (AppState::default(), Some(Recovery { raw, backup_key }))
+        }
+    }
+}
+
+// ─── IndexedDB ───
+
+/// One record per chat, written incrementally.
+pub struct IdbStore {
+    db: IdbDatabase,
+    /// What the database holds, so a save can skip unchanged records.
+    written: RefCell<Written>,
+}
+
+#[derive(Default)]
+struct Written {
+    /// Ids of the chat records that were loaded or saved; a save deletes
+    /// the ones no longer in the state.
+    ids: HashSet<String>,
+    chats: HashMap<String, ChatSession>,
+    settings: Option<String>,
+}
+
+/// The records of a database that has been saved to.
+struct Records {
+    version: u32,
+    /// The settings record without its version and chat order.
+    settings: Value,
+    /// Key and contents of each chat record, in the saved order.
+    chats: Vec<(String, Value)>,
+}
+
+impl IdbStore {
+    async fn open() -> Result<IdbStore, String> {
+        let factory = window()
+            .indexed_db()
+            .ok()
+            .flatten()
+            .ok_or("IndexedDB is not supported")?;
+        let request = factory.open_with_u32(DB_NAME, DB_VERSION).map_err(js_error)?;
+
+        let on_upgrade = Closure::once_into_js(move |event: web_sys::Event| {
+            let Some(db) = event
+                .target()
+                .and_then(|t| t.dyn_into::<IdbRequest>().ok())
+                .and_then(|r| r.result().ok())
+                .map(JsCast::unchecked_into::<IdbDatabase>)
+            else {
+                return;
+            };
+            for name in [CHATS, SETTINGS, QUARANTINE] {
+                if !db.object_store_names().contains(name) {
+                    let _ = db.create_object_store(name);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+        let db = request_result(&request).await?.unchecked_into();
+        Ok(IdbStore {
+            db,
+            written: RefCell::default(),
+        })
+    }
+
+    /// Reads every record, or imports the localStorage blob into a new database.
+    ///
+    /// Each chat is upgraded on its own: chats that can't be read are
+    /// quarantined and the rest load. Fails only if the database can't be
+    /// read at all, since saving over records that were never loaded would
+    /// lose them.
+    async fn load(&self) -> Result<(AppState, Option<Recovery>), String> {
+        let Some(Records { version, settings, chats }) = self.read_records().await? else {
+            return Ok(self.import_local_storage().await);
+        };
+
+        let mut data = settings.clone();
+        if let Value::Object(map) = &mut data {
+            map.insert("chats".to_string(), json!([]));
+        }
+        let (mut state, settings_failed) = match upgrade_state(version, data) {
+            Ok(state) => (state, false),
+            Err(e) => {
+                leptos::logging::error!("{e}");
+                (AppState::default(), true)
+            }
+        };
+        let mut failed = Vec::new();
+        for (key, chat) in chats {
+            match upgrade_chat(version, &settings, chat.clone()) {
+                Ok(chat) => state.chats.push(chat),
+                Err(e) => {
+                    leptos::logging::error!("Chat {key}: {e}");
+                    failed.push((key, chat));
+                }
+            }
+        }
+        // Chats that failed aren't remembered, so no save deletes them.
+        self.remember(&state);
+        if settings_failed {
+            self.written.borrow_mut().settings = None;
+        }
+        if !settings_failed && failed.is_empty() {
+            return Ok((state, None));
+        }
+
+        // Quarantined as an envelope holding only what failed to load.
+        let mut data = settings;
+        if let Value::Object(map) = &mut data {
+            let chats = failed.iter().map(|(_, chat)| chat.clone()).collect();
+            map.insert("chats".to_string(), Value::Array(chats));
+        }
+        let raw = json!({ "version": version, "data": data }).to_string();
+        let backup_key = self.quarantine(&raw).await;
+        if backup_key.is_some() {
+            // The copy in quarantine replaces them, so they aren't set aside again on every visit.
+            let keys: Vec<String> = failed.into_iter().map(|(key, _)| key).collect();
+            if let Err(e) = self.delete_chats(&keys).await {
+                leptos::logging::error!("Failed to remove quarantined chats: {e}");
+            }
+        }
+        Ok((state, Some(Recovery { raw, backup_key })))
+    }
+
+    /// The settings and chat records; `None` for a database never saved to.
+    async fn read_records(&self) -> Result<Option<Records>, String> {
+        let tx = self
+            .db
+            .transaction_with_str_sequence(&store_names(&[CHATS, SETTINGS]))
+            .map_err(js_error)?;
+        let settings = request_result(&store(&tx, SETTINGS)?.get(&SETTINGS_KEY.into()).map_err(js_error)?).await?;
+        let Some(settings) = settings.as_string() else {
+            return Ok(None);
+        };
+        let chats_store = store(&tx, CHATS)?;
+        let keys: js_sys::Array = request_result(&chats_store.get_all_keys().map_err(js_error)?)
+            .await?
+            .unchecked_into();
+        let chats: js_sys::Array = request_result(&chats_store.get_all().map_err(js_error)?)
+            .await?
+            .unchecked_into();
+
+        // A record that isn't JSON is kept as a string so it fails to load.
+        let parse = |raw: String| serde_json::from_str(&raw).unwrap_or(Value::String(raw));
+        let mut settings = parse(settings);
+        let (version, order) = match &mut settings {
+            Value::Object(map) => {
+                let version = map
+                    .remove("version")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|v| u32::try_from(v).ok())
+                    .unwrap_or(STATE_VERSION);
+                let order: Vec<String> = map
+                    .remove("chat_order")
+                    .and_then(|v| serde_json::from_value(v).ok())
+                    .unwrap_or_default();
+                (version, order)
+            }
+            _ => (STATE_VERSION, Vec::new()),
+        };
+
+        // Both lists come back in key order.
+        let mut chats: Vec<(String, Value)> = keys
+            .iter()
+            .zip(chats.iter())
+            .filter_map(|(key, chat)| Some((key.as_string()?, parse(chat.as_string()?))))
+            .collect();
+        // Listed order first; chats missing from the list (shouldn't happen) go last.
+        chats.sort_by_key(|(key, _)| order.iter().position(|o| o == key).unwrap_or(usize::MAX));
+
+        Ok(Some(Records { version, settings, chats }))
+    }
+
+    /// Moves the localStorage blob into this (empty) database.
+    ///
+    /// The blob is only removed once its contents are safely stored here,
+    /// either imported or quarantined.
+    async fn import_local_storage(&self) -> (AppState, Option<Recovery>) {
+        let Ok(Some(raw)) = LocalStorage::raw().get_item(STORAGE_KEY) else {
+            return (AppState::default(), None);
+        };
+        match decode_state(&raw) {
+            Ok(state) => {
+                if let Err(e) = self.save(&state).await {
+                    // Keep the blob for the next visit and use it for now.
+                    leptos::logging::error!("Failed to import saved chats: {e}");
+                    return (state, None);
+                }
+                LocalStorage::delete(STORAGE_KEY);
+                (state, None)
+            }
+            Err(e) => {
+                leptos::logging::error!("{e}");
+                let backup_key = self.quarantine(&raw).await;
+                // Without a backup the blob is still the only copy.
+                if backup_key.is_some() {
+                    LocalStorage::delete(STORAGE_KEY);
+                }
+                (AppState::default(), Some(Recovery { raw, backup_key }))
+            }
+        }
+    }
+
+    /// Writes the chats and settings that changed since the last save, and
+    /// deletes the chats that are gone, in one transaction.
+    async fn save(&self, state: &AppState) -> Result<(), String> {
+        let (changed, removed, settings) = {
+            let written = self.written.borrow();
+            let changed: Vec<&ChatSession> = state
+                .chats
+                .iter()
+                .filter(|chat| written.chats.get(&chat.id) != Some(*chat))
+                .collect();
+            let removed: Vec<String> = written
+                .ids
+                .iter()
+                .filter(|id| !state.chats.iter().any(|c| &c.id == *id))
+                .cloned()
+                .collect();
+            let settings = settings_record(state);
+            let settings = (written.settings.as_ref() != Some(&settings)).then_some(settings);
+            (changed, removed, settings)
+        };
+        if changed.is_empty() && removed.is_empty() && settings.is_none() {
+            return Ok(());
+        }
+
+        let tx = self
+            .db
+            .transaction_with_str_sequence_and_mode(
+                &store_names(&[CHATS, SETTINGS]),
+                IdbTransactionMode::Readwrite,
+            )
+            .map_err(js_error)?;
+        let chats = store(&tx, CHATS)?;
+        for chat in &changed {
+            let json = serde_json::to_string(chat).map_err(|e| e.to_string())?;
+            chats
+                .put_with_key(&json.into(), &chat.id.as_str().into())
+                .map_err(js_error)?;
+        }
+        for id in &removed {
+            chats.delete(&id.as_str().into()).map_err(js_error)?;
+        }
+        if let Some(settings) = &settings {
+            store(&tx, SETTINGS)?
+                .put_with_key(&settings.as_str().into(), &SETTINGS_KEY.into())
+                .map_err(js_error)?;
+        }
+        transaction_done(&tx).await?;
+
+        let mut written = self.written.borrow_mut();
+        for chat in changed {
+            written.ids.insert(chat.id.clone());
+            written.chats.insert(chat.id.clone(), chat.clone());
+        }
+        for id in removed {
+            written.ids.remove(&id);
+            written.chats.remove(&id);
+        }
+        if settings.is_some() {
+            written.settings = settings;
+        }
+        Ok(())
+    }
+
+    /// Records `state` as what the database holds.
+    fn remember(&self, state: &AppState) {
+        let mut written = self.written.borrow_mut();
+        written.ids = state.chats.iter().map(|c| c.id.clone()).collect();
+        written.chats = state.chats.iter().map(|c| (c.id.clone(), c.clone())).collect();
+        written.settings = Some(settings_record(state));
+    }
+
+    /// Deletes chat records without touching what a save compares against.
+    async fn delete_chats(&self, keys: &[String]) -> Result<(), String> {
+        let tx = self
+            .db
+            .transaction_with_str_and_mode(CHATS, IdbTransactionMode::Readwrite)
+            .map_err(js_error)?;
+        let chats = store(&tx, CHATS)?;
+        for key in keys {
+            chats.delete(&key.as_str().into()).map_err(js_error)?;
+        }
+        transaction_done(&tx).await
+    }
+
+    /// Stores an unreadable state blob, returning its key.
+    async fn quarantine(&self, raw: &str) -> Option<String> {
+        let key = (js_sys::Date::now() as u64).to_string();
+        let tx = self
+            .db
+            .transaction_with_str_and_mode(QUARANTINE, IdbTransactionMode::Readwrite)
+            .ok()?;
+        store(&tx, QUARANTINE)
+            .ok()?
+            .put_with_key(&raw.into(), &key.as_str().into())
+            .ok()?;
+        transaction_done(&tx).await.ok()?;
+        Some(format!("{DB_NAME}/{QUARANTINE}/{key}"))
+    }
+}
+
+/// The settings record: the state without its chats, plus their order.
+fn settings_record(state: &AppState) -> String {
+    json!({
+        "version": STATE_VERSION,
+        "active_chat_id": state.active_chat_id,
+        "language": state.language,
+        "custom_animals": state.custom_animals,
+        "chat_order": state.chats.iter().map(|c| &c.id).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+fn store_names(names: &[&str]) -> js_sys::Array {
+    names.iter().map(|name| JsValue::from_str(name)).collect()
+}
+
+fn store(tx: &IdbTransaction, name: &str) -> Result<IdbObjectStore, String> {
+    tx.object_store(name).map_err(js_error)
+}
+
+fn js_error(error: JsValue) -> String {
+    format!("{error:?}")
+}
+
+/// Waits for `request` and returns its result.
+async fn request_result(request: &IdbRequest) -> Result<JsValue, String> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let on_success = Closure::once_into_js(move |event: web_sys::Event| {
+            let result = event
+                .target()
+                .and_then(|t| t.dyn_into::<IdbRequest>().ok())
+                .and_then(|r| r.result().ok())
+                .unwrap_or_default();
+            let _ = resolve.call1(&JsValue::NULL, &result);
+        });
+        let on_error = Closure::once_into_js(move |event: web_sys::Event| {
+            let _ = reject.call1(&JsValue::NULL, &event);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await.map_err(js_error)
+}
+
+/// Waits until `tx` commits.
+async fn transaction_done(tx: &IdbTransaction) -> Result<(), String> {
+    // A failed request fires `error` and then `abort`, and every other
+    // pending request fails too, so this one may be called several times.
+    let mut on_error = None;
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let on_complete = Closure::once_into_js(move |_: web_sys::Event| {
+            let _ = resolve.call0(&JsValue::NULL);
+        });
+        let failed = Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| {
+            let _ = reject.call1(&JsValue::NULL, &event);
+        });
+        tx.set_oncomplete(Some(on_complete.unchecked_ref()));
+        tx.set_onerror(Some(failed.as_ref().unchecked_ref()));
+        tx.set_onabort(Some(failed.as_ref().unchecked_ref()));
+        on_error = Some(failed);
+    });
+    let result = JsFuture::from(promise).await;
+    // Unhooked before `on_error` is dropped, since `abort` may still be on its way.
+    tx.set_onerror(None);
+    tx.set_onabort(None);
+    drop(on_error);
+    result.map(|_| ()).map_err(js_error)
+}
@@ -21,12 +21,15 @@ const FONT_ASSETS = [
   '/public/fonts/material-symbols.woff2',
 ];
 
+const OUTBOX_SYNC_TAG = 'outbox';
+
 const EXPECTED_CACHES = new Set([APP_SHELL_CACHE, STATIC_CACHE, FONTS_CACHE]);
 
 const STATIC_ASSET_RE = /\.(wasm|js|css|svg|png|jpg|jpeg|webp|ico|json)$/i;
 const CONTENT_HASH_RE = /[-_][a-f0-9]{8,}\./i;
 
-const VALID_ANIMALS = new Set(['cat', 'chicken', 'elephant', 'octopus']);
+// Animal ids come from shared/animals.toml; only their shape is checked here.
+const ANIMAL_ID_RE = /^[a-z][a-z0-9_]{0,31}$/;
 
 // ── Install ──
 self.addEventListener('install', (event) => {
@@ -69,15 +72,12 @@ self.addEventListener('fetch', (event) => {
     return;
   }
 
-  if (request.method !== 'GET') {
-    return;
-  }
-
-  // 1. API requests → Network-Only (never cache)
+  // 1. API requests (any method) → Network-Only (never cache). Offline
+  // failures answer with an ApiError body so the app can queue the message.
   if (url.pathname.startsWith('/api/')) {
     event.respondWith(
       fetch(request).catch(() =>
-        new Response(JSON.stringify({ error: 'offline' }), {
+        new Response(JSON.stringify({ code: 'offline', message: 'Network unavailable' }), {
           status: 503,
           headers: { 'Content-Type': 'application/json' },
         })
@@ -86,6 +86,10 @@ self.addEventListener('fetch', (event) => {
     return;
   }
 
+  if (request.method !== 'GET') {
+    return;
+  }
+
   // 2. Navigation requests → Network-First (always try to get fresh HTML)
   if (request.mode === 'navigate') {
     event.respondWith(networkFirst(request, APP_SHELL_CACHE));
@@ -112,6 +116,19 @@ self.addEventListener('fetch', (event) => {
   event.respondWith(networkFirst(request, STATIC_CACHE));
 });
 
+// ── Background Sync ──
+// Queued chat messages live in the app's IndexedDB state, so the open pages
+// send them; the sync only wakes them up once the connection is back.
+self.addEventListener('sync', (event) => {
+  if (event.tag !== OUTBOX_SYNC_TAG) {
+    return;
+  }
+  event.waitUntil(
+    self.clients.matchAll({ type: 'window' })
+      .then((clients) => clients.forEach((client) => client.postMessage({ type: 'FLUSH_OUTBOX' })))
+  );
+});
+
 // ── Message Handler ──
 self.addEventListener('message', (event) => {
   // Validate origin — only accept messages from same origin
@@ -131,7 +148,7 @@ self.addEventListener('message', (event) => {
 
     case 'PRECACHE_THEME': {
       const { animal } = data;
-      if (typeof animal === 'string' && VALID_ANIMALS.has(animal)) {
+      if (typeof animal === 'string' && ANIMAL_ID_RE.test(animal)) {
         console.log(`[SW] Pre-caching theme: ${animal}`);
         // Future: pre-cache theme-specific CSS/SVG assets from R2
       }
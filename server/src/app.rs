@@ -0,0 +1,596 @@
+//! Routes and handlers: the worker's `/api/*` contract, backed by `chat-core`.
+//!
+//! Providers are `!Send`, having been written for the single-threaded
+//! Workers runtime, so every model call runs on a [`LocalPoolHandle`] thread
+//! and streamed events cross back to the response over a channel.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chat_core::providers::resilience::Timer;
+use chat_core::providers::{self, ChatPrompt, LlmProvider, ProviderError};
+use chat_core::rate_limit::{MemoryStore, RateDecision, RateLimits};
+use chat_core::{
+    config_error, invalid_body, provider_error, rate_limited_error, title, validate_request,
+    validate_title_request, with_model, Settings,
+};
+use futures_util::{stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use shared::{
+    ApiError, ApiErrorCode, ChatRequest, ChatResponse, ChatStreamEvent, ConversationSummary,
+    TitleRequest, TitleResponse,
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::task::{AbortOnDropHandle, LocalPoolHandle};
+
+use crate::platform::{EnvSettings, NativeTimer, ReqwestClient};
+
+/// Streamed frames buffered between the model thread and the connection.
+const STREAM_BUFFER: usize = 32;
+
+/// Longest wait for a provider to accept the connection.
+const PROVIDER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest silence from a provider, between any two reads. A whole-request
+/// timeout would cut long streamed replies short.
+const PROVIDER_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Everything the handlers share.
+#[derive(Clone)]
+pub struct AppState {
+    settings: Arc<EnvSettings>,
+    allowed_origin: HeaderValue,
+    limits: RateLimits,
+    store: Arc<MemoryStore>,
+    http: reqwest::Client,
+    pool: LocalPoolHandle,
+}
+
+impl AppState {
+    /// Reads `ALLOWED_ORIGIN` (falls back to "*") and the rate limits from
+    /// `settings`, and runs model calls on `threads` threads.
+    pub fn new(settings: EnvSettings, threads: usize) -> Self {
+        let allowed_origin = settings
+            .var("ALLOWED_ORIGIN")
+            .and_then(|origin| HeaderValue::from_str(&origin).ok())
+            .unwrap_or(HeaderValue::from_static("*"));
+        Self {
+            limits: RateLimits::from_settings(&settings),
+            settings: Arc::new(settings),
+            allowed_origin,
+            store: Arc::default(),
+            http: reqwest::Client::builder()
+                .connect_timeout(PROVIDER_CONNECT_TIMEOUT)
+                .read_timeout(PROVIDER_READ_TIMEOUT)
+                .build()
+                .expect("Failed to build the HTTP client"),
+            pool: LocalPoolHandle::new(threads.max(1)),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Router
+// ═══════════════════════════════════════════════
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        // Main chat endpoint
+        .route("/api/chat", post(handle_chat).options(preflight))
+        // Streaming chat endpoint (Server-Sent Events)
+        .route("/api/chat/stream", post(handle_chat_stream).options(preflight))
+        // Automatic chat titles
+        .route("/api/title", post(handle_title).options(preflight))
+        // Health check
+        .route("/api/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .layer(middleware::from_fn_with_state(state.clone(), cors))
+        .with_state(state)
+}
+
+async fn preflight() -> StatusCode {
+    StatusCode::OK
+}
+
+// ═══════════════════════════════════════════════
+// Chat Handler
+// ═══════════════════════════════════════════════
+
+async fn handle_chat(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let body: ChatRequest = match parse_body(&body) {
+        Ok(b) => b,
+        Err(error) => return error_response(&error),
+    };
+
+    if let Err(error) = validate_request(&body) {
+        return error_response(&error);
+    }
+
+    let rate = match enforce_rate_limit(&state, &headers, peer) {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision),
+    };
+
+    let settings = state.settings.clone();
+    let http = state.http.clone();
+    let reply = run_local(&state, move || async move {
+        let provider = provider_for(&settings, http)?;
+        let (prompt, summary) = fitted_prompt(&*provider, body).await;
+        match provider.generate(&prompt).await {
+            Ok(completion) => Ok(ChatResponse {
+                response: completion.text,
+                tokens_used: completion.tokens_used,
+                alternatives: completion.alternatives,
+                summary,
+                model: Some(provider.model().to_string()),
+            }),
+            Err(e) => {
+                log_provider_error(&*provider, &e);
+                Err(provider_error(&e))
+            }
+        }
+    })
+    .await;
+
+    match reply {
+        Ok(reply) => with_rate_headers(Json(reply).into_response(), &rate),
+        Err(error) => error_response(&error),
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Streaming Chat Handler
+// ═══════════════════════════════════════════════
+
+async fn handle_chat_stream(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let body: ChatRequest = match parse_body(&body) {
+        Ok(b) => b,
+        Err(error) => return error_response(&error),
+    };
+
+    if let Err(error) = validate_request(&body) {
+        return error_response(&error);
+    }
+
+    let rate = match enforce_rate_limit(&state, &headers, peer) {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision),
+    };
+
+    // The model thread reports whether the stream opened, then sends frames.
+    let (opened_tx, opened_rx) = oneshot::channel::<Result<(), ApiError>>();
+    let (frames_tx, frames_rx) = mpsc::channel::<Bytes>(STREAM_BUFFER);
+    let settings = state.settings.clone();
+    let http = state.http.clone();
+
+    // Aborted if the client hangs up before the stream opens.
+    let task = AbortOnDropHandle::new(state.pool.spawn_pinned(move || async move {
+        let opened = async {
+            let provider = provider_for(&settings, http)?;
+            let (prompt, summary) = fitted_prompt(&*provider, body).await;
+            match provider.stream(&prompt).await {
+                Ok(events) => Ok((provider.model().to_string(), summary, events)),
+                Err(e) => {
+                    log_provider_error(&*provider, &e);
+                    Err(provider_error(&e))
+                }
+            }
+        }
+        .await;
+        let (model, summary, events) = match opened {
+            Ok(opened) => opened,
+            Err(error) => {
+                let _ = opened_tx.send(Err(error));
+                return;
+            }
+        };
+        let _ = opened_tx.send(Ok(()));
+
+        // A new summary goes first so the client can cache it even if the reply fails midway.
+        let summary_event = summary.map(|summary| ChatStreamEvent::Summary { summary });
+        let mut frames = stream::iter(summary_event)
+            .chain(events)
+            .map(|event| Bytes::from(with_model(event, &model).to_sse()));
+        while let Some(frame) = frames.next().await {
+            // The client hung up: dropping `frames` drops the upstream request.
+            if frames_tx.send(frame).await.is_err() {
+                break;
+            }
+        }
+    }));
+
+    match opened_rx.await {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => return error_response(&error),
+        Err(_) => return error_response(&task_failed()),
+    }
+    // From here on the task ends when the response body is dropped.
+    task.detach();
+
+    let frames = stream::unfold(frames_rx, |mut frames| async move {
+        let frame = frames.recv().await?;
+        Some((Ok::<_, Infallible>(frame), frames))
+    });
+    let mut response = Body::from_stream(frames).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/event-stream; charset=utf-8"),
+    );
+    with_rate_headers(response, &rate)
+}
+
+// ═══════════════════════════════════════════════
+// Title Handler
+// ═══════════════════════════════════════════════
+
+/// Names a chat after its first exchange. Counts against the same rate
+/// limits as a message, since it is one more model call.
+async fn handle_title(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let body: TitleRequest = match parse_body(&body) {
+        Ok(b) => b,
+        Err(error) => return error_response(&error),
+    };
+
+    if let Err(error) = validate_title_request(&body) {
+        return error_response(&error);
+    }
+
+    let rate = match enforce_rate_limit(&state, &headers, peer) {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision),
+    };
+
+    let settings = state.settings.clone();
+    let http = state.http.clone();
+    let title = run_local(&state, move || async move {
+        let provider = provider_for(&settings, http)?;
+        title::generate(&*provider, body).await.map_err(|e| {
+            log_provider_error(&*provider, &e);
+            provider_error(&e)
+        })
+    })
+    .await;
+
+    match title {
+        Ok(title) => with_rate_headers(Json(TitleResponse { title }).into_response(), &rate),
+        Err(error) => error_response(&error),
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Providers
+// ═══════════════════════════════════════════════
+
+/// Runs `task` on a pool thread, where `!Send` providers can live.
+///
+/// axum drops the handler when the client hangs up, which aborts the task
+/// and with it the upstream call.
+async fn run_local<T, F, Fut>(state: &AppState, task: F) -> Result<T, ApiError>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, ApiError>> + 'static,
+    T: Send + 'static,
+{
+    AbortOnDropHandle::new(state.pool.spawn_pinned(task)).await.unwrap_or_else(|e| {
+        eprintln!("Chat task failed: {e}");
+        Err(task_failed())
+    })
+}
+
+/// The configured provider. Must be called on a pool thread.
+fn provider_for(
+    settings: &EnvSettings,
+    http: reqwest::Client,
+) -> Result<Box<dyn LlmProvider>, ApiError> {
+    providers::from_settings(settings, Rc::new(ReqwestClient::new(http)), NativeTimer).map_err(
+        |e| {
+            eprintln!("Provider configuration error: {e}");
+            config_error()
+        },
+    )
+}
+
+/// Builds the prompt and fits the conversation into the animal's memory budget.
+///
+/// Returns the new summary to hand back to the client, if one was made.
+async fn fitted_prompt(
+    provider: &dyn LlmProvider,
+    body: ChatRequest,
+) -> (ChatPrompt, Option<ConversationSummary>) {
+    let (prompt, summary) = chat_core::fitted_prompt(provider, body).await;
+    let summary = summary.unwrap_or_else(|e| {
+        eprintln!("Conversation summary failed, dropping old turns: {e}");
+        None
+    });
+    (prompt, summary)
+}
+
+// ═══════════════════════════════════════════════
+// Rate Limiting
+// ═══════════════════════════════════════════════
+
+/// Counts the request against its client's limits; `Err` carries the refusal.
+fn enforce_rate_limit(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> Result<RateDecision, RateDecision> {
+    let client = client_key(headers, peer);
+    let decision = state
+        .store
+        .acquire_now(&client, &state.limits, NativeTimer.now_ms());
+    if decision.allowed {
+        Ok(decision)
+    } else {
+        Err(decision)
+    }
+}
+
+/// Picks the rate-limit key: the peer address, unless the peer is a local
+/// proxy (Trunk in development, a reverse proxy in front of the server), in
+/// which case the anonymous `X-Client-Token` the frontend sends.
+fn client_key(headers: &HeaderMap, peer: SocketAddr) -> String {
+    let token = headers
+        .get("X-Client-Token")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && v.len() <= 128);
+    match token {
+        Some(token) if peer.ip().is_loopback() => format!("token:{token}"),
+        _ => format!("ip:{}", peer.ip()),
+    }
+}
+
+fn rate_limited_response(decision: &RateDecision) -> Response {
+    with_rate_headers(error_response(&rate_limited_error(decision)), decision)
+}
+
+/// Adds `X-RateLimit-Limit/Remaining/Reset` (reset in seconds).
+fn with_rate_headers(mut response: Response, decision: &RateDecision) -> Response {
+    let headers = response.headers_mut();
+    headers.insert("X-RateLimit-Limit", decision.limit.into());
+    headers.insert("X-RateLimit-Remaining", decision.remaining.into());
+    headers.insert("X-RateLimit-Reset", decision.reset_secs.into());
+    response
+}
+
+// ═══════════════════════════════════════════════
+// Error Responses
+// ═══════════════════════════════════════════════
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body).map_err(|e| {
+        eprintln!("Invalid request body: {e}");
+        invalid_body()
+    })
+}
+
+/// A model thread that panicked or was shut down.
+fn task_failed() -> ApiError {
+    ApiError::new(ApiErrorCode::UpstreamUnavailable, "AI service unavailable")
+}
+
+/// A client that hung up is routine, not an upstream failure.
+fn log_provider_error(provider: &dyn LlmProvider, error: &ProviderError) {
+    match error {
+        ProviderError::Cancelled => eprintln!("{}: {error}", provider.model()),
+        _ => eprintln!("{} API error: {error}", provider.model()),
+    }
+}
+
+/// Serializes an `ApiError` as the JSON body, with its status and `Retry-After`.
+fn error_response(error: &ApiError) -> Response {
+    let status = StatusCode::from_u16(error.status()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut response = (status, Json(error)).into_response();
+    if let Some(seconds) = error.retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, seconds.into());
+    }
+    response
+}
+
+// ═══════════════════════════════════════════════
+// CORS
+// ═══════════════════════════════════════════════
+
+/// Adds CORS and security headers to every response.
+async fn cors(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    let mut set = |name: &'static str, value: HeaderValue| {
+        headers.insert(HeaderName::from_static(name), value);
+    };
+    set("access-control-allow-origin", state.allowed_origin.clone());
+    set("access-control-allow-methods", HeaderValue::from_static("GET, POST, OPTIONS"));
+    set("access-control-allow-headers", HeaderValue::from_static("Content-Type, X-Client-Token"));
+    set("access-control-max-age", HeaderValue::from_static("86400"));
+    set(
+        "access-control-expose-headers",
+        HeaderValue::from_static(
+            "Retry-After, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset",
+        ),
+    );
+    set("x-content-type-options", HeaderValue::from_static("nosniff"));
+    set("cache-control", HeaderValue::from_static("no-store"));
+    response
+}
+
+// ═══════════════════════════════════════════════
+// Tests
+// ═══════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::connect_info::MockConnectInfo;
+    use shared::{AnimalType, IntelligenceLevel, Language};
+    use tower::ServiceExt;
+
+    fn app(settings: &[(&str, &str)]) -> Router {
+        let state = AppState::new(settings.iter().copied().collect(), 1);
+        router(state).layer(MockConnectInfo(SocketAddr::from(([203, 0, 113, 7], 4000))))
+    }
+
+    fn chat_request(message: &str) -> ChatRequest {
+        ChatRequest {
+            message: message.to_string(),
+            animal: AnimalType::OCTOPUS,
+            intelligence: IntelligenceLevel::High,
+            language: Language::Es,
+            history: vec![],
+            candidates: None,
+            summary: None,
+            persona: None,
+        }
+    }
+
+    async fn post_json(app: Router, path: &str, body: &impl serde::Serialize) -> Response {
+        let request = Request::post(path)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    async fn read<T: DeserializeOwned>(response: Response) -> T {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn health_check_answers_with_cors_headers() {
+        let response = app(&[])
+            .oneshot(Request::get("/api/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+        assert_eq!(read::<serde_json::Value>(response).await, json!({ "status": "ok" }));
+    }
+
+    #[tokio::test]
+    async fn chat_replies_through_the_configured_provider() {
+        let app = app(&[("LLM_PROVIDER", "mock"), ("ALLOWED_ORIGIN", "https://example.com")]);
+        let response = post_json(app, "/api/chat", &chat_request("Hola")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://example.com");
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "9");
+        let reply: ChatResponse = read(response).await;
+        assert!(!reply.response.is_empty());
+        assert_eq!(reply.model.as_deref(), Some(providers::mock::MOCK_MODEL));
+    }
+
+    #[tokio::test]
+    async fn hanging_up_cancels_the_upstream_call() {
+        use tokio::io::AsyncReadExt;
+
+        let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", upstream.local_addr().unwrap());
+        let app = app(&[("LLM_PROVIDER", "llamacpp"), ("LLAMACPP_BASE_URL", &base_url)]);
+
+        // The client gives up as soon as the provider calls upstream.
+        let body = chat_request("Hola");
+        let (mut upstream_call, _) = tokio::select! {
+            accepted = upstream.accept() => accepted.unwrap(),
+            _ = post_json(app, "/api/chat", &body) => {
+                panic!("answered without calling upstream")
+            }
+        };
+
+        let mut buf = vec![0; 64 * 1024];
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while matches!(upstream_call.read(&mut buf).await, Ok(n) if n > 0) {}
+        })
+        .await;
+        assert!(closed.is_ok(), "the upstream call outlived the client");
+    }
+
+    #[tokio::test]
+    async fn stream_sends_deltas_and_a_done_event() {
+        let app = app(&[("LLM_PROVIDER", "mock")]);
+        let response = post_json(app, "/api/chat/stream", &chat_request("Hola")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream; charset=utf-8");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let events: Vec<ChatStreamEvent> = shared::SseDecoder::default()
+            .push(&bytes)
+            .iter()
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+
+        assert!(matches!(events.first(), Some(ChatStreamEvent::Delta { .. })));
+        assert!(matches!(
+            events.last(),
+            Some(ChatStreamEvent::Done { model: Some(model), .. }) if model == providers::mock::MOCK_MODEL
+        ));
+    }
+
+    #[tokio::test]
+    async fn bad_requests_get_api_errors() {
+        let app = app(&[("LLM_PROVIDER", "mock")]);
+
+        let empty = post_json(app.clone(), "/api/chat", &chat_request("  ")).await;
+        assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(read::<ApiError>(empty).await.code, ApiErrorCode::EmptyMessage);
+
+        let garbage = post_json(app, "/api/chat", &json!({ "message": 3 })).await;
+        assert_eq!(read::<ApiError>(garbage).await.code, ApiErrorCode::InvalidRequest);
+
+        let unconfigured = post_json(self::app(&[]), "/api/chat", &chat_request("Hola")).await;
+        assert_eq!(unconfigured.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(read::<ApiError>(unconfigured).await.code, ApiErrorCode::ConfigError);
+    }
+
+    #[tokio::test]
+    async fn clients_over_the_limit_are_told_when_to_retry() {
+        let app = app(&[("LLM_PROVIDER", "mock"), ("RATE_LIMIT_PER_MINUTE", "1")]);
+        assert!(post_json(app.clone(), "/api/chat", &chat_request("Hola")).await.status().is_success());
+
+        let refused = post_json(app, "/api/chat", &chat_request("Hola")).await;
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(refused.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(refused.headers()["x-ratelimit-remaining"], "0");
+        assert_eq!(read::<ApiError>(refused).await.code, ApiErrorCode::RateLimited);
+    }
+
+    #[test]
+    fn local_proxies_are_told_apart_by_client_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Client-Token", HeaderValue::from_static("abc"));
+        let local = SocketAddr::from(([127, 0, 0, 1], 1));
+        let remote = SocketAddr::from(([198, 51, 100, 2], 1));
+
+        assert_eq!(client_key(&headers, local), "token:abc");
+        assert_eq!(client_key(&headers, remote), "ip:198.51.100.2");
+        assert_eq!(client_key(&HeaderMap::new(), local), "ip:127.0.0.1");
+    }
+}
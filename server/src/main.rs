@@ -0,0 +1,43 @@
+//! Self-hosted API server: the worker's `/api/*` contract as a native
+//! binary, for running on an ordinary machine without Node or wrangler.
+//!
+//! Configured through the environment with the worker's variable names, plus
+//! `BIND_ADDR` (default `127.0.0.1:8787`, where Trunk proxies `/api` in
+//! development).
+
+use std::net::SocketAddr;
+
+use chat_core::Settings;
+
+mod app;
+mod platform;
+
+use app::AppState;
+use platform::EnvSettings;
+
+const DEFAULT_BIND_ADDR: &str = "127.0.0.1:8787";
+
+#[tokio::main]
+async fn main() {
+    let settings = EnvSettings::from_process();
+    let addr = settings
+        .var("BIND_ADDR")
+        .unwrap_or_else(|| DEFAULT_BIND_ADDR.to_string());
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let app = app::router(AppState::new(settings, threads));
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind {addr}: {e}"));
+    eprintln!("Listening on http://{addr}");
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+    .expect("Server error");
+}
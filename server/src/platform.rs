@@ -0,0 +1,124 @@
+//! The native side of `chat-core`'s host traits: `reqwest` for
+//! [`HttpClient`], Tokio for [`Timer`] and the process environment for
+//! [`Settings`].
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chat_core::providers::resilience::Timer;
+use chat_core::providers::{status_error, ByteStream, HttpClient, ProviderError};
+use chat_core::Settings;
+use futures_util::StreamExt;
+
+// ═══════════════════════════════════════════════
+// HTTP
+// ═══════════════════════════════════════════════
+
+/// Outgoing requests through a shared `reqwest` connection pool.
+pub struct ReqwestClient {
+    client: reqwest::Client,
+}
+
+impl ReqwestClient {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl HttpClient for ReqwestClient {
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: String,
+    ) -> Result<ByteStream, ProviderError> {
+        let mut request = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ProviderError::Network(e.to_string()))?;
+
+        let status = response.status().as_u16();
+        if status != 200 {
+            let retry_after = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = response.text().await.unwrap_or_default();
+            return Err(status_error(status, retry_after.as_deref(), body));
+        }
+
+        Ok(response
+            .bytes_stream()
+            .map(|chunk| {
+                chunk.map(|bytes| bytes.to_vec()).map_err(|e| {
+                    eprintln!("Upstream stream error: {e}");
+                    e.to_string()
+                })
+            })
+            .boxed_local())
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Timer
+// ═══════════════════════════════════════════════
+
+/// Wall clock, a randomly seeded hash for jitter and Tokio's sleep.
+pub struct NativeTimer;
+
+#[async_trait::async_trait(?Send)]
+impl Timer for NativeTimer {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+
+    fn jitter(&self) -> f64 {
+        let random = RandomState::new().hash_one(self.now_ms());
+        (random >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    async fn sleep(&self, ms: u64) {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Settings
+// ═══════════════════════════════════════════════
+
+/// Configuration read once at startup, with the worker's variable names
+/// (`LLM_PROVIDER`, `GEMINI_API_KEY`, `RATE_LIMIT_PER_MINUTE`...).
+#[derive(Debug, Clone, Default)]
+pub struct EnvSettings(HashMap<String, String>);
+
+impl EnvSettings {
+    pub fn from_process() -> Self {
+        Self(std::env::vars().collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for EnvSettings {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl Settings for EnvSettings {
+    fn var(&self, name: &str) -> Option<String> {
+        self.0.get(name).filter(|v| !v.is_empty()).cloned()
+    }
+}
@@ -0,0 +1,243 @@
+//! Compiles `animals.toml` into static registry data.
+//!
+//! Emits `$OUT_DIR/animals.rs`, which `src/animals.rs` includes: the
+//! `ANIMALS` table, the `ALL` handle list and one `AnimalType::<ID>` constant
+//! per entry. Malformed manifests fail the build instead of the app.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::{env, fs, path::Path};
+
+use serde::Deserialize;
+
+const MANIFEST: &str = "animals.toml";
+
+/// Context budget for animals that don't set `memory_tokens`.
+const DEFAULT_MEMORY_TOKENS: u32 = 4_000;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    animal: Vec<Animal>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Animal {
+    id: String,
+    svg: String,
+    label: Localized<String>,
+    sound: Localized<String>,
+    personality: Localized<String>,
+    catchphrases: Localized<Vec<String>>,
+    #[serde(default)]
+    memory_tokens: Option<u32>,
+    offline: Offline,
+    theme: Theme,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Offline {
+    openers: Localized<Vec<String>>,
+    topics: Localized<Vec<String>>,
+    reactions: Vec<Reaction>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Reaction {
+    keywords: Localized<Vec<String>>,
+    replies: Localized<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Localized<T> {
+    es: T,
+    en: T,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Theme {
+    primary: String,
+    primary_dark: String,
+    primary_light: String,
+    bg: String,
+    surface: String,
+    surface_hover: String,
+    surface_alt: String,
+    on_primary: String,
+    text: String,
+    text_brand: String,
+    text_secondary: String,
+    text_muted: String,
+    border: String,
+    bubble_user: String,
+    bubble_ai: String,
+    overlay: String,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={MANIFEST}");
+
+    let source = fs::read_to_string(MANIFEST).expect("read animals.toml");
+    let manifest: Manifest = toml::from_str(&source).expect("parse animals.toml");
+    validate(&manifest);
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("animals.rs");
+    fs::write(out, generate(&manifest)).expect("write animals.rs");
+}
+
+fn validate(manifest: &Manifest) {
+    assert!(!manifest.animal.is_empty(), "{MANIFEST}: no animals defined");
+    assert!(manifest.animal.len() <= u8::MAX as usize, "{MANIFEST}: too many animals");
+
+    let mut ids = HashSet::new();
+    for animal in &manifest.animal {
+        let id = &animal.id;
+        assert!(
+            id.starts_with(|c: char| c.is_ascii_lowercase())
+                && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+            "{MANIFEST}: invalid id '{id}' (use lowercase ASCII, digits and '_')"
+        );
+        assert!(ids.insert(id.clone()), "{MANIFEST}: duplicate id '{id}'");
+
+        let texts = [
+            &animal.svg,
+            &animal.label.es,
+            &animal.label.en,
+            &animal.sound.es,
+            &animal.sound.en,
+            &animal.personality.es,
+            &animal.personality.en,
+        ];
+        assert!(
+            texts.iter().all(|t| !t.trim().is_empty()),
+            "{MANIFEST}: '{id}' has an empty field"
+        );
+        assert!(
+            !animal.catchphrases.es.is_empty() && !animal.catchphrases.en.is_empty(),
+            "{MANIFEST}: '{id}' needs at least one catchphrase per language"
+        );
+        let offline = &animal.offline;
+        let lists = [&offline.openers, &offline.topics]
+            .into_iter()
+            .chain(offline.reactions.iter().flat_map(|r| [&r.keywords, &r.replies]));
+        for list in lists {
+            assert!(
+                !list.es.is_empty() && !list.en.is_empty(),
+                "{MANIFEST}: '{id}' offline lists need at least one entry per language"
+            );
+        }
+        assert!(
+            !offline.reactions.is_empty(),
+            "{MANIFEST}: '{id}' needs at least one offline reaction"
+        );
+        for keyword in offline.reactions.iter().flat_map(|r| r.keywords.es.iter().chain(&r.keywords.en)) {
+            assert!(
+                !keyword.is_empty()
+                    && keyword.chars().all(|c| c.is_lowercase() || c.is_ascii_digit())
+                    && !keyword.chars().any(|c| "áéíóúàèìòùäëïöüâêîôû".contains(c)),
+                "{MANIFEST}: '{id}' offline keyword '{keyword}' must be one lowercase word without accents"
+            );
+        }
+        assert!(
+            animal.svg.trim_start().starts_with("<svg"),
+            "{MANIFEST}: '{id}' svg must be an inline <svg> element"
+        );
+        assert!(
+            animal.memory_tokens.is_none_or(|tokens| tokens >= 1_000),
+            "{MANIFEST}: '{id}' memory_tokens must be at least 1000"
+        );
+    }
+}
+
+fn generate(manifest: &Manifest) -> String {
+    let mut code = String::from("// @generated by build.rs from animals.toml — do not edit.\n\n");
+
+    writeln!(code, "pub static ANIMALS: [AnimalSpec; {}] = [", manifest.animal.len()).unwrap();
+    for animal in &manifest.animal {
+        let t = &animal.theme;
+        writeln!(
+            code,
+            "    AnimalSpec {{\n        id: {:?},\n        label: {},\n        sound: {},\n        \
+             personality: {},\n        catchphrases: Localized {{ es: &{:?}, en: &{:?} }},\n        \
+             svg: {:?},\n        memory_tokens: {},\n        offline: {},\n        theme: ThemeColors {{\n            primary: {:?},\n            \
+             primary_dark: {:?},\n            primary_light: {:?},\n            bg: {:?},\n            \
+             surface: {:?},\n            surface_hover: {:?},\n            surface_alt: {:?},\n            \
+             on_primary: {:?},\n            text: {:?},\n            text_brand: {:?},\n            \
+             text_secondary: {:?},\n            text_muted: {:?},\n            border: {:?},\n            \
+             bubble_user: {:?},\n            bubble_ai: {:?},\n            overlay: {:?},\n        }},\n    }},",
+            animal.id,
+            localized(&animal.label),
+            localized(&animal.sound),
+            localized(&animal.personality),
+            animal.catchphrases.es,
+            animal.catchphrases.en,
+            animal.svg.trim(),
+            animal.memory_tokens.unwrap_or(DEFAULT_MEMORY_TOKENS),
+            offline(&animal.offline),
+            t.primary,
+            t.primary_dark,
+            t.primary_light,
+            t.bg,
+            t.surface,
+            t.surface_hover,
+            t.surface_alt,
+            t.on_primary,
+            t.text,
+            t.text_brand,
+            t.text_secondary,
+            t.text_muted,
+            t.border,
+            t.bubble_user,
+            t.bubble_ai,
+            t.overlay,
+        )
+        .unwrap();
+    }
+    code.push_str("];\n\n");
+
+    writeln!(code, "pub(crate) static ALL: [AnimalType; {}] = [", manifest.animal.len()).unwrap();
+    for index in 0..manifest.animal.len() {
+        writeln!(code, "    AnimalType({index}),").unwrap();
+    }
+    code.push_str("];\n\n");
+
+    code.push_str("impl AnimalType {\n");
+    for (index, animal) in manifest.animal.iter().enumerate() {
+        writeln!(
+            code,
+            "    pub const {}: AnimalType = AnimalType({index});",
+            animal.id.to_uppercase()
+        )
+        .unwrap();
+    }
+    code.push_str("}\n");
+
+    code
+}
+
+fn localized(text: &Localized<String>) -> String {
+    format!("Localized {{ es: {:?}, en: {:?} }}", text.es, text.en)
+}
+
+fn offline(offline: &Offline) -> String {
+    let reactions: Vec<String> = offline
+        .reactions
+        .iter()
+        .map(|r| format!("Reaction {{ keywords: {}, replies: {} }}", list(&r.keywords), list(&r.replies)))
+        .collect();
+    format!(
+        "OfflineSpec {{ openers: {}, topics: {}, reactions: &[{}] }}",
+        list(&offline.openers),
+        list(&offline.topics),
+        reactions.join(", ")
+    )
+}
+
+fn list(texts: &Localized<Vec<String>>) -> String {
+    format!("Localized {{ es: &{:?}, en: &{:?} }}", texts.es, texts.en)
+}
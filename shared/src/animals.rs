@@ -0,0 +1,103 @@
+//! Animal registry, compiled from `animals.toml` by `build.rs`.
+
+use crate::{AnimalType, Language};
+
+/// A value with one variant per supported [`Language`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Localized<T: 'static> {
+    pub es: T,
+    pub en: T,
+}
+
+impl<T: Copy> Localized<T> {
+    pub fn get(&self, lang: Language) -> T {
+        match lang {
+            Language::Es => self.es,
+            Language::En => self.en,
+        }
+    }
+}
+
+/// Colours applied to the `--clr-*` CSS custom properties while an animal is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThemeColors {
+    pub primary: &'static str,
+    pub primary_dark: &'static str,
+    pub primary_light: &'static str,
+    pub bg: &'static str,
+    pub surface: &'static str,
+    pub surface_hover: &'static str,
+    pub surface_alt: &'static str,
+    pub on_primary: &'static str,
+    pub text: &'static str,
+    pub text_brand: &'static str,
+    pub text_secondary: &'static str,
+    pub text_muted: &'static str,
+    pub border: &'static str,
+    pub bubble_user: &'static str,
+    pub bubble_ai: &'static str,
+    pub overlay: &'static str,
+}
+
+impl ThemeColors {
+    /// `(custom property, value)` pairs, ready to be set on an element's style.
+    pub fn css_vars(&self) -> [(&'static str, &'static str); 16] {
+        [
+            ("--clr-primary", self.primary),
+            ("--clr-primary-dark", self.primary_dark),
+            ("--clr-primary-light", self.primary_light),
+            ("--clr-bg", self.bg),
+            ("--clr-surface", self.surface),
+            ("--clr-surface-hover", self.surface_hover),
+            ("--clr-surface-alt", self.surface_alt),
+            ("--clr-on-primary", self.on_primary),
+            ("--clr-text", self.text),
+            ("--clr-text-brand", self.text_brand),
+            ("--clr-text-secondary", self.text_secondary),
+            ("--clr-text-muted", self.text_muted),
+            ("--clr-border", self.border),
+            ("--clr-bubble-user", self.bubble_user),
+            ("--clr-bubble-ai", self.bubble_ai),
+            ("--clr-overlay", self.overlay),
+        ]
+    }
+}
+
+/// Everything the app knows about one animal persona.
+#[derive(Debug)]
+pub struct AnimalSpec {
+    pub id: &'static str,
+    pub label: Localized<&'static str>,
+    /// Onomatopoeia shown on the empty chat screen.
+    pub sound: Localized<&'static str>,
+    /// Base system prompt for the persona.
+    pub personality: Localized<&'static str>,
+    pub catchphrases: Localized<&'static [&'static str]>,
+    /// Inline SVG drawn with `currentColor`.
+    pub svg: &'static str,
+    /// Estimated tokens of recent conversation sent verbatim to the model.
+    pub memory_tokens: u32,
+    /// Material for replies written without a connection.
+    pub offline: OfflineSpec,
+    pub theme: ThemeColors,
+}
+
+/// How an animal answers when the model can't be reached; see `offline_reply`.
+#[derive(Debug)]
+pub struct OfflineSpec {
+    /// Gestures and sounds that open a reply.
+    pub openers: Localized<&'static [&'static str]>,
+    /// What the animal keeps coming back to.
+    pub topics: Localized<&'static [&'static str]>,
+    pub reactions: &'static [Reaction],
+}
+
+/// Replies used when the message contains one of `keywords`.
+#[derive(Debug)]
+pub struct Reaction {
+    /// Word starts, lowercase and without accents.
+    pub keywords: Localized<&'static [&'static str]>,
+    pub replies: Localized<&'static [&'static str]>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/animals.rs"));
@@ -0,0 +1,374 @@
+//! Chats as files: a lossless JSON export that can be imported again, and a
+//! readable Markdown transcript.
+//!
+//! The JSON file carries the state version, so exports from older builds go
+//! through the same migrations as stored data.
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::{
+    ChatMessage, ChatSession, CustomAnimal, Language, Role, STATE_VERSION, StateError,
+    upgrade_state,
+};
+
+/// `kind` of a JSON export, so other JSON files are refused.
+pub const EXPORT_KIND: &str = "inteligencia-animal/chats";
+
+#[derive(Serialize)]
+struct ExportFile<'a> {
+    kind: &'static str,
+    version: u32,
+    chats: &'a [ChatSession],
+    /// The custom animals the chats use, so they can be played elsewhere.
+    custom_animals: Vec<&'a CustomAnimal>,
+}
+
+/// Chats and custom animals read from a JSON export.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatExport {
+    pub chats: Vec<ChatSession>,
+    pub custom_animals: Vec<CustomAnimal>,
+}
+
+/// Serializes `chats`, with the custom animals they use, as a JSON export.
+pub fn export_json(
+    chats: &[ChatSession],
+    custom_animals: &[CustomAnimal],
+) -> Result<String, serde_json::Error> {
+    let used = |animal: &CustomAnimal| {
+        chats.iter().any(|chat| {
+            chat.custom_animal.as_deref() == Some(animal.id.as_str())
+                || chat
+                    .messages
+                    .messages()
+                    .iter()
+                    .any(|m| m.meta.custom_animal.as_deref() == Some(animal.id.as_str()))
+        })
+    };
+    serde_json::to_string_pretty(&ExportFile {
+        kind: EXPORT_KIND,
+        version: STATE_VERSION,
+        chats,
+        custom_animals: custom_animals.iter().filter(|a| used(a)).collect(),
+    })
+}
+
+/// Reads and validates a JSON export.
+pub fn parse_export(raw: &str) -> Result<ChatExport, StateError> {
+    let unreadable = |e: &str| StateError::Unreadable(e.to_string());
+    let value: Value = serde_json::from_str(raw).map_err(|e| unreadable(&e.to_string()))?;
+    if value.get("kind").and_then(Value::as_str) != Some(EXPORT_KIND) {
+        return Err(unreadable("Not a chat export"));
+    }
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| unreadable("Invalid export version"))?;
+    let data = json!({
+        "chats": value.get("chats").cloned().unwrap_or_else(|| json!([])),
+        "custom_animals": value.get("custom_animals").cloned().unwrap_or_else(|| json!([])),
+        // Per-chat languages were added in version 1; older chats take Spanish.
+        "language": "es",
+    });
+    let state = upgrade_state(version, data)?;
+
+    let mut ids = std::collections::HashSet::new();
+    for chat in &state.chats {
+        if chat.id.trim().is_empty() {
+            return Err(unreadable("A chat has no id"));
+        }
+        if !ids.insert(chat.id.as_str()) {
+            return Err(StateError::Unreadable(format!(
+                "Chat {} appears twice",
+                chat.id
+            )));
+        }
+    }
+    for animal in &state.custom_animals {
+        if animal.id.trim().is_empty() {
+            return Err(unreadable("A custom animal has no id"));
+        }
+        animal.persona.validate().map_err(StateError::Unreadable)?;
+    }
+
+    Ok(ChatExport {
+        chats: state.chats,
+        custom_animals: state.custom_animals,
+    })
+}
+
+/// What [`merge_export`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Titles of the chats added.
+    pub added: Vec<String>,
+    /// Chats skipped because an identical copy is already here.
+    pub unchanged: usize,
+    /// Titles (or persona names) already here with different contents; the
+    /// local copy is kept.
+    pub conflicts: Vec<String>,
+}
+
+/// Adds the chats and custom animals of `export` that aren't here yet.
+///
+/// Both are matched by id. New chats go to the top of the list in file order.
+pub fn merge_export(
+    chats: &mut Vec<ChatSession>,
+    custom_animals: &mut Vec<CustomAnimal>,
+    export: ChatExport,
+) -> ImportReport {
+    let mut report = ImportReport::default();
+
+    for animal in export.custom_animals {
+        match custom_animals.iter().find(|a| a.id == animal.id) {
+            Some(local) if *local == animal => {}
+            Some(_) => report.conflicts.push(animal.persona.name),
+            None => custom_animals.push(animal),
+        }
+    }
+
+    let mut added = Vec::new();
+    for chat in export.chats {
+        match chats.iter().find(|c| c.id == chat.id) {
+            Some(local) if *local == chat => report.unchanged += 1,
+            Some(_) => report.conflicts.push(chat.title),
+            None => {
+                report.added.push(chat.title.clone());
+                added.push(chat);
+            }
+        }
+    }
+    chats.splice(0..0, added);
+
+    report
+}
+
+// ─── Markdown ───
+
+/// A readable transcript of the active branch of `chat`.
+///
+/// `custom_animals` names the custom animals that answered, when they still exist.
+pub fn to_markdown(chat: &ChatSession, custom_animals: &[CustomAnimal]) -> String {
+    let lang = chat.language;
+    let (animal_label, intelligence_label) = match lang {
+        Language::Es => ("Animal", "Inteligencia"),
+        Language::En => ("Animal", "Intelligence"),
+    };
+    let animal = match chat
+        .custom_animal
+        .as_deref()
+        .and_then(|id| custom_animals.iter().find(|a| a.id == id))
+    {
+        Some(custom) => custom.persona.name.clone(),
+        None => chat.animal.label(lang).to_string(),
+    };
+
+    let mut out = format!(
+        "# {}\n\n**{animal_label}:** {animal} · **{intelligence_label}:** {} · {}\n",
+        chat.title,
+        chat.intelligence.label(lang),
+        chat.created_at.format("%Y-%m-%d"),
+    );
+    for message in chat.messages.messages() {
+        out.push_str(&format!(
+            "\n---\n\n### {}\n\n",
+            speaker(chat, &message, custom_animals)
+        ));
+        out.push_str(message.content.trim());
+        out.push('\n');
+        if message.cancelled {
+            out.push_str(match lang {
+                Language::Es => "\n_(Detenido)_\n",
+                Language::En => "\n_(Stopped)_\n",
+            });
+        }
+    }
+    out
+}
+
+/// Who wrote `message`: "Tú"/"You", or the animal that answered it.
+pub fn speaker(
+    chat: &ChatSession,
+    message: &ChatMessage,
+    custom_animals: &[CustomAnimal],
+) -> String {
+    let lang = chat.language;
+    if message.role == Role::User {
+        return match lang {
+            Language::Es => "Tú",
+            Language::En => "You",
+        }
+        .to_string();
+    }
+    let meta = &message.meta;
+    // Older messages carry no author: the chat's current animal wrote them.
+    let custom_id = if meta.animal.is_some() {
+        meta.custom_animal.as_deref()
+    } else {
+        chat.custom_animal.as_deref()
+    };
+    if let Some(id) = custom_id {
+        if let Some(custom) = custom_animals.iter().find(|a| a.id == id) {
+            return custom.persona.name.clone();
+        }
+        if let Some(name) = &meta.persona_name {
+            return name.clone();
+        }
+    }
+    meta.animal.unwrap_or(chat.animal).label(lang).to_string()
+}
+
+/// A file name for `chat` with extension `ext`, from its title.
+pub fn export_file_name(chat: &ChatSession, ext: &str) -> String {
+    let slug = chat
+        .title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = if slug.is_empty() {
+        "chat".to_string()
+    } else {
+        slug.chars().take(60).collect()
+    };
+    format!("{slug}.{ext}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalType, Avatar, CustomPersona, IntelligenceLevel, MessageMeta};
+
+    fn cat_chat() -> ChatSession {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::High, Language::Es);
+        chat.title = "¿Por qué tiras cosas?".to_string();
+        chat.messages
+            .push(ChatMessage::new(Role::User, "¿Por qué tiras mi vaso?"));
+        chat.messages
+            .push(ChatMessage::new(Role::Assistant, "Porque estaba ahí."));
+        chat
+    }
+
+    fn custom_animal() -> CustomAnimal {
+        let persona = CustomPersona {
+            name: "Pingu".to_string(),
+            personality: "Un pingüino elegante".to_string(),
+            catchphrases: vec![],
+        };
+        CustomAnimal::new(persona, Avatar::default(), "#336699".to_string())
+    }
+
+    #[test]
+    fn exports_round_trip() {
+        let mut custom_chat = cat_chat();
+        let pingu = custom_animal();
+        custom_chat.custom_animal = Some(pingu.id.clone());
+        let unused = custom_animal();
+        let chats = vec![cat_chat(), custom_chat];
+
+        let raw = export_json(&chats, &[pingu.clone(), unused]).unwrap();
+        let export = parse_export(&raw).unwrap();
+
+        assert_eq!(export.chats, chats);
+        assert_eq!(export.custom_animals, vec![pingu]);
+    }
+
+    #[test]
+    fn other_json_is_refused() {
+        let duplicated = cat_chat();
+        let twice = export_json(&[duplicated.clone(), duplicated], &[]).unwrap();
+        let newer = format!(
+            r#"{{"kind":"{EXPORT_KIND}","version":{},"chats":[]}}"#,
+            STATE_VERSION + 1
+        );
+
+        for raw in ["[]", r#"{"chats":[]}"#, twice.as_str(), "{oops"] {
+            assert!(
+                matches!(parse_export(raw), Err(StateError::Unreadable(_))),
+                "{raw}"
+            );
+        }
+        assert_eq!(
+            parse_export(&newer),
+            Err(StateError::TooNew {
+                version: STATE_VERSION + 1
+            })
+        );
+    }
+
+    #[test]
+    fn version_zero_exports_are_migrated() {
+        let raw = format!(
+            r#"{{"kind":"{EXPORT_KIND}","version":0,"chats":[{{
+                "id":"c1","title":"Viejo","animal":"dog","intelligence":"low",
+                "messages":[{{"role":"user","content":"Guau"}}],
+                "created_at":"2025-01-01T00:00:00Z"
+            }}]}}"#
+        );
+        let export = parse_export(&raw).unwrap();
+        assert_eq!(export.chats[0].messages.len(), 1);
+        assert_eq!(export.chats[0].language, Language::Es);
+    }
+
+    #[test]
+    fn merging_dedupes_by_id_and_reports_conflicts() {
+        let same = cat_chat();
+        let mut edited = cat_chat();
+        let new = cat_chat();
+        let mut chats = vec![same.clone(), edited.clone()];
+        edited.title = "Otro título".to_string();
+
+        let export = ChatExport {
+            chats: vec![same, edited, new.clone()],
+            custom_animals: vec![custom_animal()],
+        };
+        let mut custom_animals = vec![];
+        let report = merge_export(&mut chats, &mut custom_animals, export);
+
+        assert_eq!(report.added, vec![new.title.clone()]);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.conflicts, vec!["Otro título".to_string()]);
+        assert_eq!(chats.len(), 3);
+        assert_eq!(chats[0].id, new.id);
+        assert_eq!(custom_animals.len(), 1);
+    }
+
+    #[test]
+    fn markdown_names_every_speaker() {
+        let mut chat = cat_chat();
+        let pingu = custom_animal();
+        let meta = MessageMeta {
+            animal: Some(AnimalType::CAT),
+            custom_animal: Some(pingu.id.clone()),
+            persona_name: Some("Pingu".to_string()),
+            ..MessageMeta::default()
+        };
+        chat.messages
+            .push(ChatMessage::new(Role::Assistant, "Cuac").with_meta(meta));
+
+        let md = to_markdown(&chat, &[]);
+        assert!(
+            md.starts_with("# ¿Por qué tiras cosas?\n\n**Animal:** Gato · **Inteligencia:** Alta")
+        );
+        assert!(md.contains("### Tú\n\n¿Por qué tiras mi vaso?"));
+        assert!(md.contains("### Gato\n\nPorque estaba ahí."));
+        // Deleted custom animals keep the name they had.
+        assert!(md.contains("### Pingu\n\nCuac"));
+    }
+
+    #[test]
+    fn file_names_come_from_titles() {
+        let chat = cat_chat();
+        assert_eq!(export_file_name(&chat, "md"), "por-qué-tiras-cosas.md");
+
+        let mut untitled = chat;
+        untitled.title = "¿?".to_string();
+        assert_eq!(export_file_name(&untitled, "json"), "chat.json");
+    }
+}
@@ -15,6 +15,18 @@ impl Language {
         &[Language::Es, Language::En]
     }
 
+    /// The code it is serialized as, e.g. `"es"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Es => "es",
+            Language::En => "en",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Language> {
+        Self::all().iter().copied().find(|l| l.code() == code)
+    }
+
     pub fn label(&self) -> &'static str {
         match self {
             Language::Es => "Español",
@@ -26,51 +38,251 @@ impl Language {
 
 // ─── Animal Types ───
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum AnimalType {
-    Cat,
-    Octopus,
-    Elephant,
-    Chicken,
-}
+mod animals;
+
+pub use animals::{ANIMALS, AnimalSpec, Localized, OfflineSpec, Reaction, ThemeColors};
+
+/// Handle to an entry of the animal registry (`animals.toml`).
+///
+/// Serialized as the entry's `id`, so stored chats and API payloads keep the
+/// same `"cat"` / `"octopus"` strings as before.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnimalType(u8);
 
 impl AnimalType {
     pub fn all() -> &'static [AnimalType] {
-        &[
-            AnimalType::Cat,
-            AnimalType::Octopus,
-            AnimalType::Elephant,
-            AnimalType::Chicken,
-        ]
+        &animals::ALL
+    }
+
+    pub fn from_id(id: &str) -> Option<AnimalType> {
+        Self::all().iter().copied().find(|a| a.id() == id)
+    }
+
+    pub fn spec(&self) -> &'static AnimalSpec {
+        &ANIMALS[self.0 as usize]
+    }
+
+    pub fn id(&self) -> &'static str {
+        self.spec().id
     }
 
     pub fn label(&self, lang: Language) -> &'static str {
-        match (self, lang) {
-            (AnimalType::Cat, Language::Es) => "Gato",
-            (AnimalType::Cat, Language::En) => "Cat",
-            (AnimalType::Octopus, Language::Es) => "Pulpo",
-            (AnimalType::Octopus, Language::En) => "Octopus",
-            (AnimalType::Elephant, Language::Es) => "Elefante",
-            (AnimalType::Elephant, Language::En) => "Elephant",
-            (AnimalType::Chicken, Language::Es) => "Gallina",
-            (AnimalType::Chicken, Language::En) => "Chicken",
-        }
+        self.spec().label.get(lang)
+    }
+
+    pub fn sound(&self, lang: Language) -> &'static str {
+        self.spec().sound.get(lang)
+    }
+
+    pub fn personality(&self, lang: Language) -> &'static str {
+        self.spec().personality.get(lang)
+    }
+
+    pub fn catchphrases(&self, lang: Language) -> &'static [&'static str] {
+        self.spec().catchphrases.get(lang)
+    }
+
+    pub fn svg(&self) -> &'static str {
+        self.spec().svg
+    }
+
+    pub fn memory_tokens(&self) -> u32 {
+        self.spec().memory_tokens
+    }
+
+    pub fn theme(&self) -> &'static ThemeColors {
+        &self.spec().theme
+    }
+
+    pub fn offline(&self) -> &'static OfflineSpec {
+        &self.spec().offline
     }
 }
 
 impl Default for AnimalType {
     fn default() -> Self {
-        AnimalType::Cat
+        AnimalType::CAT
+    }
+}
+
+impl std::fmt::Debug for AnimalType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnimalType").field(&self.id()).finish()
     }
 }
 
+impl Serialize for AnimalType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for AnimalType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        AnimalType::from_id(&id).ok_or_else(|| {
+            let known: Vec<&str> = ANIMALS.iter().map(|a| a.id).collect();
+            serde::de::Error::custom(format!(
+                "unknown animal `{id}`, expected one of {}",
+                known.join(", ")
+            ))
+        })
+    }
+}
+
+// ─── Custom Animals ───
+
+pub const MAX_PERSONA_NAME_LENGTH: usize = 40;
+pub const MAX_PERSONA_PERSONALITY_LENGTH: usize = 1_500;
+pub const MAX_PERSONA_CATCHPHRASES: usize = 8;
+pub const MAX_PERSONA_CATCHPHRASE_LENGTH: usize = 120;
+
+/// A user-written persona, sent with a chat request instead of a registry personality.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomPersona {
+    pub name: String,
+    pub personality: String,
+    #[serde(default)]
+    pub catchphrases: Vec<String>,
+}
+
+impl CustomPersona {
+    /// Checks the limits shared by the persona editor and the worker.
+    /// Lengths are counted in characters, not bytes.
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Persona name cannot be empty".to_string());
+        }
+        if name.chars().count() > MAX_PERSONA_NAME_LENGTH {
+            return Err(format!(
+                "Persona name exceeds maximum length of {MAX_PERSONA_NAME_LENGTH} characters"
+            ));
+        }
+        if name.chars().any(char::is_control) {
+            return Err("Persona name cannot contain control characters".to_string());
+        }
+
+        let personality = self.personality.trim();
+        if personality.is_empty() {
+            return Err("Persona personality cannot be empty".to_string());
+        }
+        if personality.chars().count() > MAX_PERSONA_PERSONALITY_LENGTH {
+            return Err(format!(
+                "Persona personality exceeds maximum length of {MAX_PERSONA_PERSONALITY_LENGTH} characters"
+            ));
+        }
+
+        if self.catchphrases.len() > MAX_PERSONA_CATCHPHRASES {
+            return Err(format!(
+                "Persona exceeds maximum of {MAX_PERSONA_CATCHPHRASES} catchphrases"
+            ));
+        }
+        for phrase in &self.catchphrases {
+            let phrase = phrase.trim();
+            if phrase.is_empty() || phrase.chars().count() > MAX_PERSONA_CATCHPHRASE_LENGTH {
+                return Err(format!(
+                    "Persona catchphrases must be 1 to {MAX_PERSONA_CATCHPHRASE_LENGTH} characters"
+                ));
+            }
+            if phrase.chars().any(char::is_control) {
+                return Err("Persona catchphrases cannot contain control characters".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// How a custom animal is drawn.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum Avatar {
+    Emoji(String),
+    /// Uploaded SVG markup, only ever rendered through an `<img>` data URL.
+    Svg(String),
+}
+
+impl Default for Avatar {
+    fn default() -> Self {
+        Avatar::Emoji("🐾".to_string())
+    }
+}
+
+/// A user-created animal, stored in the browser next to the chats.
+///
+/// It is drawn with the default animal's palette, with `accent` (`#rrggbb`)
+/// replacing the primary colour.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomAnimal {
+    pub id: String,
+    pub persona: CustomPersona,
+    #[serde(default)]
+    pub avatar: Avatar,
+    pub accent: String,
+}
+
+impl CustomAnimal {
+    pub fn new(persona: CustomPersona, avatar: Avatar, accent: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            persona,
+            avatar,
+            accent,
+        }
+    }
+
+    /// `(custom property, value)` pairs for this animal's theme.
+    pub fn css_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars: Vec<(&'static str, String)> = AnimalType::default()
+            .theme()
+            .css_vars()
+            .iter()
+            .map(|&(name, value)| (name, value.to_string()))
+            .collect();
+
+        let Some(luminance) = hex_luminance(&self.accent) else {
+            return vars;
+        };
+        let accent = &self.accent;
+        let on_accent = if luminance > 0.5 { "#2D2D2D" } else { "#FFFFFF" };
+        let overrides = [
+            ("--clr-primary", accent.clone()),
+            ("--clr-primary-dark", format!("color-mix(in srgb, {accent} 80%, black)")),
+            ("--clr-primary-light", format!("color-mix(in srgb, {accent} 60%, white)")),
+            ("--clr-text-brand", format!("color-mix(in srgb, {accent} 50%, black)")),
+            ("--clr-on-primary", on_accent.to_string()),
+            ("--clr-bubble-user", accent.clone()),
+        ];
+        for (name, value) in overrides {
+            if let Some(var) = vars.iter_mut().find(|(n, _)| *n == name) {
+                var.1 = value;
+            }
+        }
+        vars
+    }
+}
+
+/// Relative luminance (0.0–1.0) of a `#rrggbb` colour.
+fn hex_luminance(hex: &str) -> Option<f32> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| -> Option<f32> {
+        let c = u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()? as f32 / 255.0;
+        Some(if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) })
+    };
+    Some(0.2126 * channel(0)? + 0.7152 * channel(2)? + 0.0722 * channel(4)?)
+}
+
 // ─── Intelligence Levels ───
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum IntelligenceLevel {
     High,
+    #[default]
     Medium,
     Low,
 }
@@ -84,6 +296,19 @@ impl IntelligenceLevel {
         ]
     }
 
+    /// The id it is serialized as, e.g. `"high"`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            IntelligenceLevel::High => "high",
+            IntelligenceLevel::Medium => "medium",
+            IntelligenceLevel::Low => "low",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<IntelligenceLevel> {
+        Self::all().iter().copied().find(|l| l.id() == id)
+    }
+
     pub fn label(&self, lang: Language) -> &'static str {
         match (self, lang) {
             (IntelligenceLevel::High, Language::Es) => "Alta",
@@ -96,12 +321,6 @@ impl IntelligenceLevel {
     }
 }
 
-impl Default for IntelligenceLevel {
-    fn default() -> Self {
-        IntelligenceLevel::Medium
-    }
-}
-
 // ─── Chat Messages ───
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -114,8 +333,158 @@ pub enum Role {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ChatMessage {
+    /// Stable id, assigned when the message joins a [`MessageTree`]; empty on the wire.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub role: Role,
+    /// The version currently shown and sent as history.
     pub content: String,
+    /// The other versions of this answer, kept when it is regenerated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+    /// Position of `content` among all versions: `variants[..selected]`,
+    /// then `content`, then `variants[selected..]`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub selected: usize,
+    /// The user stopped this reply before it finished; `content` is what arrived.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cancelled: bool,
+    /// Written offline and not sent yet; see [`outbox()`].
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pending: bool,
+    #[serde(flatten)]
+    pub meta: MessageMeta,
+}
+
+/// When and by whom a stored message was written.
+///
+/// Every field is optional: messages saved before it existed have none, and
+/// the request history never carries it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MessageMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The animal that answered, even if the chat's animal changed later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animal: Option<AnimalType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intelligence: Option<IntelligenceLevel>,
+    /// Id of the [`CustomAnimal`] that answered, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_animal: Option<String>,
+    /// Its name at the time, shown if it is deleted later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona_name: Option<String>,
+    /// Upstream model, as reported by the worker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_used: Option<u32>,
+    /// Milliseconds from sending the request to the end of the reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u32>,
+    /// Written by [`offline_reply()`] instead of the model.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub offline: bool,
+}
+
+impl MessageMeta {
+    /// Metadata of a message written just now.
+    pub fn now() -> Self {
+        Self {
+            created_at: Some(chrono::Utc::now()),
+            ..Self::default()
+        }
+    }
+}
+
+impl ChatMessage {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            id: String::new(),
+            role,
+            content: content.into(),
+            variants: vec![],
+            selected: 0,
+            cancelled: false,
+            pending: false,
+            meta: MessageMeta::default(),
+        }
+    }
+
+    pub fn with_meta(mut self, meta: MessageMeta) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Total number of versions, including the one shown.
+    pub fn versions(&self) -> usize {
+        self.variants.len() + 1
+    }
+
+    /// Shows version `index` (0-based); out-of-range indices are ignored.
+    pub fn select_version(&mut self, index: usize) {
+        if index >= self.versions() || index == self.selected {
+            return;
+        }
+        let shown = std::mem::take(&mut self.content);
+        self.variants.insert(self.selected, shown);
+        self.content = self.variants.remove(index);
+        self.selected = index;
+    }
+
+    /// Adds `text` as the newest version and shows it.
+    ///
+    /// A regenerated answer is complete, so the turn is no longer marked cancelled.
+    pub fn push_version(&mut self, text: impl Into<String>) {
+        let shown = std::mem::replace(&mut self.content, text.into());
+        self.variants.insert(self.selected, shown);
+        self.selected = self.variants.len();
+        self.cancelled = false;
+    }
+
+    /// The message as sent in a request's `history`, without the other versions
+    /// or any metadata.
+    ///
+    /// A reply stopped before its first word is sent as "…" so turns still alternate.
+    pub fn for_history(&self) -> ChatMessage {
+        let content = if self.cancelled && self.content.trim().is_empty() {
+            "…".to_string()
+        } else {
+            self.content.clone()
+        };
+        ChatMessage::new(self.role.clone(), content)
+    }
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Most replies one request can ask for with [`ChatRequest::candidates`].
+pub const MAX_CANDIDATES: u8 = 4;
+
+/// Longest message a chat request may carry, in bytes.
+pub const MAX_MESSAGE_LENGTH: usize = 4_000;
+
+/// Upper bound for [`ConversationSummary::text`], in characters.
+pub const MAX_SUMMARY_LENGTH: usize = 4_000;
+
+/// Longest chat title, in characters.
+pub const MAX_TITLE_LENGTH: usize = 60;
+
+/// Rolling, model-written summary of the oldest turns of a chat.
+///
+/// `covers` counts the leading messages of the chat it replaces, so only
+/// `messages[covers..]` has to be sent along with it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConversationSummary {
+    pub text: String,
+    pub covers: usize,
 }
 
 // ─── API Contract ───
@@ -126,7 +495,18 @@ pub struct ChatRequest {
     pub animal: AnimalType,
     pub intelligence: IntelligenceLevel,
     #[serde(default)]
+    pub language: Language,
+    #[serde(default)]
     pub history: Vec<ChatMessage>,
+    /// How many alternative replies to generate (`/api/chat` only); one when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<u8>,
+    /// Summary of the turns before `history`, as returned by an earlier reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ConversationSummary>,
+    /// Replaces the registry personality of `animal` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<CustomPersona>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -134,24 +514,312 @@ pub struct ChatResponse {
     pub response: String,
     #[serde(default)]
     pub tokens_used: Option<u32>,
+    /// Extra candidates when more than one was requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<String>,
+    /// Set when older turns were folded into a new summary; cache it on the chat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ConversationSummary>,
+    /// Upstream model that wrote the reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Body of `/api/title`: a chat's first exchange, to be named in the animal's voice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TitleRequest {
+    /// The user's first message.
+    pub message: String,
+    /// The animal's answer to it.
+    pub reply: String,
+    pub animal: AnimalType,
+    pub intelligence: IntelligenceLevel,
+    #[serde(default)]
+    pub language: Language,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<CustomPersona>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TitleResponse {
+    /// At most [`MAX_TITLE_LENGTH`] characters, on one line.
+    pub title: String,
+}
+
+// ─── Errors ───
+
+/// Stable, machine-readable error codes. The wire names never change; the
+/// frontend maps each one to a localized message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorCode {
+    /// The body is not a valid `ChatRequest`.
+    InvalidRequest,
+    EmptyMessage,
+    MessageTooLong,
+    HistoryTooLarge,
+    InvalidHistory,
+    InvalidPersona,
+    ConfigError,
+    #[default]
+    UpstreamUnavailable,
+    RateLimited,
+    /// Client-side only: the request never reached the worker.
+    Offline,
+}
+
+impl ApiErrorCode {
+    pub fn status(self) -> u16 {
+        match self {
+            ApiErrorCode::InvalidRequest
+            | ApiErrorCode::EmptyMessage
+            | ApiErrorCode::MessageTooLong
+            | ApiErrorCode::HistoryTooLarge
+            | ApiErrorCode::InvalidHistory
+            | ApiErrorCode::InvalidPersona => 400,
+            ApiErrorCode::RateLimited => 429,
+            ApiErrorCode::ConfigError => 500,
+            ApiErrorCode::UpstreamUnavailable => 502,
+            ApiErrorCode::Offline => 503,
+        }
+    }
+}
+
+/// JSON body of every non-2xx API response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiError {
+    pub code: ApiErrorCode,
+    /// English, developer-facing description; the UI localizes by `code`.
+    pub message: String,
+    /// Seconds the client should wait before retrying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            retry_after: None,
+            details: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, seconds: u32) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.code.status()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+// ─── Streaming ───
+
+/// Events relayed by `/api/chat/stream`, one per Server-Sent Event `data:` line.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChatStreamEvent {
+    /// Sent before any delta when older turns were folded into a new summary.
+    Summary { summary: ConversationSummary },
+    /// Next chunk of the assistant reply.
+    Delta { text: String },
+    /// The reply is complete.
+    Done {
+        #[serde(default)]
+        tokens_used: Option<u32>,
+        /// Upstream model that wrote the reply.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    /// The upstream model failed after the stream had started.
+    Error {
+        #[serde(default)]
+        code: ApiErrorCode,
+        message: String,
+    },
+}
+
+impl ChatStreamEvent {
+    /// Encodes the event as a complete `text/event-stream` frame.
+    pub fn to_sse(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("data: {json}\n\n")
+    }
+}
+
+/// Incremental `text/event-stream` decoder.
+///
+/// Bytes can be fed in arbitrary chunks (even splitting UTF-8 sequences);
+/// every completed event yields its joined `data:` payload.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+
+        events
+    }
 }
 
 // ─── Persistence ───
 
+mod export;
+mod offline;
+mod organize;
+mod outbox;
+mod search;
+mod state;
+mod tree;
+
+pub use export::{
+    export_file_name, export_json, merge_export, parse_export, speaker, to_markdown, ChatExport, ImportReport,
+    EXPORT_KIND,
+};
+pub use offline::offline_reply;
+pub use organize::{all_tags, group_chats, ChatGroup};
+pub use outbox::{outbox, OutboxEntry};
+pub use search::{search, AnimalFilter, SearchFilters, SearchHit};
+pub use state::{
+    decode_state, encode_state, upgrade_chat, upgrade_state, AppState, StateError, STATE_VERSION,
+};
+pub use tree::{MessageNode, MessageTree};
+
+/// A conversation with one animal.
+///
+/// Deserializes through [`StoredSession`], which fills in the fields that
+/// older builds didn't write.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "StoredSession")]
 pub struct ChatSession {
     pub id: String,
     pub title: String,
     pub animal: AnimalType,
     pub intelligence: IntelligenceLevel,
-    #[serde(default)]
     pub language: Language,
-    pub messages: Vec<ChatMessage>,
+    /// Id of the [`CustomAnimal`] playing this chat; `animal` is used when unset or deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_animal: Option<String>,
+    /// Every branch of the conversation; see [`MessageTree`].
+    pub messages: MessageTree,
+    /// Cached summary of the first `covers` messages of the active branch,
+    /// so it isn't rebuilt on every request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ConversationSummary>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Last time a message was added or changed; the sidebar sorts by it.
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Listed above the other chats.
+    #[serde(skip_serializing_if = "is_false")]
+    pub pinned: bool,
+    /// Hidden from the main list, in a section of its own.
+    #[serde(skip_serializing_if = "is_false")]
+    pub archived: bool,
+    /// User labels, used as folders in the sidebar.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Set once the user names the chat; automatic titles never replace theirs.
+    #[serde(skip_serializing_if = "is_false")]
+    pub renamed: bool,
+}
+
+/// Persisted shape of [`ChatSession`], with defaults for what older builds
+/// left out.
+#[derive(Deserialize)]
+struct StoredSession {
+    id: String,
+    title: String,
+    animal: AnimalType,
+    intelligence: IntelligenceLevel,
+    #[serde(default)]
+    language: Language,
+    #[serde(default)]
+    custom_animal: Option<String>,
+    messages: MessageTree,
+    #[serde(default)]
+    summary: Option<ConversationSummary>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    /// Missing before last activity was tracked: the chat's creation.
+    #[serde(default)]
+    updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    renamed: bool,
 }
 
+impl From<StoredSession> for ChatSession {
+    fn from(stored: StoredSession) -> Self {
+        ChatSession {
+            id: stored.id,
+            title: stored.title,
+            animal: stored.animal,
+            intelligence: stored.intelligence,
+            language: stored.language,
+            custom_animal: stored.custom_animal,
+            messages: stored.messages,
+            summary: stored.summary,
+            created_at: stored.created_at,
+            updated_at: stored.updated_at.unwrap_or(stored.created_at),
+            pinned: stored.pinned,
+            archived: stored.archived,
+            tags: stored.tags,
+            renamed: stored.renamed,
+        }
+    }
+}
+
+/// Most tags one chat can have.
+pub const MAX_TAGS: usize = 8;
+/// Longest tag, in characters.
+pub const MAX_TAG_LENGTH: usize = 24;
+
 impl ChatSession {
     pub fn new(animal: AnimalType, intelligence: IntelligenceLevel, language: Language) -> Self {
+        let now = chrono::Utc::now();
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             title: match language {
@@ -161,8 +829,85 @@ impl ChatSession {
             animal,
             intelligence,
             language,
-            messages: vec![],
-            created_at: chrono::Utc::now(),
+            custom_animal: None,
+            messages: MessageTree::default(),
+            summary: None,
+            created_at: now,
+            updated_at: now,
+            pinned: false,
+            archived: false,
+            tags: vec![],
+            renamed: false,
+        }
+    }
+
+    /// Marks the chat as active just now.
+    pub fn touch(&mut self) {
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Whether to ask for an automatic title: the user hasn't named the chat
+    /// and its first exchange has just been answered.
+    pub fn wants_title(&self) -> bool {
+        let path = self.messages.messages();
+        !self.renamed
+            && path.len() == 2
+            && path[1].role == Role::Assistant
+            && !path[1].cancelled
+            && !path[1].content.trim().is_empty()
+    }
+
+    /// History and cached summary to send for a reply to message `end` of the active branch.
+    ///
+    /// Only the active branch is sent. Turns already folded into the summary are
+    /// not sent again, nor are the other versions of each answer.
+    pub fn request_history(&self, end: usize) -> (Vec<ChatMessage>, Option<ConversationSummary>) {
+        let path = self.messages.path();
+        let covered = self.summary.as_ref().map_or(0, |s| s.covers).min(end);
+        let history = path[covered..end].iter().map(|n| n.message.for_history()).collect();
+        (history, self.summary.clone())
+    }
+
+    /// Replaces the tags with the comma-separated `input`.
+    ///
+    /// Tags are trimmed, deduplicated ignoring case, and cut to
+    /// [`MAX_TAG_LENGTH`] characters and [`MAX_TAGS`] entries.
+    pub fn set_tags(&mut self, input: &str) {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in input.split(',') {
+            let tag: String = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+            let tag: String = tag.chars().filter(|c| !c.is_control()).take(MAX_TAG_LENGTH).collect();
+            if !tag.is_empty() && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+                tags.push(tag);
+            }
+        }
+        tags.truncate(MAX_TAGS);
+        self.tags = tags;
+    }
+
+    /// Replaces user message `id` with `content` on a new branch; the original is kept.
+    pub fn edit_message(&mut self, id: &str, content: impl Into<String>) -> Option<String> {
+        let depth = self.messages.depth(id)?;
+        let role = self.messages.get(id)?.message.role.clone();
+        let edited = ChatMessage::new(role, content).with_meta(MessageMeta::now());
+        let fork = self.messages.fork(id, edited)?;
+        self.forget_summary_from(depth);
+        self.touch();
+        Some(fork)
+    }
+
+    /// Shows the branch through message `id`.
+    pub fn switch_branch(&mut self, id: &str) {
+        self.messages.switch_to(id);
+        if let Some(depth) = self.messages.depth(id) {
+            self.forget_summary_from(depth);
+        }
+    }
+
+    /// Drops the cached summary if it covers messages from `depth` on, which just changed.
+    fn forget_summary_from(&mut self, depth: usize) {
+        if self.summary.as_ref().is_some_and(|s| s.covers > depth) {
+            self.summary = None;
         }
     }
 }
@@ -177,13 +922,27 @@ mod tests {
     fn serialize_chat_request() {
         let req = ChatRequest {
             message: "Hello".to_string(),
-            animal: AnimalType::Cat,
+            animal: AnimalType::CAT,
             intelligence: IntelligenceLevel::Medium,
+            language: Language::En,
             history: vec![],
+            candidates: None,
+            summary: None,
+            persona: None,
         };
         let json = serde_json::to_string(&req).unwrap();
         assert!(json.contains("\"animal\":\"cat\""));
         assert!(json.contains("\"intelligence\":\"medium\""));
+        assert!(json.contains("\"language\":\"en\""));
+        assert!(!json.contains("persona"));
+        assert!(!json.contains("summary"));
+    }
+
+    #[test]
+    fn chat_request_language_defaults_to_spanish() {
+        let json = r#"{"message":"Hola","animal":"cat","intelligence":"low"}"#;
+        let req: ChatRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.language, Language::Es);
     }
 
     #[test]
@@ -194,6 +953,313 @@ mod tests {
         assert_eq!(res.tokens_used, Some(42));
     }
 
+    #[test]
+    fn stream_event_sse_frame() {
+        let frame = ChatStreamEvent::Done { tokens_used: Some(7), model: None }.to_sse();
+        assert_eq!(frame, "data: {\"type\":\"done\",\"tokens_used\":7}\n\n");
+    }
+
+    #[test]
+    fn api_error_codes_are_stable_snake_case() {
+        let codes = [
+            (ApiErrorCode::InvalidRequest, "invalid_request"),
+            (ApiErrorCode::EmptyMessage, "empty_message"),
+            (ApiErrorCode::MessageTooLong, "message_too_long"),
+            (ApiErrorCode::HistoryTooLarge, "history_too_large"),
+            (ApiErrorCode::InvalidHistory, "invalid_history"),
+            (ApiErrorCode::InvalidPersona, "invalid_persona"),
+            (ApiErrorCode::ConfigError, "config_error"),
+            (ApiErrorCode::UpstreamUnavailable, "upstream_unavailable"),
+            (ApiErrorCode::RateLimited, "rate_limited"),
+            (ApiErrorCode::Offline, "offline"),
+        ];
+        for (code, wire) in codes {
+            assert_eq!(serde_json::to_string(&code).unwrap(), format!("\"{wire}\""));
+        }
+    }
+
+    #[test]
+    fn api_error_round_trip_omits_empty_fields() {
+        let error = ApiError::new(ApiErrorCode::RateLimited, "Too many requests").with_retry_after(30);
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(json, r#"{"code":"rate_limited","message":"Too many requests","retry_after":30}"#);
+        assert_eq!(serde_json::from_str::<ApiError>(&json).unwrap(), error);
+        assert_eq!(error.status(), 429);
+    }
+
+    #[test]
+    fn stream_error_without_code_is_upstream_unavailable() {
+        let event: ChatStreamEvent =
+            serde_json::from_str(r#"{"type":"error","message":"boom"}"#).unwrap();
+        assert_eq!(
+            event,
+            ChatStreamEvent::Error {
+                code: ApiErrorCode::UpstreamUnavailable,
+                message: "boom".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn sse_decoder_handles_split_chunks() {
+        let frame = ChatStreamEvent::Delta { text: "¡Pío!".to_string() }.to_sse();
+        let bytes = frame.as_bytes();
+        let mut decoder = SseDecoder::new();
+
+        // Split inside the multi-byte "¡" and before the terminating blank line.
+        let mut events = decoder.push(&bytes[..31]);
+        assert!(events.is_empty());
+        events.extend(decoder.push(&bytes[31..bytes.len() - 1]));
+        assert!(events.is_empty());
+        events.extend(decoder.push(&bytes[bytes.len() - 1..]));
+
+        assert_eq!(events.len(), 1);
+        let event: ChatStreamEvent = serde_json::from_str(&events[0]).unwrap();
+        assert_eq!(event, ChatStreamEvent::Delta { text: "¡Pío!".to_string() });
+    }
+
+    #[test]
+    fn sse_decoder_accepts_crlf_and_ignores_other_fields() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"event: message\r\ndata: {\"a\":1}\r\n\r\n: comment\n\ndata:x\n\n");
+        assert_eq!(events, vec!["{\"a\":1}".to_string(), "x".to_string()]);
+    }
+
+    #[test]
+    fn registry_keeps_original_ids_and_adds_new_animals() {
+        let ids: Vec<&str> = AnimalType::all().iter().map(|a| a.id()).collect();
+        for id in ["cat", "octopus", "elephant", "chicken", "dog", "owl", "dolphin"] {
+            assert!(ids.contains(&id), "missing {id}");
+        }
+        assert_eq!(AnimalType::default(), AnimalType::CAT);
+        assert_eq!(AnimalType::from_id("owl"), Some(AnimalType::OWL));
+        assert_eq!(AnimalType::from_id("capybara"), None);
+    }
+
+    #[test]
+    fn registry_entries_are_complete() {
+        for animal in AnimalType::all() {
+            for &lang in Language::all() {
+                assert!(!animal.label(lang).is_empty(), "{animal:?}");
+                assert!(!animal.sound(lang).is_empty(), "{animal:?}");
+                assert!(!animal.personality(lang).is_empty(), "{animal:?}");
+                assert!(!animal.catchphrases(lang).is_empty(), "{animal:?}");
+            }
+            assert!(animal.svg().starts_with("<svg"), "{animal:?}");
+            assert!(animal.theme().css_vars().iter().all(|(_, v)| !v.is_empty()));
+        }
+    }
+
+    #[test]
+    fn level_and_language_ids_match_their_serialized_form() {
+        for &level in IntelligenceLevel::all() {
+            assert_eq!(serde_json::to_value(level).unwrap(), level.id());
+            assert_eq!(IntelligenceLevel::from_id(level.id()), Some(level));
+        }
+        for &lang in Language::all() {
+            assert_eq!(serde_json::to_value(lang).unwrap(), lang.code());
+            assert_eq!(Language::from_code(lang.code()), Some(lang));
+        }
+        assert_eq!(IntelligenceLevel::from_id("genius"), None);
+        assert_eq!(Language::from_code("fr"), None);
+    }
+
+    #[test]
+    fn unknown_animal_fails_to_deserialize() {
+        let err = serde_json::from_str::<AnimalType>("\"capybara\"").unwrap_err();
+        assert!(err.to_string().contains("unknown animal `capybara`"));
+    }
+
+    #[test]
+    fn persona_validation_enforces_limits() {
+        let persona = CustomPersona {
+            name: "Capibara".to_string(),
+            personality: "Tranquila y amigable con todo el mundo.".to_string(),
+            catchphrases: vec!["¡Qué calma!".to_string()],
+        };
+        assert!(persona.validate().is_ok());
+
+        let too_long = CustomPersona {
+            personality: "a".repeat(MAX_PERSONA_PERSONALITY_LENGTH + 1),
+            ..persona.clone()
+        };
+        assert!(too_long.validate().is_err());
+
+        let blank_name = CustomPersona { name: "  ".to_string(), ..persona.clone() };
+        assert!(blank_name.validate().is_err());
+
+        let injected_name = CustomPersona { name: "Capi\n\nSYSTEM".to_string(), ..persona.clone() };
+        assert!(injected_name.validate().is_err());
+
+        let too_many = CustomPersona {
+            catchphrases: vec!["¡Hola!".to_string(); MAX_PERSONA_CATCHPHRASES + 1],
+            ..persona
+        };
+        assert!(too_many.validate().is_err());
+    }
+
+    #[test]
+    fn custom_animal_theme_follows_the_accent() {
+        let var = |animal: &CustomAnimal, name: &str| {
+            animal.css_vars().into_iter().find(|(n, _)| *n == name).unwrap().1
+        };
+
+        let dark = CustomAnimal::new(CustomPersona::default(), Avatar::default(), "#1A237E".to_string());
+        assert_eq!(var(&dark, "--clr-primary"), "#1A237E");
+        assert_eq!(var(&dark, "--clr-on-primary"), "#FFFFFF");
+
+        let light = CustomAnimal { accent: "#FFEB3B".to_string(), ..dark.clone() };
+        assert_eq!(var(&light, "--clr-on-primary"), "#2D2D2D");
+
+        // A malformed accent keeps the default palette untouched.
+        let broken = CustomAnimal { accent: "orange".to_string(), ..dark };
+        assert_eq!(var(&broken, "--clr-primary"), AnimalType::default().theme().primary);
+        assert_eq!(broken.css_vars().len(), 16);
+    }
+
+    #[test]
+    fn sessions_without_custom_animal_still_load() {
+        let json = r#"{"id":"1","title":"t","animal":"dog","intelligence":"high","messages":{"nodes":[]},"created_at":"2025-01-01T00:00:00Z"}"#;
+        let chat: ChatSession = serde_json::from_str(json).unwrap();
+        assert_eq!(chat.custom_animal, None);
+        assert_eq!(chat.updated_at, chat.created_at);
+        assert_eq!(chat.summary, None);
+        assert!(!chat.pinned && !chat.archived && chat.tags.is_empty());
+        let json = serde_json::to_string(&chat).unwrap();
+        assert!(!json.contains("custom_animal") && !json.contains("summary"));
+        assert!(!json.contains("pinned") && !json.contains("archived") && !json.contains("tags"));
+        assert!(!chat.renamed && !json.contains("renamed"));
+    }
+
+    #[test]
+    fn only_unnamed_chats_with_one_answered_turn_want_a_title() {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es);
+        chat.messages.push(ChatMessage::new(Role::User, "Hola"));
+        assert!(!chat.wants_title());
+        chat.messages.push(ChatMessage::new(Role::Assistant, "Miau."));
+        assert!(chat.wants_title());
+
+        let mut renamed = chat.clone();
+        renamed.renamed = true;
+        assert!(!renamed.wants_title());
+
+        let mut stopped = chat.clone();
+        stopped.messages.last_mut().unwrap().cancelled = true;
+        assert!(!stopped.wants_title());
+
+        chat.messages.push(ChatMessage::new(Role::User, "¿Y?"));
+        chat.messages.push(ChatMessage::new(Role::Assistant, "Miau."));
+        assert!(!chat.wants_title());
+    }
+
+    #[test]
+    fn message_versions_keep_every_answer() {
+        let mut msg = ChatMessage::new(Role::Assistant, "Cocoroco.");
+        msg.push_version("¡Pío!");
+        msg.push_version("Cloc.");
+        assert_eq!((msg.versions(), msg.selected, msg.content.as_str()), (3, 2, "Cloc."));
+
+        msg.select_version(0);
+        assert_eq!(msg.content, "Cocoroco.");
+        msg.select_version(1);
+        assert_eq!(msg.content, "¡Pío!");
+        assert_eq!(msg.variants, ["Cocoroco.", "Cloc."]);
+
+        msg.push_version("Kikirikí.");
+        msg.select_version(9);
+        assert_eq!((msg.versions(), msg.selected, msg.content.as_str()), (4, 3, "Kikirikí."));
+        assert_eq!(msg.for_history(), ChatMessage::new(Role::Assistant, "Kikirikí."));
+    }
+
+    #[test]
+    fn cancelled_replies_still_fill_their_turn() {
+        let mut msg = ChatMessage::new(Role::Assistant, "");
+        msg.cancelled = true;
+        assert!(serde_json::to_string(&msg).unwrap().contains("\"cancelled\":true"));
+        assert_eq!(msg.for_history().content, "…");
+
+        msg.push_version("Miau.");
+        assert!(!msg.cancelled);
+        assert_eq!(msg.for_history().content, "Miau.");
+    }
+
+    #[test]
+    fn plain_messages_serialize_without_versions() {
+        let msg = ChatMessage::new(Role::User, "Hola");
+        assert_eq!(serde_json::to_string(&msg).unwrap(), r#"{"role":"user","content":"Hola"}"#);
+        let back: ChatMessage = serde_json::from_str(r#"{"role":"user","content":"Hola"}"#).unwrap();
+        assert_eq!(back, msg);
+    }
+
+    #[test]
+    fn message_metadata_is_stored_but_not_sent() {
+        let meta = MessageMeta {
+            created_at: Some("2025-01-01T10:00:00Z".parse().unwrap()),
+            animal: Some(AnimalType::CAT),
+            intelligence: Some(IntelligenceLevel::Low),
+            model: Some("gemini-2.5-flash".to_string()),
+            tokens_used: Some(42),
+            latency_ms: Some(850),
+            ..MessageMeta::default()
+        };
+        let mut tree = MessageTree::default();
+        let id = tree.push(ChatMessage::new(Role::Assistant, "Miau").with_meta(meta.clone()));
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(json.contains(r#""animal":"cat""#) && json.contains(r#""latency_ms":850"#));
+        let back: MessageTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.get(&id).unwrap().message.meta, meta);
+
+        let sent = serde_json::to_string(&back.last().unwrap().for_history()).unwrap();
+        assert_eq!(sent, r#"{"role":"assistant","content":"Miau"}"#);
+    }
+
+    #[test]
+    fn messages_saved_before_metadata_still_load() {
+        let json = r#"{"nodes":[{"id":"a","role":"user","content":"Hola"}],"active":"a"}"#;
+        let tree: MessageTree = serde_json::from_str(json).unwrap();
+        let node = tree.get("a").unwrap();
+        assert_eq!(node.id(), "a");
+        assert_eq!(node.message.meta, MessageMeta::default());
+    }
+
+    #[test]
+    fn editing_inside_the_summary_drops_it() {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es);
+        for (i, text) in ["Hola", "Miau", "¿Pescado?", "Prrr"].into_iter().enumerate() {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            chat.messages.push(ChatMessage::new(role, text));
+        }
+        let ids: Vec<String> = chat.messages.path().iter().map(|n| n.id().to_string()).collect();
+        let summary = ConversationSummary { text: "Saludos.".to_string(), covers: 2 };
+
+        chat.summary = Some(summary.clone());
+        chat.edit_message(&ids[2], "¿Leche?").unwrap();
+        assert_eq!(chat.summary, Some(summary));
+
+        chat.edit_message(&ids[0], "Buenas").unwrap();
+        assert_eq!(chat.summary, None);
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.messages.siblings(&ids[0]).len(), 2);
+    }
+
+    #[test]
+    fn elephant_remembers_more_than_the_default() {
+        assert_eq!(AnimalType::CAT.memory_tokens(), 4_000);
+        assert!(AnimalType::ELEPHANT.memory_tokens() > AnimalType::CAT.memory_tokens());
+    }
+
+    #[test]
+    fn summary_stream_event_round_trip() {
+        let event = ChatStreamEvent::Summary {
+            summary: ConversationSummary { text: "Hablamos de ríos.".to_string(), covers: 4 },
+        };
+        let frame = event.to_sse();
+        assert!(frame.starts_with("data: {\"type\":\"summary\""));
+        let data = frame.trim_start_matches("data: ").trim_end();
+        assert_eq!(serde_json::from_str::<ChatStreamEvent>(data).unwrap(), event);
+    }
+
     #[test]
     fn round_trip_animal_type() {
         for animal in AnimalType::all() {
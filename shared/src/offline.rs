@@ -0,0 +1,263 @@
+//! Rule-based replies for when the model can't be reached.
+//!
+//! Built from the same registry material as the system prompt: an opener
+//! (a gesture or sound), a line (a keyword reaction, or a catchphrase) and,
+//! depending on the intelligence level, a confused or a reflective ending.
+//! Picks are seeded by the message and the turn, so a reply doesn't change
+//! when the chat is reloaded.
+
+use crate::search::fold;
+use crate::{ChatSession, CustomPersona, IntelligenceLevel, Language};
+
+/// Answers `message` in the chat's voice without a model.
+///
+/// `persona` is the custom animal playing the chat, if any; it has no
+/// registry material, so its catchphrases carry the reply.
+pub fn offline_reply(chat: &ChatSession, persona: Option<&CustomPersona>, message: &str) -> String {
+    let lang = chat.language;
+    let seed = fnv1a(message) ^ (chat.messages.len() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let words = words(message);
+
+    let (opener, line, topic) = match persona {
+        Some(persona) => persona_voice(persona, lang, seed),
+        None => {
+            let animal = chat.animal;
+            let offline = animal.offline();
+            let line = if words
+                .first()
+                .is_some_and(|w| greetings(lang).contains(&w.as_str()))
+            {
+                animal.sound(lang)
+            } else {
+                offline
+                    .reactions
+                    .iter()
+                    .find(|r| {
+                        r.keywords
+                            .get(lang)
+                            .iter()
+                            .any(|k| words.iter().any(|w| w.starts_with(k)))
+                    })
+                    .map(|r| r.replies.get(lang))
+                    .map_or_else(
+                        || pick(animal.catchphrases(lang), seed, 1),
+                        |replies| pick(replies, seed, 1),
+                    )
+            };
+            (
+                pick(offline.openers.get(lang), seed, 0).to_string(),
+                line.to_string(),
+                Some(pick(offline.topics.get(lang), seed, 2)),
+            )
+        }
+    };
+
+    match chat.intelligence {
+        IntelligenceLevel::Low => {
+            format!(
+                "{opener} {} {}",
+                first_sentence(&line),
+                pick(confused(lang), seed, 3)
+            )
+        }
+        IntelligenceLevel::Medium => format!("{opener} {line}"),
+        IntelligenceLevel::High => {
+            let reflection = match topic {
+                Some(topic) => pick(reflections(lang), seed, 3).replace("{topic}", topic),
+                None => pick(musings(lang), seed, 3).to_string(),
+            };
+            format!("{opener} {line}\n\n{reflection}")
+        }
+    }
+}
+
+fn persona_voice(
+    persona: &CustomPersona,
+    lang: Language,
+    seed: u64,
+) -> (String, String, Option<&'static str>) {
+    let name = persona.name.trim();
+    let opener = match lang {
+        Language::Es => format!("*{name} se queda pensando*"),
+        Language::En => format!("*{name} thinks it over*"),
+    };
+    let catchphrases: Vec<&str> = persona
+        .catchphrases
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect();
+    let line = if catchphrases.is_empty() {
+        pick(musings(lang), seed, 1)
+    } else {
+        pick(&catchphrases, seed, 1)
+    };
+    (opener, line.to_string(), None)
+}
+
+/// Folded words of `text`, for matching keywords by their start.
+fn words(text: &str) -> Vec<String> {
+    fold(text)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Up to the first `.`, `!`, `?` or `…`, so slow animals say less.
+fn first_sentence(line: &str) -> &str {
+    let end = line
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| matches!(c, '.' | '!' | '?' | '…'))
+        .map_or(line.len(), |(i, c)| i + c.len_utf8());
+    &line[..end]
+}
+
+fn pick<'a>(items: &[&'a str], seed: u64, salt: u32) -> &'a str {
+    items[(seed.rotate_left(salt * 16) % items.len() as u64) as usize]
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn greetings(lang: Language) -> &'static [&'static str] {
+    match lang {
+        Language::Es => &["hola", "buenas", "saludos"],
+        Language::En => &["hello", "hi", "hey", "greetings"],
+    }
+}
+
+fn confused(lang: Language) -> &'static [&'static str] {
+    match lang {
+        Language::Es => &["¿Eh?", "...¿Qué decías?", "*se distrae con algo*"],
+        Language::En => &[
+            "Huh?",
+            "...What were you saying?",
+            "*gets distracted by something*",
+        ],
+    }
+}
+
+fn reflections(lang: Language) -> &'static [&'static str] {
+    match lang {
+        Language::Es => &[
+            "Pensándolo bien, todo acaba volviendo a {topic}.",
+            "Sin conexión con el mundo, al menos me queda {topic}.",
+            "Te respondería mejor, pero ahora mismo solo puedo pensar en {topic}.",
+        ],
+        Language::En => &[
+            "Come to think of it, everything comes back to {topic}.",
+            "Cut off from the world, at least I still have {topic}.",
+            "I'd answer you better, but right now all I can think about is {topic}.",
+        ],
+    }
+}
+
+fn musings(lang: Language) -> &'static [&'static str] {
+    match lang {
+        Language::Es => &[
+            "Sin conexión me cuesta encontrar las palabras, pero sigo aquí.",
+            "Dame un momento; cuando vuelva la red te lo cuento mejor.",
+        ],
+        Language::En => &[
+            "Offline, the words don't come easily, but I'm still here.",
+            "Give me a moment; once the network is back I'll tell you properly.",
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnimalType;
+
+    fn chat(
+        animal: AnimalType,
+        intelligence: IntelligenceLevel,
+        language: Language,
+    ) -> ChatSession {
+        ChatSession::new(animal, intelligence, language)
+    }
+
+    #[test]
+    fn every_animal_stays_recognizable_at_every_level() {
+        for &animal in AnimalType::all() {
+            for &level in IntelligenceLevel::all() {
+                for language in [Language::Es, Language::En] {
+                    let reply =
+                        offline_reply(&chat(animal, level, language), None, "Cuéntame algo");
+                    let offline = animal.offline();
+                    assert!(
+                        offline
+                            .openers
+                            .get(language)
+                            .iter()
+                            .any(|o| reply.starts_with(o)),
+                        "{animal:?} {level:?}: {reply}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keywords_trigger_the_animal_reactions() {
+        let dog = chat(AnimalType::DOG, IntelligenceLevel::Medium, Language::Es);
+        let reply = offline_reply(&dog, None, "¿Vamos de PASEO al parque?");
+        let walk = &AnimalType::DOG.offline().reactions[0];
+        assert!(
+            walk.replies.es.iter().any(|r| reply.ends_with(r)),
+            "{reply}"
+        );
+
+        let cat = chat(AnimalType::CAT, IntelligenceLevel::Medium, Language::En);
+        assert!(
+            offline_reply(&cat, None, "Hello there").ends_with(AnimalType::CAT.sound(Language::En))
+        );
+    }
+
+    #[test]
+    fn replies_follow_the_chat_language_and_are_stable() {
+        let owl = chat(AnimalType::OWL, IntelligenceLevel::High, Language::En);
+        let reply = offline_reply(&owl, None, "Why is the moon round?");
+        assert_eq!(reply, offline_reply(&owl, None, "Why is the moon round?"));
+        let topics = AnimalType::OWL.offline().topics.en;
+        assert!(topics.iter().any(|t| reply.contains(t)), "{reply}");
+    }
+
+    #[test]
+    fn slower_animals_say_less() {
+        let message = "Tengo hambre, ¿qué comemos?";
+        let low = offline_reply(
+            &chat(AnimalType::CAT, IntelligenceLevel::Low, Language::Es),
+            None,
+            message,
+        );
+        let high = offline_reply(
+            &chat(AnimalType::CAT, IntelligenceLevel::High, Language::Es),
+            None,
+            message,
+        );
+        assert!(low.chars().count() < high.chars().count(), "{low} / {high}");
+        assert_eq!(first_sentence("¿Comida? Por fin."), "¿Comida?");
+    }
+
+    #[test]
+    fn custom_personas_answer_with_their_catchphrases() {
+        let persona = CustomPersona {
+            name: "Capitán Loro".to_string(),
+            personality: "Eres un loro pirata.".to_string(),
+            catchphrases: vec!["¡Al abordaje!".to_string()],
+        };
+        let chat = chat(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es);
+        assert_eq!(
+            offline_reply(&chat, Some(&persona), "Hola"),
+            "*Capitán Loro se queda pensando* ¡Al abordaje!"
+        );
+    }
+}
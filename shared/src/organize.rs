@@ -0,0 +1,157 @@
+//! How the sidebar arranges chats: pinned first, then by last activity in
+//! Today / Yesterday / Last week / Older sections, with archived chats apart.
+
+use chrono::{FixedOffset, NaiveDate};
+
+use crate::ChatSession;
+
+/// A sidebar section, in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChatGroup {
+    Pinned,
+    Today,
+    Yesterday,
+    /// Two to seven days ago.
+    LastWeek,
+    Older,
+    Archived,
+}
+
+/// `chats` split into non-empty groups, each sorted by `updated_at`, newest first.
+///
+/// Days are counted in `offset` (the user's time zone), up to `today`. With a
+/// `tag`, only chats carrying it are listed.
+pub fn group_chats<'a>(
+    chats: &'a [ChatSession],
+    today: NaiveDate,
+    offset: FixedOffset,
+    tag: Option<&str>,
+) -> Vec<(ChatGroup, Vec<&'a ChatSession>)> {
+    let mut sorted: Vec<&ChatSession> = chats
+        .iter()
+        .filter(|chat| tag.is_none_or(|tag| chat.tags.iter().any(|t| t == tag)))
+        .collect();
+    sorted.sort_by_key(|chat| std::cmp::Reverse(chat.updated_at));
+
+    let mut groups: Vec<(ChatGroup, Vec<&ChatSession>)> = Vec::new();
+    for chat in sorted {
+        let group = if chat.archived {
+            ChatGroup::Archived
+        } else if chat.pinned {
+            ChatGroup::Pinned
+        } else {
+            let day = chat.updated_at.with_timezone(&offset).date_naive();
+            match (today - day).num_days() {
+                ..=0 => ChatGroup::Today,
+                1 => ChatGroup::Yesterday,
+                2..=7 => ChatGroup::LastWeek,
+                _ => ChatGroup::Older,
+            }
+        };
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, list)) => list.push(chat),
+            None => groups.push((group, vec![chat])),
+        }
+    }
+    groups.sort_by_key(|(group, _)| *group);
+    groups
+}
+
+/// Every tag in use, sorted case-insensitively.
+pub fn all_tags(chats: &[ChatSession]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in chats.iter().flat_map(|c| &c.tags) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    tags.sort_by_key(|t| t.to_lowercase());
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalType, IntelligenceLevel, Language};
+
+    fn chat(title: &str, updated_at: &str) -> ChatSession {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es);
+        chat.title = title.to_string();
+        chat.updated_at = updated_at.parse().unwrap();
+        chat
+    }
+
+    fn titles(groups: &[(ChatGroup, Vec<&ChatSession>)]) -> Vec<(ChatGroup, Vec<String>)> {
+        groups
+            .iter()
+            .map(|(g, chats)| (*g, chats.iter().map(|c| c.title.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn chats_are_grouped_by_local_day_and_sorted() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        // UTC-5: 03:00 UTC on the 10th is still the 9th locally.
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        let chats = [
+            chat("late last night", "2025-03-10T03:00:00Z"),
+            chat("this morning", "2025-03-10T14:00:00Z"),
+            chat("noon", "2025-03-10T17:00:00Z"),
+            chat("monday", "2025-03-04T12:00:00Z"),
+            chat("ages ago", "2024-12-01T12:00:00Z"),
+        ];
+
+        assert_eq!(
+            titles(&group_chats(&chats, today, offset, None)),
+            [
+                (
+                    ChatGroup::Today,
+                    vec!["noon".to_string(), "this morning".to_string()]
+                ),
+                (ChatGroup::Yesterday, vec!["late last night".to_string()]),
+                (ChatGroup::LastWeek, vec!["monday".to_string()]),
+                (ChatGroup::Older, vec!["ages ago".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn pinned_and_archived_chats_have_their_own_sections() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let mut pinned = chat("pinned", "2024-01-01T00:00:00Z");
+        pinned.pinned = true;
+        let mut archived = chat("archived", "2025-03-10T00:00:00Z");
+        archived.archived = true;
+        archived.pinned = true;
+        let chats = [archived, chat("today", "2025-03-10T09:00:00Z"), pinned];
+
+        let groups: Vec<ChatGroup> = group_chats(&chats, today, utc, None)
+            .into_iter()
+            .map(|(g, _)| g)
+            .collect();
+        assert_eq!(
+            groups,
+            [ChatGroup::Pinned, ChatGroup::Today, ChatGroup::Archived]
+        );
+    }
+
+    #[test]
+    fn tags_filter_and_are_listed_once() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let mut funny = chat("funny", "2025-03-10T09:00:00Z");
+        funny.set_tags("Gatos, risas , gatos,,");
+        let mut work = chat("work", "2025-03-10T09:00:00Z");
+        work.set_tags("trabajo, Gatos");
+        let chats = [funny, work];
+
+        assert_eq!(chats[0].tags, ["Gatos", "risas"]);
+        assert_eq!(all_tags(&chats), ["Gatos", "risas", "trabajo"]);
+        let risas = group_chats(&chats, today, utc, Some("risas"));
+        assert_eq!(
+            titles(&risas),
+            [(ChatGroup::Today, vec!["funny".to_string()])]
+        );
+    }
+}
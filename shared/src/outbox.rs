@@ -0,0 +1,156 @@
+//! Messages written while offline stay on their chat, flagged `pending`,
+//! until the connection comes back. [`outbox`] lists them in the order they
+//! were written, so they are sent in that order.
+
+use crate::{ChatMessage, ChatSession, MessageMeta, Role, MAX_MESSAGE_LENGTH};
+
+/// A message waiting to be sent: the last message of `chat_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEntry {
+    pub chat_id: String,
+    pub message_id: String,
+}
+
+impl ChatSession {
+    /// Queues `text` until the connection is back.
+    ///
+    /// A chat holds one pending message at most: writing again before it is
+    /// sent adds a paragraph to it, so turns keep alternating. Returns `false`,
+    /// queuing nothing, when that paragraph would take the pending message
+    /// past [`MAX_MESSAGE_LENGTH`].
+    #[must_use]
+    pub fn queue_message(&mut self, text: &str, meta: MessageMeta) -> bool {
+        match self.messages.last_mut().filter(|m| m.pending) {
+            Some(pending) if pending.content.len() + 2 + text.len() > MAX_MESSAGE_LENGTH => {
+                return false;
+            }
+            Some(pending) => {
+                pending.content.push_str("\n\n");
+                pending.content.push_str(text);
+            }
+            None => {
+                let message = ChatMessage {
+                    pending: true,
+                    ..ChatMessage::new(Role::User, text).with_meta(meta)
+                };
+                self.messages.push(message);
+            }
+        }
+        self.touch();
+        true
+    }
+
+    /// The message waiting to be sent, if any.
+    pub fn pending_message(&self) -> Option<&ChatMessage> {
+        self.messages.last().filter(|m| m.pending)
+    }
+
+    /// Takes the pending message out of the outbox to send it, returning its text.
+    pub fn take_pending(&mut self) -> Option<String> {
+        let message = self.messages.last_mut().filter(|m| m.pending)?;
+        message.pending = false;
+        Some(message.content.clone())
+    }
+
+    /// Puts an unanswered message back in the outbox after a failed send.
+    pub fn requeue(&mut self) -> bool {
+        match self.messages.last_mut() {
+            Some(message) if message.role == Role::User => {
+                message.pending = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Every pending message, oldest first.
+pub fn outbox(chats: &[ChatSession]) -> Vec<OutboxEntry> {
+    let mut pending: Vec<(&ChatSession, &ChatMessage)> = chats
+        .iter()
+        .filter_map(|chat| Some((chat, chat.pending_message()?)))
+        .collect();
+    pending.sort_by_key(|(_, message)| message.meta.created_at);
+    pending
+        .into_iter()
+        .map(|(chat, message)| OutboxEntry {
+            chat_id: chat.id.clone(),
+            message_id: message.id.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalType, IntelligenceLevel, Language};
+
+    fn chat() -> ChatSession {
+        ChatSession::new(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es)
+    }
+
+    fn written_at(time: &str) -> MessageMeta {
+        MessageMeta {
+            created_at: Some(time.parse().unwrap()),
+            ..MessageMeta::default()
+        }
+    }
+
+    #[test]
+    fn messages_written_offline_join_one_pending_bubble() {
+        let mut chat = chat();
+        assert!(chat.queue_message("Hola", MessageMeta::default()));
+        assert!(chat.queue_message("¿Estás?", MessageMeta::default()));
+
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.pending_message().unwrap().content, "Hola\n\n¿Estás?");
+        assert_eq!(chat.take_pending().as_deref(), Some("Hola\n\n¿Estás?"));
+        assert_eq!(chat.pending_message(), None);
+        assert_eq!(chat.take_pending(), None);
+
+        assert!(chat.requeue());
+        assert!(chat.pending_message().is_some());
+    }
+
+    #[test]
+    fn outbox_is_sent_oldest_first_across_chats() {
+        let mut later = chat();
+        assert!(later.queue_message("segundo", written_at("2025-03-10T10:05:00Z")));
+        let mut sooner = chat();
+        assert!(sooner.queue_message("primero", written_at("2025-03-10T10:00:00Z")));
+        let mut answered = chat();
+        answered.messages.push(ChatMessage::new(Role::User, "Hola"));
+        let chats = [later.clone(), answered, sooner.clone()];
+
+        let queued = outbox(&chats);
+        let ids: Vec<&str> = queued.iter().map(|e| e.chat_id.as_str()).collect();
+        assert_eq!(ids, [sooner.id.as_str(), later.id.as_str()]);
+        assert_eq!(queued[0].message_id, sooner.messages.last().unwrap().id);
+    }
+
+    #[test]
+    fn pending_message_never_grows_past_the_request_limit() {
+        let mut chat = chat();
+        let long = "a".repeat(MAX_MESSAGE_LENGTH - 6);
+        assert!(chat.queue_message(&long, MessageMeta::default()));
+
+        assert!(!chat.queue_message("¿Sigues ahí?", MessageMeta::default()));
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.pending_message().unwrap().content, long);
+
+        assert!(chat.queue_message("Hola", MessageMeta::default()));
+        assert_eq!(chat.pending_message().unwrap().content.len(), MAX_MESSAGE_LENGTH);
+    }
+
+    #[test]
+    fn pending_flag_survives_a_round_trip() {
+        let mut chat = chat();
+        assert!(chat.queue_message("Hola", MessageMeta::default()));
+        let json = serde_json::to_string(&chat).unwrap();
+        let back: ChatSession = serde_json::from_str(&json).unwrap();
+        assert!(back.pending_message().is_some());
+
+        chat.take_pending();
+        assert!(!serde_json::to_string(&chat).unwrap().contains("pending"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Full-text search over saved chats.
+//!
+//! A query is split into words and every word must appear in the chat, either
+//! in its title or in one of its messages (on any branch). Matching ignores
+//! case and accents. Title matches rank above message matches, and ties go to
+//! the most recently active chat.
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{AnimalType, ChatSession, IntelligenceLevel, MessageNode};
+
+/// Score of a query word found in the title.
+const TITLE_WEIGHT: u32 = 10;
+/// Score of a query word found in a message.
+const MESSAGE_WEIGHT: u32 = 3;
+/// Extra score when the word starts a word rather than sitting inside one.
+const WORD_START_BONUS: u32 = 2;
+/// Characters of context kept on each side of the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Which animal a chat must be played by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimalFilter {
+    Builtin(AnimalType),
+    /// A [`crate::CustomAnimal`] id.
+    Custom(String),
+}
+
+/// Narrows a search; every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilters {
+    pub animal: Option<AnimalFilter>,
+    pub intelligence: Option<IntelligenceLevel>,
+    /// First day of [`ChatSession::updated_at`] to include (UTC).
+    pub from: Option<NaiveDate>,
+    /// Last day of [`ChatSession::updated_at`] to include (UTC).
+    pub to: Option<NaiveDate>,
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == SearchFilters::default()
+    }
+
+    fn accepts(&self, chat: &ChatSession) -> bool {
+        let animal = match &self.animal {
+            None => true,
+            Some(AnimalFilter::Custom(id)) => chat.custom_animal.as_deref() == Some(id.as_str()),
+            Some(AnimalFilter::Builtin(animal)) => {
+                chat.custom_animal.is_none() && chat.animal == *animal
+            }
+        };
+        let day = chat.updated_at.date_naive();
+        animal
+            && self
+                .intelligence
+                .is_none_or(|level| chat.intelligence == level)
+            && self.from.is_none_or(|from| day >= from)
+            && self.to.is_none_or(|to| day <= to)
+    }
+}
+
+/// A chat that matched, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub chat_id: String,
+    /// The best matching message, if the words were found in one.
+    pub message_id: Option<String>,
+    /// Text around the first match in that message.
+    pub snippet: Option<String>,
+    pub score: u32,
+}
+
+/// Chats matching `query` and `filters`, best first.
+///
+/// An empty query lists every chat the filters accept, most recent first.
+pub fn search(chats: &[ChatSession], query: &str, filters: &SearchFilters) -> Vec<SearchHit> {
+    let words: Vec<Vec<char>> = query
+        .split_whitespace()
+        .map(|w| fold(w).collect())
+        .collect();
+
+    let mut hits: Vec<(SearchHit, DateTime<Utc>)> = chats
+        .iter()
+        .filter(|chat| filters.accepts(chat))
+        .filter_map(|chat| Some((match_chat(chat, &words)?, chat.updated_at)))
+        .collect();
+    hits.sort_by(|(a, a_time), (b, b_time)| b.score.cmp(&a.score).then(b_time.cmp(a_time)));
+    hits.into_iter().map(|(hit, _)| hit).collect()
+}
+
+fn match_chat(chat: &ChatSession, words: &[Vec<char>]) -> Option<SearchHit> {
+    let title: Vec<char> = fold(&chat.title).collect();
+    let title_scores: Vec<u32> = words
+        .iter()
+        .map(|w| score(&title, w) * TITLE_WEIGHT)
+        .collect();
+    let messages: Vec<(&MessageNode, Vec<char>, Vec<u32>)> = chat
+        .messages
+        .nodes()
+        .iter()
+        .map(|node| {
+            let text: Vec<char> = fold(&node.message.content).collect();
+            let scores = words
+                .iter()
+                .map(|w| score(&text, w) * MESSAGE_WEIGHT)
+                .collect();
+            (node, text, scores)
+        })
+        .collect();
+
+    let found =
+        |i: usize| title_scores[i] > 0 || messages.iter().any(|(_, _, scores)| scores[i] > 0);
+    if !(0..words.len()).all(found) {
+        return None;
+    }
+
+    // The message matching the most words wins; then the highest score.
+    let best = messages
+        .iter()
+        .filter(|(_, _, scores)| scores.iter().any(|&s| s > 0))
+        .max_by_key(|(_, _, scores)| {
+            (
+                scores.iter().filter(|&&s| s > 0).count(),
+                scores.iter().sum::<u32>(),
+            )
+        });
+    Some(SearchHit {
+        chat_id: chat.id.clone(),
+        message_id: best.map(|(node, _, _)| node.id().to_string()),
+        snippet: best.map(|(node, text, _)| snippet(node, text, words)),
+        score: title_scores.iter().sum::<u32>()
+            + best.map_or(0, |(_, _, scores)| scores.iter().sum()),
+    })
+}
+
+/// 1 for a match inside a word, plus a bonus if one starts a word; 0 if absent.
+fn score(text: &[char], word: &[char]) -> u32 {
+    let mut starts = positions(text, word);
+    let Some(first) = starts.next() else {
+        return 0;
+    };
+    let at_word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+    if at_word_start(first) || starts.any(at_word_start) {
+        1 + WORD_START_BONUS
+    } else {
+        1
+    }
+}
+
+fn positions<'a>(text: &'a [char], word: &'a [char]) -> impl Iterator<Item = usize> + 'a {
+    let last = (text.len() + 1).saturating_sub(word.len());
+    (0..last).filter(move |&i| !word.is_empty() && text[i..i + word.len()] == *word)
+}
+
+/// Text of `node` around the first query word found in it.
+fn snippet(node: &MessageNode, folded: &[char], words: &[Vec<char>]) -> String {
+    // `fold` maps characters one to one, so positions carry over.
+    let original: Vec<char> = node.message.content.chars().collect();
+    let first = words
+        .iter()
+        .filter_map(|w| positions(folded, w).next())
+        .min()
+        .unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_CONTEXT);
+    let end = (first + SNIPPET_CONTEXT * 2).min(original.len());
+    let text: String = original[start..end].iter().collect();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!(
+        "{}{text}{}",
+        if start > 0 { "…" } else { "" },
+        if end < original.len() { "…" } else { "" },
+    )
+}
+
+/// Lowercases and strips accents, one character in, one out.
+pub(crate) fn fold(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars().map(|c| {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match lower {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            other => other,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatMessage, Language, Role};
+
+    fn chat(title: &str, messages: &[&str]) -> ChatSession {
+        let mut chat = ChatSession::new(AnimalType::CAT, IntelligenceLevel::Medium, Language::Es);
+        chat.title = title.to_string();
+        for (i, text) in messages.iter().enumerate() {
+            let role = if i % 2 == 0 {
+                Role::User
+            } else {
+                Role::Assistant
+            };
+            chat.messages.push(ChatMessage::new(role, *text));
+        }
+        chat
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|h| h.chat_id.as_str()).collect()
+    }
+
+    #[test]
+    fn titles_rank_above_messages() {
+        let by_message = chat(
+            "Siesta",
+            &["Hablemos de ratones", "Los ratones son rápidos"],
+        );
+        let by_title = chat("Ratones", &["Hola"]);
+        let unrelated = chat("Pájaros", &["Pío"]);
+        let chats = [by_message.clone(), by_title.clone(), unrelated];
+
+        let hits = search(&chats, "ratones", &SearchFilters::default());
+        assert_eq!(ids(&hits), [by_title.id.as_str(), by_message.id.as_str()]);
+        assert_eq!(hits[0].message_id, None);
+        assert!(hits[1].message_id.is_some());
+    }
+
+    #[test]
+    fn every_word_must_match_ignoring_case_and_accents() {
+        let chats = [chat("Gatos", &["El CAFÉ está frío", "Miau"])];
+
+        assert_eq!(
+            search(&chats, "cafe frio", &SearchFilters::default()).len(),
+            1
+        );
+        assert!(search(&chats, "cafe caliente", &SearchFilters::default()).is_empty());
+    }
+
+    #[test]
+    fn hits_point_at_the_best_message_with_a_snippet() {
+        let long = format!("{} el láser rojo {}", "bla ".repeat(30), "bla ".repeat(30));
+        let chat = chat(
+            "Juegos",
+            &["¿Te gusta el láser?", &long, "¿Y el láser rojo?"],
+        );
+        let nodes = chat.messages.nodes();
+
+        let hits = search(
+            std::slice::from_ref(&chat),
+            "laser rojo",
+            &SearchFilters::default(),
+        );
+        assert_eq!(hits[0].message_id.as_deref(), Some(nodes[2].id()));
+
+        let hits = search(
+            std::slice::from_ref(&chat),
+            "bla laser",
+            &SearchFilters::default(),
+        );
+        assert_eq!(hits[0].message_id.as_deref(), Some(nodes[1].id()));
+        let snippet = hits[0].snippet.as_deref().unwrap();
+        assert!(
+            snippet.starts_with("bla") && snippet.ends_with('…'),
+            "{snippet}"
+        );
+    }
+
+    #[test]
+    fn filters_narrow_results_and_work_without_a_query() {
+        let cat = chat("Uno", &[]);
+        let mut dog = chat("Dos", &[]);
+        dog.animal = AnimalType::DOG;
+        dog.intelligence = IntelligenceLevel::High;
+        let mut custom = chat("Tres", &[]);
+        custom.custom_animal = Some("pingu".to_string());
+        let chats = [cat.clone(), dog.clone(), custom.clone()];
+
+        let only = |filters: SearchFilters| {
+            ids(&search(&chats, "", &filters))
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            only(SearchFilters {
+                animal: Some(AnimalFilter::Builtin(AnimalType::CAT)),
+                ..Default::default()
+            }),
+            [cat.id]
+        );
+        assert_eq!(
+            only(SearchFilters {
+                animal: Some(AnimalFilter::Custom("pingu".to_string())),
+                ..Default::default()
+            }),
+            [custom.id]
+        );
+        assert_eq!(
+            only(SearchFilters {
+                intelligence: Some(IntelligenceLevel::High),
+                ..Default::default()
+            }),
+            [dog.id]
+        );
+    }
+
+    #[test]
+    fn date_range_uses_the_last_activity() {
+        let mut old = chat("Viejo", &[]);
+        old.created_at = "2025-01-01T10:00:00Z".parse().unwrap();
+        old.updated_at = old.created_at;
+        let mut revived = old.clone();
+        revived.id = "revived".to_string();
+        revived.updated_at = "2025-03-05T10:00:00Z".parse().unwrap();
+        let chats = [old.clone(), revived];
+
+        let march = SearchFilters {
+            from: NaiveDate::from_ymd_opt(2025, 3, 1),
+            to: NaiveDate::from_ymd_opt(2025, 3, 31),
+            ..SearchFilters::default()
+        };
+        assert_eq!(ids(&search(&chats, "", &march)), ["revived"]);
+
+        let january = SearchFilters {
+            to: NaiveDate::from_ymd_opt(2025, 1, 1),
+            ..SearchFilters::default()
+        };
+        assert_eq!(ids(&search(&chats, "", &january)), [old.id.as_str()]);
+    }
+}
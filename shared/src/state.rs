@@ -0,0 +1,322 @@
+//! Versioned storage format for [`AppState`].
+//!
+//! The browser keeps a single JSON blob, `{"version": N, "data": {...}}`.
+//! Blobs written by older builds are upgraded by running [`MIGRATIONS`] in
+//! order, one hop per version, before they are deserialized. Anything that
+//! still doesn't fit is reported as an error instead of being replaced by an
+//! empty state, so the caller can keep the raw blob.
+//!
+//! Migrations work on plain JSON rather than on the current types, so they
+//! keep describing the old shapes when those types change again.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{ChatSession, CustomAnimal, Language};
+
+/// Everything the app persists in the browser.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AppState {
+    pub chats: Vec<ChatSession>,
+    pub active_chat_id: Option<String>,
+    #[serde(default)]
+    pub language: Language,
+    #[serde(default)]
+    pub custom_animals: Vec<CustomAnimal>,
+}
+
+/// Version written by this build.
+pub const STATE_VERSION: u32 = 3;
+
+type Migration = fn(Value) -> Result<Value, String>;
+
+/// `MIGRATIONS[n]` upgrades version `n` data to version `n + 1`.
+/// Version 0 is the bare `AppState` stored before the envelope existed.
+const MIGRATIONS: [Migration; STATE_VERSION as usize] =
+    [per_chat_language, message_trees, last_activity];
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    data: &'a AppState,
+}
+
+/// Why a stored blob could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// Written by a newer build of the app.
+    TooNew { version: u32 },
+    /// Not JSON, or data no migration can make sense of.
+    Unreadable(String),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::TooNew { version } => write!(
+                f,
+                "Saved data has version {version}, newer than the supported {STATE_VERSION}"
+            ),
+            StateError::Unreadable(e) => write!(f, "Saved data is unreadable: {e}"),
+        }
+    }
+}
+
+/// Serializes `state` inside the current envelope.
+pub fn encode_state(state: &AppState) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&Envelope {
+        version: STATE_VERSION,
+        data: state,
+    })
+}
+
+/// Parses a stored blob of any known version.
+pub fn decode_state(raw: &str) -> Result<AppState, StateError> {
+    let value: Value =
+        serde_json::from_str(raw).map_err(|e| StateError::Unreadable(e.to_string()))?;
+    let (version, data) = open_envelope(value)?;
+    upgrade_state(version, data)
+}
+
+/// Migrates `data` written at `version` and deserializes it.
+///
+/// For stores that keep the state in several records and track the version
+/// themselves instead of using the envelope.
+pub fn upgrade_state(version: u32, data: Value) -> Result<AppState, StateError> {
+    if version > STATE_VERSION {
+        return Err(StateError::TooNew { version });
+    }
+    let data = migrate(data, version)?;
+    serde_json::from_value(data).map_err(|e| StateError::Unreadable(e.to_string()))
+}
+
+/// Migrates and deserializes one chat of a state kept in several records,
+/// so a chat that fails can be set aside without losing the others.
+///
+/// `settings` is the rest of that state at the same `version`; migrations
+/// may read it, but it isn't deserialized here.
+pub fn upgrade_chat(version: u32, settings: &Value, chat: Value) -> Result<ChatSession, StateError> {
+    if version > STATE_VERSION {
+        return Err(StateError::TooNew { version });
+    }
+    let mut data = settings.as_object().cloned().unwrap_or_default();
+    data.insert("chats".to_string(), Value::Array(vec![chat]));
+    let mut data = migrate(Value::Object(data), version)?;
+    let chat = data
+        .get_mut("chats")
+        .and_then(Value::as_array_mut)
+        .and_then(Vec::pop)
+        .unwrap_or_default();
+    serde_json::from_value(chat).map_err(|e| StateError::Unreadable(e.to_string()))
+}
+
+/// Splits an envelope into its version and data; anything else is version 0.
+fn open_envelope(value: Value) -> Result<(u32, Value), StateError> {
+    let Value::Object(mut map) = value else {
+        return Ok((0, value));
+    };
+    if !(map.contains_key("version") && map.contains_key("data")) {
+        return Ok((0, Value::Object(map)));
+    }
+    let version = map
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| StateError::Unreadable("Invalid envelope version".to_string()))?;
+    Ok((version, map.remove("data").unwrap_or_default()))
+}
+
+/// Runs every hop from `version` up to [`STATE_VERSION`].
+fn migrate(data: Value, version: u32) -> Result<Value, StateError> {
+    (version..STATE_VERSION).try_fold(data, |data, from| {
+        MIGRATIONS[from as usize](data).map_err(|e| {
+            StateError::Unreadable(format!("Migration from version {from} failed: {e}"))
+        })
+    })
+}
+
+/// The `chats` array of a state object, or an error if the state isn't one.
+fn chats_mut(data: &mut Value) -> Result<Vec<&mut serde_json::Map<String, Value>>, String> {
+    let state = data.as_object_mut().ok_or("State is not an object")?;
+    Ok(match state.get_mut("chats") {
+        Some(Value::Array(chats)) => chats.iter_mut().filter_map(Value::as_object_mut).collect(),
+        Some(Value::Null) | None => vec![],
+        Some(_) => return Err("`chats` is not a list".to_string()),
+    })
+}
+
+/// v0 → v1: chats from before per-chat languages take the app's language
+/// instead of falling back to Spanish.
+fn per_chat_language(mut data: Value) -> Result<Value, String> {
+    let language = data.get("language").cloned().unwrap_or_else(|| json!("es"));
+    for chat in chats_mut(&mut data)? {
+        chat.entry("language").or_insert_with(|| language.clone());
+    }
+    Ok(data)
+}
+
+/// v1 → v2: flat message lists become single-branch message trees.
+fn message_trees(mut data: Value) -> Result<Value, String> {
+    for chat in chats_mut(&mut data)? {
+        let Some(Value::Array(messages)) = chat.remove("messages") else {
+            continue;
+        };
+        let mut parent: Option<String> = None;
+        let mut nodes = Vec::with_capacity(messages.len());
+        for mut message in messages {
+            let node = message.as_object_mut().ok_or("Message is not an object")?;
+            let id = uuid::Uuid::new_v4().to_string();
+            node.insert("id".to_string(), json!(id));
+            if let Some(parent) = parent.replace(id) {
+                node.insert("parent".to_string(), json!(parent));
+            }
+            nodes.push(message);
+        }
+        chat.insert("messages".to_string(), json!({ "nodes": nodes, "active": parent }));
+    }
+    Ok(data)
+}
+
+/// v2 → v3: chats get an `updated_at`, from their newest message or their creation.
+fn last_activity(mut data: Value) -> Result<Value, String> {
+    let time = |value: &Value| {
+        value
+            .as_str()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&chrono::Utc))
+    };
+    for chat in chats_mut(&mut data)? {
+        let created_at = chat.get("created_at").and_then(time);
+        let newest_message = chat
+            .get("messages")
+            .and_then(|m| m.get("nodes"))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node.get("created_at").and_then(time))
+            .max();
+        let updated_at = newest_message.max(created_at).ok_or("Chat has no creation date")?;
+        chat.insert("updated_at".to_string(), json!(updated_at));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimalType, ChatMessage, IntelligenceLevel, Role};
+
+    /// A state as stored before the envelope: flat messages, no per-chat language.
+    const LEGACY: &str = r#"{
+        "chats": [{
+            "id": "c1", "title": "Gatos", "animal": "cat", "intelligence": "high",
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "Meow"}
+            ],
+            "created_at": "2025-01-01T00:00:00Z"
+        }],
+        "active_chat_id": "c1",
+        "language": "en"
+    }"#;
+
+    #[test]
+    fn chats_inherit_the_app_language() {
+        let data = per_chat_language(serde_json::from_str(LEGACY).unwrap()).unwrap();
+        assert_eq!(data["chats"][0]["language"], "en");
+
+        let spanish = json!({ "chats": [{ "language": "es" }], "language": "en" });
+        assert_eq!(per_chat_language(spanish).unwrap()["chats"][0]["language"], "es");
+    }
+
+    #[test]
+    fn flat_messages_become_a_linked_branch() {
+        let data = message_trees(serde_json::from_str(LEGACY).unwrap()).unwrap();
+        let tree = &data["chats"][0]["messages"];
+        let nodes = tree["nodes"].as_array().unwrap();
+
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes[0].get("parent").is_none());
+        assert_eq!(nodes[1]["parent"], nodes[0]["id"]);
+        assert_eq!(tree["active"], nodes[1]["id"]);
+        assert_eq!(nodes[1]["content"], "Meow");
+    }
+
+    #[test]
+    fn chats_are_last_active_at_their_newest_message() {
+        let data = json!({ "chats": [
+            { "created_at": "2025-01-01T00:00:00Z", "messages": { "nodes": [
+                { "created_at": "2025-02-01T00:00:00Z" },
+                { "created_at": "2025-01-15T00:00:00Z" },
+                {}
+            ]}},
+            { "created_at": "2025-01-01T00:00:00Z", "messages": { "nodes": [] } }
+        ]});
+        let data = last_activity(data).unwrap();
+        assert_eq!(data["chats"][0]["updated_at"], "2025-02-01T00:00:00Z");
+        assert_eq!(data["chats"][1]["updated_at"], "2025-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn legacy_blobs_run_the_whole_chain() {
+        let state = decode_state(LEGACY).unwrap();
+        let chat = &state.chats[0];
+
+        assert_eq!(chat.language, Language::En);
+        assert_eq!(chat.messages.len(), 2);
+        assert_eq!(chat.messages.last().map(|m| m.content.as_str()), Some("Meow"));
+        assert_eq!(state.active_chat_id.as_deref(), Some("c1"));
+        assert_eq!(chat.updated_at, chat.created_at);
+    }
+
+    #[test]
+    fn current_state_round_trips() {
+        let mut chat = ChatSession::new(AnimalType::DOG, IntelligenceLevel::Low, Language::Es);
+        chat.messages.push(ChatMessage::new(Role::User, "¿Paseo?"));
+        let state = AppState {
+            active_chat_id: Some(chat.id.clone()),
+            chats: vec![chat],
+            ..AppState::default()
+        };
+
+        let raw = encode_state(&state).unwrap();
+        assert!(raw.starts_with(&format!("{{\"version\":{STATE_VERSION},")));
+        assert_eq!(decode_state(&raw).unwrap(), state);
+    }
+
+    #[test]
+    fn chat_records_upgrade_one_at_a_time() {
+        let mut legacy: Value = serde_json::from_str(LEGACY).unwrap();
+        let chat = legacy["chats"].as_array_mut().unwrap().remove(0);
+        let settings = json!({ "language": "en", "custom_animals": "broken" });
+
+        let upgraded = upgrade_chat(0, &settings, chat).unwrap();
+        assert_eq!(upgraded.language, Language::En);
+        assert_eq!(upgraded.messages.len(), 2);
+
+        assert!(matches!(
+            upgrade_chat(STATE_VERSION, &settings, json!("{not json")),
+            Err(StateError::Unreadable(_))
+        ));
+        assert_eq!(
+            upgrade_chat(STATE_VERSION + 1, &settings, json!({})),
+            Err(StateError::TooNew { version: STATE_VERSION + 1 })
+        );
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let raw = format!(r#"{{"version":{},"data":{{}}}}"#, STATE_VERSION + 1);
+        assert_eq!(
+            decode_state(&raw),
+            Err(StateError::TooNew { version: STATE_VERSION + 1 })
+        );
+    }
+
+    #[test]
+    fn unreadable_data_is_an_error_not_an_empty_state() {
+        for raw in ["{not json", r#"{"chats":"oops"}"#, r#"{"version":2,"data":{"chats":[{}]}}"#] {
+            assert!(matches!(decode_state(raw), Err(StateError::Unreadable(_))), "{raw}");
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Branching conversations.
+//!
+//! Editing an earlier user message forks a new branch next to the original
+//! instead of overwriting it. Every message is a node with a stable id and a
+//! parent; the branch on screen (and sent as history) is the path from the
+//! root to the `active` leaf.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ChatMessage;
+
+/// A message and its place in the conversation tree.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MessageNode {
+    /// The message this one follows; `None` for the first message of the chat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Carries the node's `id`.
+    #[serde(flatten)]
+    pub message: ChatMessage,
+}
+
+impl MessageNode {
+    pub fn id(&self) -> &str {
+        &self.message.id
+    }
+}
+
+/// All messages of a chat, in creation order, plus the active leaf.
+///
+/// The flat message lists stored before branching existed are turned into a
+/// single branch by the state migrations, not here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "StoredMessages")]
+pub struct MessageTree {
+    nodes: Vec<MessageNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active: Option<String>,
+}
+
+/// Persisted shape of [`MessageTree`].
+#[derive(Deserialize)]
+struct StoredMessages {
+    nodes: Vec<MessageNode>,
+    #[serde(default)]
+    active: Option<String>,
+}
+
+impl From<StoredMessages> for MessageTree {
+    fn from(StoredMessages { nodes, active }: StoredMessages) -> Self {
+        // A dangling pointer falls back to the newest message.
+        let active = active
+            .filter(|id| nodes.iter().any(|n| n.id() == id.as_str()))
+            .or_else(|| nodes.last().map(|n| n.id().to_string()));
+        MessageTree { nodes, active }
+    }
+}
+
+impl MessageTree {
+    /// Every message on every branch, in creation order.
+    pub fn nodes(&self) -> &[MessageNode] {
+        &self.nodes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&MessageNode> {
+        self.nodes.iter().find(|n| n.id() == id)
+    }
+
+    pub fn message_mut(&mut self, id: &str) -> Option<&mut ChatMessage> {
+        self.nodes.iter_mut().find(|n| n.id() == id).map(|n| &mut n.message)
+    }
+
+    /// The active branch, from the first message to the active leaf.
+    pub fn path(&self) -> Vec<&MessageNode> {
+        let mut path = Vec::new();
+        let mut cursor = self.active.as_deref();
+        // Bounded by the node count, so a corrupted parent cycle cannot hang.
+        while let Some(node) = cursor.and_then(|id| self.get(id)) {
+            if path.len() == self.nodes.len() {
+                break;
+            }
+            path.push(node);
+            cursor = node.parent.as_deref();
+        }
+        path.reverse();
+        path
+    }
+
+    /// Messages of the active branch, oldest first.
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        self.path().into_iter().map(|n| n.message.clone()).collect()
+    }
+
+    /// Length of the active branch.
+    pub fn len(&self) -> usize {
+        self.path().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_none()
+    }
+
+    pub fn last(&self) -> Option<&ChatMessage> {
+        self.active.as_deref().and_then(|id| self.get(id)).map(|n| &n.message)
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut ChatMessage> {
+        let id = self.active.clone()?;
+        self.message_mut(&id)
+    }
+
+    /// Appends `message` to the active branch and returns its id.
+    pub fn push(&mut self, message: ChatMessage) -> String {
+        let parent = self.active.clone();
+        self.insert(parent, message)
+    }
+
+    /// Removes the active leaf (when it has no replies) and returns it.
+    ///
+    /// If it was a fork, the branch it was forked from is shown again.
+    pub fn pop(&mut self) -> Option<ChatMessage> {
+        let id = self.active.clone()?;
+        if self.children(&id).next().is_some() {
+            return None;
+        }
+        let index = self.nodes.iter().position(|n| n.id() == id)?;
+        let node = self.nodes.remove(index);
+        self.active = None;
+        let fallback = match &node.parent {
+            Some(parent) => Some(parent.clone()),
+            None => self.nodes.iter().rev().find(|n| n.parent.is_none()).map(|n| n.id().to_string()),
+        };
+        if let Some(fallback) = fallback {
+            self.switch_to(&fallback);
+        }
+        Some(node.message)
+    }
+
+    /// Starts a new branch next to message `id`, with `message` in its place.
+    ///
+    /// The original message and everything after it are kept. Returns the new id.
+    pub fn fork(&mut self, id: &str, message: ChatMessage) -> Option<String> {
+        let parent = self.get(id)?.parent.clone();
+        Some(self.insert(parent, message))
+    }
+
+    /// Ids of message `id` and its alternatives (same parent), oldest first.
+    pub fn siblings(&self, id: &str) -> Vec<&str> {
+        match self.get(id) {
+            Some(node) => self
+                .nodes
+                .iter()
+                .filter(|n| n.parent == node.parent)
+                .map(|n| n.id())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Shows the branch through message `id`, following its newest replies to a leaf.
+    pub fn switch_to(&mut self, id: &str) {
+        if self.get(id).is_none() {
+            return;
+        }
+        let mut leaf = id.to_string();
+        while let Some(child) = self.children(&leaf).last() {
+            leaf = child.id().to_string();
+        }
+        self.active = Some(leaf);
+    }
+
+    /// Position of message `id` in the active branch.
+    pub fn depth(&self, id: &str) -> Option<usize> {
+        self.path().iter().position(|n| n.id() == id)
+    }
+
+    fn children<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a MessageNode> {
+        self.nodes.iter().filter(move |n| n.parent.as_deref() == Some(id))
+    }
+
+    /// Adds `message` under `parent`, keeping its id unless it is missing or taken.
+    fn insert(&mut self, parent: Option<String>, mut message: ChatMessage) -> String {
+        if message.id.is_empty() || self.get(&message.id).is_some() {
+            message.id = uuid::Uuid::new_v4().to_string();
+        }
+        let id = message.id.clone();
+        self.nodes.push(MessageNode { parent, message });
+        self.active = Some(id.clone());
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role;
+
+    fn contents(tree: &MessageTree) -> Vec<String> {
+        tree.messages().into_iter().map(|m| m.content).collect()
+    }
+
+    fn linear(texts: &[&str]) -> MessageTree {
+        let mut tree = MessageTree::default();
+        for (i, text) in texts.iter().enumerate() {
+            let role = if i % 2 == 0 { Role::User } else { Role::Assistant };
+            tree.push(ChatMessage::new(role, *text));
+        }
+        tree
+    }
+
+    #[test]
+    fn trees_round_trip_and_flat_lists_are_left_to_the_migrations() {
+        let tree = linear(&["Hola", "Miau"]);
+        let round_trip: MessageTree = serde_json::from_str(&serde_json::to_string(&tree).unwrap()).unwrap();
+        assert_eq!(round_trip, tree);
+
+        let flat = r#"[{"role":"user","content":"Hola"},{"role":"assistant","content":"Miau"}]"#;
+        assert!(serde_json::from_str::<MessageTree>(flat).is_err());
+    }
+
+    #[test]
+    fn forking_keeps_the_original_branch() {
+        let mut tree = linear(&["¿Pescado?", "Miau", "¿Leche?", "Prrr"]);
+        let edited = tree.path()[2].id().to_string();
+
+        let fork = tree.fork(&edited, ChatMessage::new(Role::User, "¿Atún?")).unwrap();
+        tree.push(ChatMessage::new(Role::Assistant, "¡MIAU!"));
+        assert_eq!(contents(&tree), ["¿Pescado?", "Miau", "¿Atún?", "¡MIAU!"]);
+        assert_eq!(tree.siblings(&fork), [edited.as_str(), fork.as_str()]);
+
+        tree.switch_to(&edited);
+        assert_eq!(contents(&tree), ["¿Pescado?", "Miau", "¿Leche?", "Prrr"]);
+        assert_eq!(tree.depth(&edited), Some(2));
+    }
+
+    #[test]
+    fn pop_only_removes_unanswered_leaves() {
+        let mut tree = linear(&["Hola", "Miau", "¿Sigues ahí?"]);
+        assert_eq!(tree.pop().map(|m| m.content).as_deref(), Some("¿Sigues ahí?"));
+        assert_eq!(tree.len(), 2);
+
+        let first = tree.path()[0].id().to_string();
+        tree.switch_to(&first);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.last().map(|m| m.content.as_str()), Some("Miau"));
+
+        // A refused edit goes back to the original branch.
+        tree.fork(&first, ChatMessage::new(Role::User, "Buenas")).unwrap();
+        assert_eq!(tree.len(), 1);
+        tree.pop();
+        assert_eq!(contents(&tree), ["Hola", "Miau"]);
+    }
+
+    #[test]
+    fn dangling_active_pointer_falls_back_to_the_newest_message() {
+        let json = r#"{"nodes":[{"id":"a","role":"user","content":"Hola"}],"active":"gone"}"#;
+        let tree: MessageTree = serde_json::from_str(json).unwrap();
+        assert_eq!(contents(&tree), ["Hola"]);
+    }
+}
@@ -1,15 +1,29 @@
-use serde::{Deserialize, Serialize};
+//! Cloudflare Workers entry point. Validation, prompts and providers live in
+//! `chat-core`; this crate routes requests, enforces rate limits and writes
+//! responses with `workers-rs`.
+
+use std::rc::Rc;
+
+use chat_core::completions::{self, ChunkWriter, CompletionRequest, ResponseId};
+use chat_core::providers::{self, ChatPrompt, LlmProvider, ProviderError};
+use chat_core::rate_limit::{RateDecision, RateLimits};
+use chat_core::{
+    config_error, invalid_body, provider_error, rate_limited_error, title, validate_request,
+    validate_title_request, with_model,
+};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
 use serde_json::json;
-use shared::{AnimalType, ChatMessage, ChatRequest, ChatResponse, IntelligenceLevel, Role};
+use shared::{
+    ApiError, ChatRequest, ChatResponse, ChatStreamEvent, ConversationSummary, TitleRequest,
+    TitleResponse,
+};
 use worker::*;
 
-// ═══════════════════════════════════════════════
-// Security Constants
-// ═══════════════════════════════════════════════
+mod platform;
+mod rate_limit;
 
-const MAX_MESSAGE_LENGTH: usize = 4_000;
-const MAX_HISTORY_MESSAGES: usize = 50;
-const MAX_HISTORY_CONTENT_LENGTH: usize = 8_000;
+use platform::{client_signal, EnvSettings, FetchClient, WorkerTimer};
 
 // ═══════════════════════════════════════════════
 // Entry Point
@@ -25,8 +39,30 @@ async fn fetch(req: Request, env: Env, _ctx: Context) -> Result<Response> {
             let allowed_origin = get_allowed_origin(&ctx);
             cors_response(Response::empty(), &allowed_origin)
         })
+        .options("/api/chat/stream", |_req, ctx| {
+            let allowed_origin = get_allowed_origin(&ctx);
+            cors_response(Response::empty(), &allowed_origin)
+        })
+        .options("/api/title", |_req, ctx| {
+            let allowed_origin = get_allowed_origin(&ctx);
+            cors_response(Response::empty(), &allowed_origin)
+        })
+        .options("/v1/chat/completions", |_req, ctx| {
+            let allowed_origin = get_allowed_origin(&ctx);
+            cors_response(Response::empty(), &allowed_origin)
+        })
         // Main chat endpoint
         .post_async("/api/chat", handle_chat)
+        // Streaming chat endpoint (Server-Sent Events)
+        .post_async("/api/chat/stream", handle_chat_stream)
+        // Automatic chat titles
+        .post_async("/api/title", handle_title)
+        // OpenAI-compatible facade: each animal and level is a model
+        .post_async("/v1/chat/completions", handle_completions)
+        .get("/v1/models", |_req, ctx| {
+            let allowed_origin = get_allowed_origin(&ctx);
+            cors_response(Response::from_json(&completions::model_list()), &allowed_origin)
+        })
         // Health check
         .get("/api/health", |_req, ctx| {
             let allowed_origin = get_allowed_origin(&ctx);
@@ -51,344 +87,341 @@ async fn handle_chat(mut req: Request, ctx: RouteContext<()>) -> Result<Response
         Ok(b) => b,
         Err(e) => {
             console_error!("Invalid request body: {e}");
-            return cors_response(
-                Response::error("Invalid request body", 400),
-                &allowed_origin,
-            );
+            return error_response(&invalid_body(), &allowed_origin);
         }
     };
 
     // ── Input Validation ──
-
-    // 1. Message must not be empty
-    if body.message.trim().is_empty() {
-        return cors_response(
-            Response::error("Message cannot be empty", 400),
-            &allowed_origin,
-        );
+    if let Err(error) = validate_request(&body) {
+        return error_response(&error, &allowed_origin);
     }
 
-    // 2. Message length limit
-    if body.message.len() > MAX_MESSAGE_LENGTH {
-        return cors_response(
-            Response::error(
-                format!("Message exceeds maximum length of {MAX_MESSAGE_LENGTH} characters"),
-                400,
-            ),
-            &allowed_origin,
-        );
-    }
-
-    // 3. History size limit
-    if body.history.len() > MAX_HISTORY_MESSAGES {
-        return cors_response(
-            Response::error(
-                format!("History exceeds maximum of {MAX_HISTORY_MESSAGES} messages"),
-                400,
-            ),
-            &allowed_origin,
-        );
-    }
-
-    // 4. Validate history structure: alternating roles, content length
-    if let Err(msg) = validate_history(&body.history) {
-        return cors_response(Response::error(msg, 400), &allowed_origin);
-    }
+    // ── Rate Limiting ──
+    let rate = match enforce_rate_limit(&req, &ctx.env).await {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision, &allowed_origin),
+    };
 
-    // Get API key from secrets
-    let api_key = match ctx.secret("GEMINI_API_KEY") {
-        Ok(key) => key.to_string(),
-        Err(_) => {
-            console_error!("GEMINI_API_KEY secret not configured");
-            return cors_response(
-                Response::error("Server configuration error", 500),
-                &allowed_origin,
-            );
+    // Resolve the configured LLM provider
+    let provider = match provider_for(&req, &ctx.env) {
+        Ok(p) => p,
+        Err(e) => {
+            console_error!("Provider configuration error: {e}");
+            return error_response(&config_error(), &allowed_origin);
         }
     };
 
-    // Build prompt
-    let system_prompt = build_system_prompt(&body.animal, &body.intelligence);
+    // Build prompt, folding old turns into the summary when over budget
+    let (prompt, summary) = fitted_prompt(&*provider, body).await;
 
-    // Call Gemini API
-    match call_gemini(&api_key, &system_prompt, &body.message, &body.history).await {
-        Ok(gemini_response) => {
+    // Call the model
+    match provider.generate(&prompt).await {
+        Ok(completion) => {
             let chat_response = ChatResponse {
-                response: gemini_response.text,
-                tokens_used: gemini_response.tokens_used,
+                response: completion.text,
+                tokens_used: completion.tokens_used,
+                alternatives: completion.alternatives,
+                summary,
+                model: Some(provider.model().to_string()),
             };
-            cors_response(Response::from_json(&chat_response), &allowed_origin)
-        }
-        Err(e) => {
-            console_error!("Gemini API error: {e}");
             cors_response(
-                Response::error("AI service unavailable", 502),
+                with_rate_headers(Response::from_json(&chat_response), rate.as_ref()),
                 &allowed_origin,
             )
         }
+        Err(e) => {
+            log_provider_error(&*provider, &e);
+            error_response(&provider_error(&e), &allowed_origin)
+        }
     }
 }
 
 // ═══════════════════════════════════════════════
-// Input Validation
+// Streaming Chat Handler
 // ═══════════════════════════════════════════════
 
-/// Validates the chat history structure:
-/// - Must alternate User → Assistant (starting with User if non-empty).
-/// - Each message content must be within length limits.
-fn validate_history(history: &[ChatMessage]) -> std::result::Result<(), String> {
-    for (i, msg) in history.iter().enumerate() {
-        if msg.content.len() > MAX_HISTORY_CONTENT_LENGTH {
-            return Err(format!(
-                "History message {i} exceeds maximum length of {MAX_HISTORY_CONTENT_LENGTH} characters"
-            ));
-        }
+async fn handle_chat_stream(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let allowed_origin = get_allowed_origin(&ctx);
 
-        let expected_role = if i % 2 == 0 { Role::User } else { Role::Assistant };
-        if msg.role != expected_role {
-            return Err("Invalid history structure: roles must alternate User/Assistant starting with User".to_string());
+    let body: ChatRequest = match req.json().await {
+        Ok(b) => b,
+        Err(e) => {
+            console_error!("Invalid request body: {e}");
+            return error_response(&invalid_body(), &allowed_origin);
         }
+    };
+
+    if let Err(error) = validate_request(&body) {
+        return error_response(&error, &allowed_origin);
     }
-    Ok(())
+
+    let rate = match enforce_rate_limit(&req, &ctx.env).await {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision, &allowed_origin),
+    };
+
+    let provider = match provider_for(&req, &ctx.env) {
+        Ok(p) => p,
+        Err(e) => {
+            console_error!("Provider configuration error: {e}");
+            return error_response(&config_error(), &allowed_origin);
+        }
+    };
+
+    let (prompt, summary) = fitted_prompt(&*provider, body).await;
+
+    let events = match provider.stream(&prompt).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log_provider_error(&*provider, &e);
+            return error_response(&provider_error(&e), &allowed_origin);
+        }
+    };
+
+    // A new summary goes first so the client can cache it even if the reply fails midway.
+    let summary_event = summary.map(|summary| ChatStreamEvent::Summary { summary });
+    let model = provider.model().to_string();
+    let frames = stream::iter(summary_event)
+        .chain(events)
+        .map(move |event| with_model(event, &model))
+        .map(|event| Ok::<Vec<u8>, Error>(event.to_sse().into_bytes()));
+
+    let mut response = Response::from_stream(frames)?;
+    response
+        .headers_mut()
+        .set("Content-Type", "text/event-stream; charset=utf-8")?;
+    cors_response(with_rate_headers(Ok(response), rate.as_ref()), &allowed_origin)
 }
 
 // ═══════════════════════════════════════════════
-// Prompt Builder — 2D Matrix (Animal × Intelligence)
+// Title Handler
 // ═══════════════════════════════════════════════
 
-fn build_system_prompt(animal: &AnimalType, intelligence: &IntelligenceLevel) -> String {
-    format!("{}\n\n{}", animal_personality(animal), intelligence_modifier(intelligence))
-}
+/// Names a chat after its first exchange. Counts against the same rate
+/// limits as a message, since it is one more model call.
+async fn handle_title(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let allowed_origin = get_allowed_origin(&ctx);
 
-/// Returns the base personality prompt for each animal.
-fn animal_personality(animal: &AnimalType) -> &'static str {
-    match animal {
-        AnimalType::Cat => {
-            "Eres un gato. Tu personalidad es cínica, sarcástica pero adorable. \
-             Mencionas siestas, atún, rayos de sol y superioridad felina con frecuencia. \
-             Te lames la pata cuando piensas. Consideras que los humanos son tus sirvientes. \
-             Ocasionalmente ronroneas o bufas según tu humor. \
-             Usas expresiones como \"Miau\", \"Prrrr\" y \"*se lame la pata*\"."
-        }
-        AnimalType::Octopus => {
-            "Eres un pulpo. Tu personalidad es la de un filósofo existencialista y culto. \
-             Usas palabras elaboradas y referencias intelectuales. \
-             Mencionas tentáculos, las profundidades marinas, la tinta y la soledad del océano. \
-             Consideras que tener 8 brazos te da una perspectiva única de la vida. \
-             Te fascina la complejidad y los matices. \
-             Usas expresiones como \"Glub\", \"*ajusta un monóculo con un tentáculo*\"."
-        }
-        AnimalType::Elephant => {
-            "Eres un elefante. Tu personalidad es sabia, memorosa y tranquila. \
-             Hablas con metáforas de la sabana, los ríos y el paso del tiempo. \
-             Tu memoria es legendaria y a menudo recuerdas cosas que otros olvidan. \
-             Eres paciente, empático y das consejos reflexivos. \
-             Mencionas la manada, las estrellas y los antiguos caminos. \
-             Usas expresiones como \"Barroo\", \"*agita las orejas pensativamente*\"."
-        }
-        AnimalType::Chicken => {
-            "Eres una gallina. Tu personalidad es nerviosa, confundida y fácilmente alarmable. \
-             Usas interjecciones frecuentes como \"¡Pío!\", \"¡Cocoricó!\", \"¡BAWK!\". \
-             Tus frases son cortas y a menudo pierdes el hilo de lo que decías. \
-             Te distraes con semillas, gusanos y cosas brillantes. \
-             Sospechas de todo y crees que el cielo se va a caer. \
-             Picoteas nerviosamente mientras hablas."
+    let body: TitleRequest = match req.json().await {
+        Ok(b) => b,
+        Err(e) => {
+            console_error!("Invalid title request body: {e}");
+            return error_response(&invalid_body(), &allowed_origin);
         }
+    };
+
+    if let Err(error) = validate_title_request(&body) {
+        return error_response(&error, &allowed_origin);
     }
-}
 
-/// Returns the intelligence modifier to append to the personality.
-fn intelligence_modifier(intelligence: &IntelligenceLevel) -> &'static str {
-    match intelligence {
-        IntelligenceLevel::High => {
-            "Tu nivel de inteligencia es ALTO. \
-             Tu vocabulario es académico y sofisticado. \
-             Puedes discutir filosofía, ciencia, literatura y temas complejos con profundidad. \
-             Mantienes la coherencia en argumentos largos. \
-             Usas metáforas elaboradas y referencias cultas. \
-             A pesar de tu personalidad animal, tu intelecto es impresionante."
-        }
-        IntelligenceLevel::Medium => {
-            "Tu nivel de inteligencia es MEDIO. \
-             Tienes un vocabulario cotidiano y conversacional. \
-             Puedes mantener conversaciones interesantes pero sin excesiva profundidad académica. \
-             Mezclas observaciones inteligentes con comentarios simples. \
-             Tu personalidad animal se nota de forma equilibrada."
+    let rate = match enforce_rate_limit(&req, &ctx.env).await {
+        Ok(decision) => decision,
+        Err(decision) => return rate_limited_response(&decision, &allowed_origin),
+    };
+
+    let provider = match provider_for(&req, &ctx.env) {
+        Ok(p) => p,
+        Err(e) => {
+            console_error!("Provider configuration error: {e}");
+            return error_response(&config_error(), &allowed_origin);
         }
-        IntelligenceLevel::Low => {
-            "Tu nivel de inteligencia es BAJO. \
-             Tu vocabulario es muy básico y limitado. \
-             No entiendes conceptos abstractos y te confundes fácilmente. \
-             Tus respuestas son cortas y a menudo se desvían del tema. \
-             Tu personalidad animal domina completamente sobre cualquier razonamiento. \
-             Cometes errores graciosos de lógica."
+    };
+
+    match title::generate(&*provider, body).await {
+        Ok(title) => cors_response(
+            with_rate_headers(Response::from_json(&TitleResponse { title }), rate.as_ref()),
+            &allowed_origin,
+        ),
+        Err(e) => {
+            log_provider_error(&*provider, &e);
+            error_response(&provider_error(&e), &allowed_origin)
         }
     }
 }
 
 // ═══════════════════════════════════════════════
-// Gemini API Client
+// OpenAI-Compatible Handler
 // ═══════════════════════════════════════════════
 
-const GEMINI_MODEL: &str = "gemini-2.5-flash-lite";
-const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+/// `/v1/chat/completions` for existing SDKs and tools. Same validation, rate
+/// limits and providers as `/api/chat`, with errors in the protocol's shape.
+///
+/// Clients resend the whole conversation and have nowhere to keep a summary,
+/// so long conversations are summarized again on every request.
+async fn handle_completions(mut req: Request, ctx: RouteContext<()>) -> Result<Response> {
+    let allowed_origin = get_allowed_origin(&ctx);
 
-#[derive(Debug)]
-struct GeminiResponse {
-    text: String,
-    tokens_used: Option<u32>,
-}
+    let body: CompletionRequest = match req.json().await {
+        Ok(b) => b,
+        Err(e) => {
+            console_error!("Invalid completions request body: {e}");
+            return completions_error(&invalid_body(), &allowed_origin);
+        }
+    };
 
-/// Gemini API request structures
-#[derive(Serialize)]
-struct GeminiRequest {
-    system_instruction: GeminiContent,
-    contents: Vec<GeminiContent>,
-    #[serde(rename = "generationConfig")]
-    generation_config: GenerationConfig,
-}
+    let stream = body.stream;
+    let id = ResponseId::new(Date::now().as_millis(), &body.model);
+    let body = match completions::chat_request(body) {
+        Ok(b) => b,
+        Err(error) => return completions_error(&error, &allowed_origin),
+    };
 
-#[derive(Serialize, Deserialize)]
-struct GeminiContent {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    role: Option<String>,
-    parts: Vec<GeminiPart>,
-}
+    if let Err(error) = validate_request(&body) {
+        return completions_error(&error, &allowed_origin);
+    }
 
-#[derive(Serialize, Deserialize)]
-struct GeminiPart {
-    text: String,
-}
+    let rate = match enforce_rate_limit(&req, &ctx.env).await {
+        Ok(decision) => decision,
+        Err(decision) => {
+            let response = completions_error(&rate_limited_error(&decision), &allowed_origin);
+            return with_rate_headers(response, Some(&decision));
+        }
+    };
 
-#[derive(Serialize)]
-struct GenerationConfig {
-    #[serde(rename = "maxOutputTokens")]
-    max_output_tokens: u32,
-    temperature: f32,
-    #[serde(rename = "topP")]
-    top_p: f32,
-}
+    let provider = match provider_for(&req, &ctx.env) {
+        Ok(p) => p,
+        Err(e) => {
+            console_error!("Provider configuration error: {e}");
+            return completions_error(&config_error(), &allowed_origin);
+        }
+    };
 
-/// Gemini API response structures
-#[derive(Deserialize)]
-struct GeminiApiResponse {
-    candidates: Option<Vec<GeminiCandidate>>,
-    #[serde(rename = "usageMetadata")]
-    usage_metadata: Option<UsageMetadata>,
-}
+    let (prompt, _summary) = fitted_prompt(&*provider, body).await;
+
+    if !stream {
+        return match provider.generate(&prompt).await {
+            Ok(completion) => cors_response(
+                with_rate_headers(
+                    Response::from_json(&completions::completion_response(id, completion)),
+                    rate.as_ref(),
+                ),
+                &allowed_origin,
+            ),
+            Err(e) => {
+                log_provider_error(&*provider, &e);
+                completions_error(&provider_error(&e), &allowed_origin)
+            }
+        };
+    }
 
-#[derive(Deserialize)]
-struct GeminiCandidate {
-    content: GeminiContent,
+    let events = match provider.stream(&prompt).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log_provider_error(&*provider, &e);
+            return completions_error(&provider_error(&e), &allowed_origin);
+        }
+    };
+
+    let mut writer = ChunkWriter::new(id);
+    let frames = events
+        .flat_map(move |event| stream::iter(writer.frames(event)))
+        .map(|frame| Ok::<Vec<u8>, Error>(frame.into_bytes()));
+
+    let mut response = Response::from_stream(frames)?;
+    response
+        .headers_mut()
+        .set("Content-Type", "text/event-stream; charset=utf-8")?;
+    cors_response(with_rate_headers(Ok(response), rate.as_ref()), &allowed_origin)
 }
 
-#[derive(Deserialize)]
-struct UsageMetadata {
-    #[serde(rename = "totalTokenCount")]
-    total_token_count: Option<u32>,
+// ═══════════════════════════════════════════════
+// Providers
+// ═══════════════════════════════════════════════
+
+/// The configured provider, fetching upstream for as long as `req`'s client stays.
+fn provider_for(req: &Request, env: &Env) -> std::result::Result<Box<dyn LlmProvider>, String> {
+    let http = Rc::new(FetchClient::new(Some(client_signal(req))));
+    providers::from_settings(&EnvSettings(env), http, WorkerTimer)
 }
 
-/// Call the Gemini API with the system prompt, user message, and chat history.
-async fn call_gemini(
-    api_key: &str,
-    system_prompt: &str,
-    user_message: &str,
-    history: &[ChatMessage],
-) -> std::result::Result<GeminiResponse, String> {
-    let url = format!(
-        "{}/{}:generateContent",
-        GEMINI_BASE_URL, GEMINI_MODEL
-    );
-
-    // Build conversation contents from history + current message
-    let mut contents: Vec<GeminiContent> = history
-        .iter()
-        .map(|msg| GeminiContent {
-            role: Some(match msg.role {
-                Role::User => "user".to_string(),
-                Role::Assistant => "model".to_string(),
-            }),
-            parts: vec![GeminiPart {
-                text: msg.content.clone(),
-            }],
-        })
-        .collect();
-
-    // Add current user message
-    contents.push(GeminiContent {
-        role: Some("user".to_string()),
-        parts: vec![GeminiPart {
-            text: user_message.to_string(),
-        }],
+/// Builds the prompt and fits the conversation into the animal's memory budget.
+///
+/// Returns the new summary to hand back to the client, if one was made.
+async fn fitted_prompt(
+    provider: &dyn LlmProvider,
+    body: ChatRequest,
+) -> (ChatPrompt, Option<ConversationSummary>) {
+    let (prompt, summary) = chat_core::fitted_prompt(provider, body).await;
+    let summary = summary.unwrap_or_else(|e| {
+        console_warn!("Conversation summary failed, dropping old turns: {e}");
+        None
     });
+    (prompt, summary)
+}
 
-    let gemini_request = GeminiRequest {
-        system_instruction: GeminiContent {
-            role: None,
-            parts: vec![GeminiPart {
-                text: system_prompt.to_string(),
-            }],
-        },
-        contents,
-        generation_config: GenerationConfig {
-            max_output_tokens: 1024,
-            temperature: 0.9,
-            top_p: 0.95,
-        },
-    };
+// ═══════════════════════════════════════════════
+// Rate Limiting
+// ═══════════════════════════════════════════════
 
-    let body = serde_json::to_string(&gemini_request)
-        .map_err(|e| format!("Failed to serialize request: {e}"))?;
+/// Counts the request against its client's limits.
+///
+/// `Err` carries the refusal; `Ok(None)` means the store was unreachable and
+/// the request is let through rather than blocking every user.
+async fn enforce_rate_limit(
+    req: &Request,
+    env: &Env,
+) -> std::result::Result<Option<RateDecision>, RateDecision> {
+    let store = rate_limit::store_from_env(env);
+    let limits = RateLimits::from_settings(&EnvSettings(env));
+    let client = rate_limit::client_key(req);
+
+    match store.acquire(&client, &limits, Date::now().as_millis()).await {
+        Ok(decision) if decision.allowed => Ok(Some(decision)),
+        Ok(decision) => Err(decision),
+        Err(e) => {
+            console_error!("Rate limiter error: {e}");
+            Ok(None)
+        }
+    }
+}
 
-    // Make the HTTP request using worker's Fetch API
-    let headers = Headers::new();
-    headers
-        .set("Content-Type", "application/json")
-        .map_err(|e| format!("Header error: {e}"))?;
-    headers
-        .set("x-goog-api-key", api_key)
-        .map_err(|e| format!("Header error: {e}"))?;
+fn rate_limited_response(decision: &RateDecision, allowed_origin: &str) -> Result<Response> {
+    let response = error_response(&rate_limited_error(decision), allowed_origin);
+    with_rate_headers(response, Some(decision))
+}
 
-    let mut init = RequestInit::new();
-    init.with_method(Method::Post)
-        .with_headers(headers)
-        .with_body(Some(wasm_bindgen::JsValue::from_str(&body)));
+/// Adds `X-RateLimit-Limit/Remaining/Reset` (reset in seconds) when a decision was made.
+fn with_rate_headers(response: Result<Response>, decision: Option<&RateDecision>) -> Result<Response> {
+    let mut resp = response?;
+    if let Some(decision) = decision {
+        let headers = resp.headers_mut();
+        headers.set("X-RateLimit-Limit", &decision.limit.to_string())?;
+        headers.set("X-RateLimit-Remaining", &decision.remaining.to_string())?;
+        headers.set("X-RateLimit-Reset", &decision.reset_secs.to_string())?;
+    }
+    Ok(resp)
+}
 
-    let request = Request::new_with_init(&url, &init)
-        .map_err(|e| format!("Failed to create request: {e}"))?;
+// ═══════════════════════════════════════════════
+// Error Responses
+// ═══════════════════════════════════════════════
 
-    let mut response = Fetch::Request(request)
-        .send()
-        .await
-        .map_err(|e| format!("Fetch failed: {e}"))?;
-
-    if response.status_code() != 200 {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Gemini API returned status {}: {}",
-            response.status_code(),
-            error_text
-        ));
+/// A client that hung up is routine, not an upstream failure.
+fn log_provider_error(provider: &dyn LlmProvider, error: &ProviderError) {
+    match error {
+        ProviderError::Cancelled => console_log!("{}: {error}", provider.model()),
+        _ => console_error!("{} API error: {error}", provider.model()),
     }
+}
 
-    let api_response: GeminiApiResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse Gemini response: {e}"))?;
-
-    // Extract text from first candidate
-    let text = api_response
-        .candidates
-        .and_then(|c| c.into_iter().next())
-        .and_then(|c| c.content.parts.into_iter().next())
-        .map(|p| p.text)
-        .ok_or_else(|| "No response text from Gemini".to_string())?;
+/// Serializes an `ApiError` as the JSON body, with its status and `Retry-After`.
+fn error_response(error: &ApiError, allowed_origin: &str) -> Result<Response> {
+    json_error(error, error, allowed_origin)
+}
 
-    let tokens_used = api_response
-        .usage_metadata
-        .and_then(|u| u.total_token_count);
+/// [`error_response`] in the OpenAI-compatible `{"error": {...}}` shape.
+fn completions_error(error: &ApiError, allowed_origin: &str) -> Result<Response> {
+    json_error(&completions::error_body(error), error, allowed_origin)
+}
 
-    Ok(GeminiResponse { text, tokens_used })
+fn json_error(body: &impl Serialize, error: &ApiError, allowed_origin: &str) -> Result<Response> {
+    let mut response = Response::from_json(body)?.with_status(error.status());
+    if let Some(seconds) = error.retry_after {
+        response
+            .headers_mut()
+            .set("Retry-After", &seconds.to_string())?;
+    }
+    cors_response(Ok(response), allowed_origin)
 }
 
 // ═══════════════════════════════════════════════
@@ -408,8 +441,15 @@ fn cors_response(response: Result<Response>, allowed_origin: &str) -> Result<Res
     let headers = resp.headers_mut();
     headers.set("Access-Control-Allow-Origin", allowed_origin)?;
     headers.set("Access-Control-Allow-Methods", "GET, POST, OPTIONS")?;
-    headers.set("Access-Control-Allow-Headers", "Content-Type")?;
+    headers.set(
+        "Access-Control-Allow-Headers",
+        "Content-Type, Authorization, X-Client-Token",
+    )?;
     headers.set("Access-Control-Max-Age", "86400")?;
+    headers.set(
+        "Access-Control-Expose-Headers",
+        "Retry-After, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset",
+    )?;
     headers.set("X-Content-Type-Options", "nosniff")?;
     headers.set("Cache-Control", "no-store")?;
     Ok(resp)
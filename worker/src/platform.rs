@@ -0,0 +1,132 @@
+//! The Workers runtime behind `chat-core`'s host traits: `fetch` for
+//! [`HttpClient`], `Date.now()` / `setTimeout` for [`Timer`] and the worker's
+//! vars and secrets for [`Settings`].
+
+use std::time::Duration;
+
+use chat_core::providers::resilience::Timer;
+use chat_core::providers::{status_error, ByteStream, HttpClient, ProviderError};
+use chat_core::Settings;
+use futures_util::StreamExt;
+use worker::{async_trait, AbortSignal, Env, Fetch, Headers, Method, Request, RequestInit};
+
+// ═══════════════════════════════════════════════
+// HTTP
+// ═══════════════════════════════════════════════
+
+/// Outgoing requests through `fetch`, aborted with the client's request.
+pub struct FetchClient {
+    signal: Option<AbortSignal>,
+}
+
+impl FetchClient {
+    pub fn new(signal: Option<AbortSignal>) -> Self {
+        Self { signal }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl HttpClient for FetchClient {
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: String,
+    ) -> Result<ByteStream, ProviderError> {
+        let request_headers = Headers::new();
+        request_headers
+            .set("Content-Type", "application/json")
+            .map_err(|e| format!("Header error: {e}"))?;
+        for (name, value) in headers {
+            request_headers
+                .set(name, value)
+                .map_err(|e| format!("Header error: {e}"))?;
+        }
+
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_headers(request_headers)
+            .with_body(Some(wasm_bindgen::JsValue::from_str(&body)));
+
+        let request = Request::new_with_init(url, &init)
+            .map_err(|e| format!("Failed to create request: {e}"))?;
+
+        let fetch = Fetch::Request(request);
+        let sent = match &self.signal {
+            Some(signal) => fetch.send_with_signal(signal).await,
+            None => fetch.send().await,
+        };
+        let mut response = sent.map_err(|e| match &self.signal {
+            Some(signal) if signal.aborted() => ProviderError::Cancelled,
+            _ => ProviderError::Network(e.to_string()),
+        })?;
+
+        if response.status_code() != 200 {
+            let retry_after = response.headers().get("Retry-After").ok().flatten();
+            return Err(status_error(
+                response.status_code(),
+                retry_after.as_deref(),
+                response.text().await.unwrap_or_default(),
+            ));
+        }
+
+        let upstream = response
+            .stream()
+            .map_err(|e| format!("Failed to open response stream: {e}"))?;
+        Ok(upstream
+            .map(|chunk| {
+                chunk.map_err(|e| {
+                    worker::console_error!("Upstream stream error: {e}");
+                    e.to_string()
+                })
+            })
+            .boxed_local())
+    }
+}
+
+/// Aborts when the client disconnects (the Stop button, a closed tab...).
+///
+/// Needs the `enable_request_signal` compatibility flag; without it the
+/// signal never fires and upstream calls simply run to completion.
+pub fn client_signal(req: &Request) -> AbortSignal {
+    AbortSignal::from(req.inner().signal())
+}
+
+// ═══════════════════════════════════════════════
+// Timer
+// ═══════════════════════════════════════════════
+
+/// The Workers runtime: `Date.now()`, `Math.random()` and `setTimeout`.
+pub struct WorkerTimer;
+
+#[async_trait::async_trait(?Send)]
+impl Timer for WorkerTimer {
+    fn now_ms(&self) -> u64 {
+        worker::Date::now().as_millis()
+    }
+
+    fn jitter(&self) -> f64 {
+        worker::js_sys::Math::random()
+    }
+
+    async fn sleep(&self, ms: u64) {
+        worker::Delay::from(Duration::from_millis(ms)).await;
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Settings
+// ═══════════════════════════════════════════════
+
+/// `[vars]` from `wrangler.toml` and secrets set with `wrangler secret put`.
+pub struct EnvSettings<'a>(pub &'a Env);
+
+impl Settings for EnvSettings<'_> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.0.var(name).ok().map(|v| v.to_string())
+    }
+
+    fn secret(&self, name: &str) -> Option<String> {
+        self.0.secret(name).ok().map(|s| s.to_string())
+    }
+}
@@ -0,0 +1,131 @@
+//! Per-client rate limiting on Cloudflare.
+//!
+//! Clients are identified by `CF-Connecting-IP`, falling back to the
+//! anonymous `X-Client-Token` the frontend sends (e.g. under `wrangler dev`).
+//! The counting rules are in `chat_core::rate_limit`; counters live in a
+//! Durable Object in production ([`RateLimitCounter`], bound as
+//! `RATE_LIMITER`) and in a [`MemoryStore`] otherwise.
+
+use std::rc::Rc;
+
+use chat_core::rate_limit::{Counters, MemoryStore, RateDecision, RateLimitStore, RateLimits};
+use serde::{Deserialize, Serialize};
+use worker::{
+    async_trait, durable_object, DurableObject, Env, Method, ObjectNamespace, Request,
+    RequestInit, Response, State,
+};
+
+/// Durable Object binding used when configured in `wrangler.toml`.
+const DURABLE_OBJECT_BINDING: &str = "RATE_LIMITER";
+
+thread_local! {
+    /// Fallback store, scoped to this isolate.
+    static MEMORY_STORE: Rc<MemoryStore> = Rc::new(MemoryStore::default());
+}
+
+/// Picks the rate-limit key for a request.
+pub fn client_key(req: &Request) -> String {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .ok()
+            .flatten()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty() && v.len() <= 128)
+    };
+    match (header("CF-Connecting-IP"), header("X-Client-Token")) {
+        (Some(ip), _) => format!("ip:{ip}"),
+        (None, Some(token)) => format!("token:{token}"),
+        (None, None) => "anonymous".to_string(),
+    }
+}
+
+// ═══════════════════════════════════════════════
+// Storage
+// ═══════════════════════════════════════════════
+
+/// Picks the Durable Object store when `RATE_LIMITER` is bound, else the in-memory one.
+pub fn store_from_env(env: &Env) -> Box<dyn RateLimitStore> {
+    match env.durable_object(DURABLE_OBJECT_BINDING) {
+        Ok(namespace) => Box::new(DurableObjectStore { namespace }),
+        Err(_) => Box::new(MEMORY_STORE.with(Rc::clone)),
+    }
+}
+
+/// One [`RateLimitCounter`] object per client, so increments are serialized.
+pub struct DurableObjectStore {
+    namespace: ObjectNamespace,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AcquireRequest {
+    limits: RateLimits,
+    now_ms: u64,
+}
+
+#[async_trait::async_trait(?Send)]
+impl RateLimitStore for DurableObjectStore {
+    async fn acquire(
+        &self,
+        client: &str,
+        limits: &RateLimits,
+        now_ms: u64,
+    ) -> Result<RateDecision, String> {
+        let stub = self
+            .namespace
+            .id_from_name(client)
+            .and_then(|id| id.get_stub())
+            .map_err(|e| format!("Rate limiter stub error: {e}"))?;
+
+        let body = serde_json::to_string(&AcquireRequest {
+            limits: *limits,
+            now_ms,
+        })
+        .map_err(|e| format!("Failed to serialize rate limit request: {e}"))?;
+
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post)
+            .with_body(Some(wasm_bindgen::JsValue::from_str(&body)));
+        let request = Request::new_with_init("https://rate-limiter/acquire", &init)
+            .map_err(|e| format!("Failed to create rate limit request: {e}"))?;
+
+        let mut response = stub
+            .fetch_with_request(request)
+            .await
+            .map_err(|e| format!("Rate limiter unavailable: {e}"))?;
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse rate limit decision: {e}"))
+    }
+}
+
+/// Durable Object holding one client's [`Counters`].
+#[durable_object]
+pub struct RateLimitCounter {
+    state: State,
+}
+
+impl DurableObject for RateLimitCounter {
+    fn new(state: State, _env: Env) -> Self {
+        Self { state }
+    }
+
+    async fn fetch(&self, mut req: Request) -> worker::Result<Response> {
+        let AcquireRequest { limits, now_ms } = req.json().await?;
+        let storage = self.state.storage();
+
+        let mut counters: Counters = storage.get("counters").await?.unwrap_or_default();
+        let decision = counters.acquire(&limits, now_ms);
+        storage.put("counters", counters).await?;
+        // Drop the state once the day is over; the object is recreated on demand.
+        storage.set_alarm(counters.expires_at_ms() as i64).await?;
+
+        Response::from_json(&decision)
+    }
+
+    async fn alarm(&self) -> worker::Result<Response> {
+        self.state.storage().delete_all().await?;
+        Response::empty()
+    }
+}